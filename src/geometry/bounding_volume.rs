@@ -1,7 +1,7 @@
 use std::mem::swap;
 
 use crate::geometry::ray::Ray;
-use crate::geometry::vector::Point;
+use crate::geometry::vector::{Point, Vector3};
 
 #[derive(Clone)]
 pub struct AxisAlignedBoundingBox {
@@ -11,21 +11,41 @@ pub struct AxisAlignedBoundingBox {
 
 impl AxisAlignedBoundingBox {
     pub fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> bool {
+        let inv_direction = Vector3 { x: 1.0 / ray.direction.x, y: 1.0 / ray.direction.y, z: 1.0 / ray.direction.z };
+        return self.hit_precomputed(ray.origin, inv_direction, t_min, t_max);
+    }
+
+    // Slab test with the reciprocal ray direction computed once per ray rather than once per box.
+    pub fn hit_precomputed(&self, origin: Point, inv_direction: Vector3, t_min: f32, t_max: f32) -> bool {
+        return self.hit_range(origin, inv_direction, t_min, t_max).is_some();
+    }
+
+    // The part of [t_min, t_max] the ray spends inside the box.
+    pub fn hit_range(&self, origin: Point, inv_direction: Vector3, t_min: f32, t_max: f32) -> Option<(f32, f32)> {
         let mut t_min = t_min;
         let mut t_max = t_max;
         for a in 0..3 {
-            let mut t0 = (self.minimum[a] - ray.origin[a]) / ray.direction[a];
-            let mut t1 = (self.maximum[a] - ray.origin[a]) / ray.direction[a];
+            let mut t0 = (self.minimum[a] - origin[a]) * inv_direction[a];
+            let mut t1 = (self.maximum[a] - origin[a]) * inv_direction[a];
 
-            if ray.direction[a] < 0.0 {
+            if inv_direction[a] < 0.0 {
                 swap(&mut t0, &mut t1);
             }
 
             if t0 > t_min { t_min = t0; }
             if t1 < t_max { t_max = t1; }
-            if t_max <= t_min { return false; }
+            if t_max <= t_min { return Option::None; }
         }
-        return true;
+        return Option::from((t_min, t_max));
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let d = self.maximum - self.minimum;
+        return 2.0 * (d.x * d.y + d.y * d.z + d.z * d.x);
+    }
+
+    pub fn centroid(&self) -> Point {
+        return 0.5 * (self.minimum + self.maximum);
     }
 
     pub fn surrounding_box(box0: Self, box1: Self) -> Self {
@@ -0,0 +1,226 @@
+use std::ops::Mul;
+
+use crate::geometry::bounding_volume::AxisAlignedBoundingBox;
+use crate::geometry::quaternion::Quaternion;
+use crate::geometry::vector::{Point, Vector3};
+use crate::utils::{degrees_to_radians, INF_F32};
+
+// Row-major 4x4 affine matrix, applied to column vectors.
+#[derive(Clone, Copy)]
+pub struct Matrix4 {
+    pub m: [[f32; 4]; 4],
+}
+
+impl Matrix4 {
+    pub const IDENTITY: Matrix4 = Matrix4 {
+        m: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ],
+    };
+
+    pub fn from_column_major(columns: [[f32; 4]; 4]) -> Self {
+        return Self { m: columns }.transpose();
+    }
+
+    pub fn translation(offset: Vector3) -> Self {
+        let mut result = Self::IDENTITY;
+        result.m[0][3] = offset.x;
+        result.m[1][3] = offset.y;
+        result.m[2][3] = offset.z;
+        return result;
+    }
+
+    pub fn scale(factors: Vector3) -> Self {
+        let mut result = Self::IDENTITY;
+        result.m[0][0] = factors.x;
+        result.m[1][1] = factors.y;
+        result.m[2][2] = factors.z;
+        return result;
+    }
+
+    // Rotation by `angle` degrees about `axis`, counter-clockwise when looking down the axis.
+    pub fn rotation(axis: Vector3, angle: f32) -> Self {
+        let a = axis.direction();
+        let radians = degrees_to_radians(angle);
+        let sin_theta = radians.sin();
+        let cos_theta = radians.cos();
+        let k = 1.0 - cos_theta;
+
+        return Self {
+            m: [
+                [a.x * a.x * k + cos_theta, a.x * a.y * k - a.z * sin_theta, a.x * a.z * k + a.y * sin_theta, 0.0],
+                [a.y * a.x * k + a.z * sin_theta, a.y * a.y * k + cos_theta, a.y * a.z * k - a.x * sin_theta, 0.0],
+                [a.z * a.x * k - a.y * sin_theta, a.z * a.y * k + a.x * sin_theta, a.z * a.z * k + cos_theta, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        };
+    }
+
+    pub fn from_quaternion(q: Quaternion) -> Self {
+        let (x, y, z, w) = (q.x, q.y, q.z, q.w);
+        return Self {
+            m: [
+                [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - z * w), 2.0 * (x * z + y * w), 0.0],
+                [2.0 * (x * y + z * w), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - x * w), 0.0],
+                [2.0 * (x * z - y * w), 2.0 * (y * z + x * w), 1.0 - 2.0 * (x * x + y * y), 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        };
+    }
+
+    // Each factor moves one axis in proportion to another, e.g. `xy` adds `xy * y` to x.
+    pub fn shear(xy: f32, xz: f32, yx: f32, yz: f32, zx: f32, zy: f32) -> Self {
+        let mut result = Self::IDENTITY;
+        result.m[0][1] = xy;
+        result.m[0][2] = xz;
+        result.m[1][0] = yx;
+        result.m[1][2] = yz;
+        result.m[2][0] = zx;
+        result.m[2][1] = zy;
+        return result;
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut result = Self::IDENTITY;
+        for r in 0..4 {
+            for c in 0..4 {
+                result.m[r][c] = self.m[c][r];
+            }
+        }
+        return result;
+    }
+
+    // Gauss-Jordan elimination with partial pivoting.
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.m;
+        let mut inv = Self::IDENTITY.m;
+
+        for col in 0..4 {
+            let mut pivot = col;
+            for row in (col + 1)..4 {
+                if a[row][col].abs() > a[pivot][col].abs() {
+                    pivot = row;
+                }
+            }
+            if a[pivot][col].abs() < 1e-12 {
+                return Option::None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let scale = 1.0 / a[col][col];
+            for c in 0..4 {
+                a[col][c] *= scale;
+                inv[col][c] *= scale;
+            }
+
+            for row in 0..4 {
+                if row == col {
+                    continue;
+                }
+                let factor = a[row][col];
+                for c in 0..4 {
+                    a[row][c] -= factor * a[col][c];
+                    inv[row][c] -= factor * inv[col][c];
+                }
+            }
+        }
+
+        return Option::from(Self { m: inv });
+    }
+
+    // Inverse transpose, for carrying surface normals through the transform.
+    pub fn normal_matrix(&self) -> Option<Self> {
+        return self.inverse().map(|inv| inv.transpose());
+    }
+
+    pub fn determinant3(&self) -> f32 {
+        let m = &self.m;
+        return m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+    }
+
+    pub fn transform_point(&self, p: Point) -> Point {
+        return self.transform_vector(p) + Point { x: self.m[0][3], y: self.m[1][3], z: self.m[2][3] };
+    }
+
+    pub fn transform_vector(&self, v: Vector3) -> Vector3 {
+        let m = &self.m;
+        return Vector3 {
+            x: m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            y: m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            z: m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        };
+    }
+
+    pub fn transform_box(&self, bound_box: &AxisAlignedBoundingBox) -> AxisAlignedBoundingBox {
+        let mut min = Point { x: INF_F32, y: INF_F32, z: INF_F32 };
+        let mut max = Point { x: -INF_F32, y: -INF_F32, z: -INF_F32 };
+
+        for i in 0..2 {
+            for j in 0..2 {
+                for k in 0..2 {
+                    let corner = Point {
+                        x: if i == 0 { bound_box.minimum.x } else { bound_box.maximum.x },
+                        y: if j == 0 { bound_box.minimum.y } else { bound_box.maximum.y },
+                        z: if k == 0 { bound_box.minimum.z } else { bound_box.maximum.z },
+                    };
+                    let tester = self.transform_point(corner);
+                    for c in 0..3 {
+                        min[c] = min[c].min(tester[c]);
+                        max[c] = max[c].max(tester[c]);
+                    }
+                }
+            }
+        }
+
+        return AxisAlignedBoundingBox { minimum: min, maximum: max };
+    }
+}
+
+impl Mul for Matrix4 {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut result = [[0.0; 4]; 4];
+        for r in 0..4 {
+            for c in 0..4 {
+                for k in 0..4 {
+                    result[r][c] += self.m[r][k] * rhs.m[k][c];
+                }
+            }
+        }
+        return Self { m: result };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_identity(m: &Matrix4) {
+        for r in 0..4 {
+            for c in 0..4 {
+                let expected = if r == c { 1.0 } else { 0.0 };
+                assert!((m.m[r][c] - expected).abs() < 1e-5, "[{}][{}] = {}", r, c, m.m[r][c]);
+            }
+        }
+    }
+
+    #[test]
+    fn inverse_undoes_the_matrix() {
+        let m = Matrix4::translation(Vector3 { x: 3.0, y: -2.0, z: 0.5 })
+            * Matrix4::rotation(Vector3 { x: 1.0, y: 2.0, z: -1.0 }, 35.0)
+            * Matrix4::shear(0.3, 0.0, 0.1, 0.0, 0.0, -0.4)
+            * Matrix4::scale(Vector3 { x: 2.0, y: 0.5, z: 1.5 });
+        let inverse = m.inverse().unwrap();
+        assert_identity(&(inverse * m));
+        assert_identity(&(m * inverse));
+
+        assert!(Matrix4::scale(Vector3 { x: 1.0, y: 0.0, z: 1.0 }).inverse().is_none());
+    }
+}
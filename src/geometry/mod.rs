@@ -4,3 +4,6 @@ pub mod ray;
 pub mod bounding_volume;
 pub mod onb;
 pub mod pdf;
+pub mod matrix;
+pub mod quaternion;
+pub mod spectrum;
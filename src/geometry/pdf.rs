@@ -77,3 +77,77 @@ impl PDF for MixturePDF {
         };
     }
 }
+
+// Mixture with its own weight for each pdf, which must add up to 1.
+pub struct WeightedMixturePDF {
+    pub ptr: Vec<Arc<dyn PDF>>,
+    pub weights: Vec<f32>,
+}
+
+impl PDF for WeightedMixturePDF {
+    fn value(&self, direction: Vector3) -> f32 {
+        return self.ptr.iter().zip(self.weights.iter()).map(|(pdf, weight)| weight * pdf.value(direction)).sum();
+    }
+
+    fn generate(&self) -> Vector3 {
+        let mut u = random_f32();
+        for (pdf, weight) in self.ptr.iter().zip(self.weights.iter()) {
+            if u < *weight {
+                return pdf.generate();
+            }
+            u -= weight;
+        }
+        // Rounding can leave `u` just past the last weight. An empty mixture has no density anywhere, so any
+        // direction will do.
+        return match self.ptr.last() {
+            Some(pdf) => pdf.generate(),
+            None => Vector3::random_unit_vector(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two cosine lobes about perpendicular normals, so their hemispheres overlap over half of each.
+    fn mixture() -> WeightedMixturePDF {
+        return WeightedMixturePDF {
+            ptr: vec![
+                Arc::new(CosinePDF::new(Vector3 { x: 0.0, y: 0.0, z: 1.0 })),
+                Arc::new(CosinePDF::new(Vector3 { x: 1.0, y: 0.0, z: 0.0 })),
+            ],
+            weights: vec![0.3, 0.7],
+        };
+    }
+
+    #[test]
+    fn weighted_mixture_integrates_to_one() {
+        let pdf = mixture();
+        let samples = 200_000;
+        let integral: f32 = (0..samples).map(|_| pdf.value(Vector3::random_unit_vector())).sum::<f32>() * 4.0 * PI / samples as f32;
+        assert!((integral - 1.0).abs() < 0.02, "integral {}", integral);
+    }
+
+    // Generated directions land in a cap as often as the pdf integrated over the cap says.
+    #[test]
+    fn weighted_mixture_generates_by_its_value() {
+        let pdf = mixture();
+        let samples = 200_000;
+        let in_cap = |direction: Vector3| direction.z > 0.5;
+        let generated = (0..samples).filter(|_| in_cap(pdf.generate())).count() as f32 / samples as f32;
+        let integral: f32 = (0..samples)
+            .map(|_| Vector3::random_unit_vector())
+            .filter(|direction| in_cap(*direction))
+            .map(|direction| pdf.value(direction))
+            .sum::<f32>() * 4.0 * PI / samples as f32;
+        assert!((generated - integral).abs() < 0.01, "generated {} vs integral {}", generated, integral);
+    }
+
+    #[test]
+    fn empty_mixture_still_generates() {
+        let pdf = WeightedMixturePDF { ptr: vec![], weights: vec![] };
+        assert!((pdf.generate().length() - 1.0).abs() < 1e-4);
+        assert_eq!(pdf.value(Vector3 { x: 0.0, y: 0.0, z: 1.0 }), 0.0);
+    }
+}
@@ -0,0 +1,60 @@
+use crate::geometry::vector::Vector3;
+use crate::utils::degrees_to_radians;
+
+#[derive(Clone, Copy)]
+pub struct Quaternion {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Quaternion {
+    pub const IDENTITY: Quaternion = Quaternion { x: 0.0, y: 0.0, z: 0.0, w: 1.0 };
+
+    pub fn from_axis_angle(axis: Vector3, angle: f32) -> Self {
+        let a = axis.direction();
+        let half = 0.5 * degrees_to_radians(angle);
+        let sin_half = half.sin();
+        return Self { x: sin_half * a.x, y: sin_half * a.y, z: sin_half * a.z, w: half.cos() };
+    }
+
+    pub fn dot(&self, rhs: Self) -> f32 {
+        return self.x * rhs.x + self.y * rhs.y + self.z * rhs.z + self.w * rhs.w;
+    }
+
+    pub fn normalized(self) -> Self {
+        let inv_len = 1.0 / self.dot(self).sqrt();
+        return Self { x: self.x * inv_len, y: self.y * inv_len, z: self.z * inv_len, w: self.w * inv_len };
+    }
+
+    // Angle in radians of the rotation taking `self` to `other`.
+    pub fn angle_to(&self, other: Self) -> f32 {
+        return 2.0 * self.dot(other).abs().min(1.0).acos();
+    }
+
+    // Spherical interpolation along the shorter arc.
+    pub fn slerp(self, other: Self, s: f32) -> Self {
+        let mut cos_theta = self.dot(other);
+        let mut end = other;
+        if cos_theta < 0.0 {
+            cos_theta = -cos_theta;
+            end = Self { x: -other.x, y: -other.y, z: -other.z, w: -other.w };
+        }
+
+        let (w0, w1) = if cos_theta > 0.9995 {
+            (1.0 - s, s)
+        } else {
+            let theta = cos_theta.acos();
+            let sin_theta = theta.sin();
+            (((1.0 - s) * theta).sin() / sin_theta, (s * theta).sin() / sin_theta)
+        };
+
+        return Self {
+            x: w0 * self.x + w1 * end.x,
+            y: w0 * self.y + w1 * end.y,
+            z: w0 * self.z + w1 * end.z,
+            w: w0 * self.w + w1 * end.w,
+        }.normalized();
+    }
+}
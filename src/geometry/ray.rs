@@ -1,3 +1,5 @@
+use super::color::Color;
+use super::spectrum::Wavelengths;
 use super::vector::{Point, Vector3};
 
 #[derive(Clone, Copy)]
@@ -5,10 +7,30 @@ pub struct Ray {
     pub origin: Point,
     pub direction: Vector3,
     pub time: f32,
+    // Wavelengths the path is traced at in spectral mode, None when rendering in RGB.
+    pub wavelengths: Option<Wavelengths>,
+    // Absorption coefficient per unit length of the medium the ray travels through, such as tinted glass it has
+    // refracted into. None outside absorbing media.
+    pub absorption: Option<Color>,
 }
 
 impl Ray {
     pub fn at_distance(&self, distance: f32) -> Point {
         self.origin + distance * self.direction
     }
+
+    // Fraction of light left after the ray has gone `distance` along its direction, by Beer-Lambert's law.
+    pub fn transmittance(&self, distance: f32) -> Color {
+        match self.absorption {
+            Some(absorption) => {
+                let length = distance * self.direction.length();
+                Color {
+                    r: (-absorption.r * length).exp(),
+                    g: (-absorption.g * length).exp(),
+                    b: (-absorption.b * length).exp(),
+                }
+            }
+            None => Color::WHITE,
+        }
+    }
 }
\ No newline at end of file
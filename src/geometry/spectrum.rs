@@ -0,0 +1,193 @@
+use crate::geometry::color::Color;
+use crate::utils::random_f32;
+
+// Visible range sampled in spectral mode, in nanometres.
+pub const LAMBDA_MIN: f32 = 360.0;
+pub const LAMBDA_MAX: f32 = 830.0;
+
+// A path carries one wavelength per colour channel, so spectral radiance fits in a `Color` with r, g and b
+// holding the values at the first, second and third wavelength.
+const SPECTRUM_SAMPLES: usize = 3;
+
+// Integrals of the colour matching functions below over the sampled range, so that an equal-energy spectrum
+// has XYZ (1, 1, 1).
+const CMF_INTEGRAL: [f32; 3] = [106.765_82, 106.922_07, 106.875];
+
+// XYZ to linear sRGB, with a Bradford adaptation from the equal-energy white point to D65 so that a constant
+// spectrum comes out grey.
+const XYZ_TO_RGB: [[f32; 3]; 3] = [
+    [3.146_251, -1.666_124, -0.480_127],
+    [-0.995_535, 1.955_763, 0.039_772],
+    [0.063_598, -0.214_597, 1.150_999],
+];
+
+// Smits' (1999) spectra for upsampling RGB, in ten bins from 380nm to 720nm.
+const SMITS_BIN_START: f32 = 380.0;
+const SMITS_BIN_WIDTH: f32 = 34.0;
+const SMITS_WHITE: [f32; 10] = [1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000];
+const SMITS_CYAN: [f32; 10] = [0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000];
+const SMITS_MAGENTA: [f32; 10] = [1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959];
+const SMITS_YELLOW: [f32; 10] = [0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840];
+const SMITS_RED: [f32; 10] = [0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149];
+const SMITS_GREEN: [f32; 10] = [0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025];
+const SMITS_BLUE: [f32; 10] = [1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496];
+
+// Wavelengths a path is traced at, picked by hero wavelength sampling (Wilkie et al. 2014): a random hero and
+// the others evenly rotated through the visible range from it. A wavelength-dependent interaction such as
+// dispersion can only follow the hero, and terminates the others.
+#[derive(Clone, Copy)]
+pub struct Wavelengths {
+    pub lambda: [f32; SPECTRUM_SAMPLES],
+    pub secondary_terminated: bool,
+}
+
+impl Wavelengths {
+    pub fn sample() -> Self {
+        let u = random_f32();
+        let mut lambda = [0.0; SPECTRUM_SAMPLES];
+        for (i, l) in lambda.iter_mut().enumerate() {
+            let offset = (u + i as f32 / SPECTRUM_SAMPLES as f32).fract();
+            *l = LAMBDA_MIN + offset * (LAMBDA_MAX - LAMBDA_MIN);
+        }
+        return Self { lambda, secondary_terminated: false };
+    }
+
+    pub fn hero(&self) -> f32 {
+        return self.lambda[0];
+    }
+
+    pub fn terminate_secondary(&self) -> Self {
+        return Self { lambda: self.lambda, secondary_terminated: true };
+    }
+
+    // Weight for the step from `self` to `next`. Once the secondary wavelengths drop out, the hero stands in
+    // for all of them.
+    pub fn termination_weight(&self, next: &Wavelengths) -> Color {
+        if self.secondary_terminated || !next.secondary_terminated {
+            return Color::WHITE;
+        }
+        return Color { r: SPECTRUM_SAMPLES as f32, g: 0.0, b: 0.0 };
+    }
+
+    // Values at the path's wavelengths of a spectrum matching the RGB `color`.
+    pub fn from_rgb(&self, color: Color) -> Color {
+        return Color {
+            r: rgb_to_spectrum(color, self.lambda[0]),
+            g: rgb_to_spectrum(color, self.lambda[1]),
+            b: rgb_to_spectrum(color, self.lambda[2]),
+        };
+    }
+
+    // Linear sRGB of spectral radiance sampled at the path's wavelengths, for the film.
+    pub fn to_rgb(&self, radiance: Color) -> Color {
+        let samples = [radiance.r, radiance.g, radiance.b];
+        let pdf = 1.0 / (LAMBDA_MAX - LAMBDA_MIN);
+        let mut xyz = [0.0; 3];
+        for (lambda, value) in self.lambda.iter().zip(samples.iter()) {
+            let cmf = color_matching(*lambda);
+            for c in 0..3 {
+                xyz[c] += value * cmf[c] / (pdf * CMF_INTEGRAL[c] * SPECTRUM_SAMPLES as f32);
+            }
+        }
+        let rgb: Vec<f32> = XYZ_TO_RGB.iter().map(|row| row[0] * xyz[0] + row[1] * xyz[1] + row[2] * xyz[2]).collect();
+        return Color { r: rgb[0], g: rgb[1], b: rgb[2] };
+    }
+}
+
+fn piecewise_gaussian(x: f32, mean: f32, sigma_low: f32, sigma_high: f32) -> f32 {
+    let sigma = if x < mean { sigma_low } else { sigma_high };
+    return (-(x - mean).powi(2) / (2.0 * sigma.powi(2))).exp();
+}
+
+// CIE 1931 colour matching functions by Wyman, Sloan and Shirley's multi-lobe fit (2013).
+pub fn color_matching(lambda: f32) -> [f32; 3] {
+    let x = 1.056 * piecewise_gaussian(lambda, 599.8, 37.9, 31.0) + 0.362 * piecewise_gaussian(lambda, 442.0, 16.0, 26.7)
+        - 0.065 * piecewise_gaussian(lambda, 501.1, 20.4, 26.2);
+    let y = 0.821 * piecewise_gaussian(lambda, 568.8, 46.9, 40.5) + 0.286 * piecewise_gaussian(lambda, 530.9, 16.3, 31.1);
+    let z = 1.217 * piecewise_gaussian(lambda, 437.0, 11.8, 36.0) + 0.681 * piecewise_gaussian(lambda, 459.0, 26.0, 13.8);
+    return [x, y, z];
+}
+
+// Smits' RGB to spectrum conversion: the smallest channel becomes white, and the rest is made up of the
+// secondary and primary colours in between. Linear in `color`, so it also holds for emission above 1.
+pub fn rgb_to_spectrum(color: Color, lambda: f32) -> f32 {
+    let bin = (((lambda - SMITS_BIN_START) / SMITS_BIN_WIDTH).max(0.0) as usize).min(9);
+    let Color { r, g, b } = color;
+    if r <= g && r <= b {
+        return r * SMITS_WHITE[bin] + if g <= b {
+            (g - r) * SMITS_CYAN[bin] + (b - g) * SMITS_BLUE[bin]
+        } else {
+            (b - r) * SMITS_CYAN[bin] + (g - b) * SMITS_GREEN[bin]
+        };
+    }
+    if g <= r && g <= b {
+        return g * SMITS_WHITE[bin] + if r <= b {
+            (r - g) * SMITS_MAGENTA[bin] + (b - r) * SMITS_BLUE[bin]
+        } else {
+            (b - g) * SMITS_MAGENTA[bin] + (r - b) * SMITS_RED[bin]
+        };
+    }
+    return b * SMITS_WHITE[bin] + if r <= g {
+        (r - b) * SMITS_YELLOW[bin] + (g - r) * SMITS_GREEN[bin]
+    } else {
+        (g - b) * SMITS_YELLOW[bin] + (r - g) * SMITS_RED[bin]
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Film colour of the spectrum `spectrum`, by stratifying the hero wavelength across the visible range.
+    fn film_color(spectrum: impl Fn(f32) -> f32) -> Color {
+        let steps = 4000;
+        let mut sum = Color::BLACK;
+        for i in 0..steps {
+            let u = (i as f32 + 0.5) / steps as f32;
+            let mut lambda = [0.0; SPECTRUM_SAMPLES];
+            for (j, l) in lambda.iter_mut().enumerate() {
+                *l = LAMBDA_MIN + (u + j as f32 / SPECTRUM_SAMPLES as f32).fract() * (LAMBDA_MAX - LAMBDA_MIN);
+            }
+            let wavelengths = Wavelengths { lambda, secondary_terminated: false };
+            let rgb = wavelengths.to_rgb(Color { r: spectrum(lambda[0]), g: spectrum(lambda[1]), b: spectrum(lambda[2]) });
+            sum = Color { r: sum.r + rgb.r, g: sum.g + rgb.g, b: sum.b + rgb.b };
+        }
+        return Color { r: sum.r / steps as f32, g: sum.g / steps as f32, b: sum.b / steps as f32 };
+    }
+
+    fn assert_close(a: Color, b: Color, tolerance: f32) {
+        let error = (a.r - b.r).abs().max((a.g - b.g).abs()).max((a.b - b.b).abs());
+        assert!(error < tolerance, "({}, {}, {}) vs ({}, {}, {})", a.r, a.g, a.b, b.r, b.g, b.b);
+    }
+
+    #[test]
+    fn equal_energy_spectrum_is_white() {
+        assert_close(film_color(|_| 1.0), Color::WHITE, 0.01);
+    }
+
+    #[test]
+    fn white_and_grey_upsample_to_flat_spectra() {
+        for lambda in [380.0, 450.0, 550.0, 650.0, 720.0] {
+            assert!((rgb_to_spectrum(Color::WHITE, lambda) - 1.0).abs() < 1e-3);
+            let grey = Color { r: 0.4, g: 0.4, b: 0.4 };
+            assert!((rgb_to_spectrum(grey, lambda) - 0.4).abs() < 1e-3);
+        }
+    }
+
+    // Smits' spectra are a fit, so saturated primaries come back up to about a tenth off.
+    #[test]
+    fn upsampled_colors_round_trip() {
+        let colors = [
+            Color { r: 1.0, g: 0.0, b: 0.0 },
+            Color { r: 0.0, g: 1.0, b: 0.0 },
+            Color { r: 0.0, g: 0.0, b: 1.0 },
+            Color { r: 0.8, g: 0.6, b: 0.2 },
+            Color { r: 0.2, g: 0.5, b: 0.7 },
+            Color { r: 0.65, g: 0.05, b: 0.05 },
+        ];
+        for color in colors {
+            let round_trip = film_color(|lambda| rgb_to_spectrum(color, lambda));
+            assert_close(round_trip, color, 0.12);
+        }
+    }
+}
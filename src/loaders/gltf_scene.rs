@@ -0,0 +1,468 @@
+use std::sync::Arc;
+
+use gltf::camera::Projection;
+use gltf::image::Format;
+use gltf::khr_lights_punctual::Kind;
+use gltf::mesh::Mode;
+use gltf::texture::WrappingMode;
+use image::{Rgb, RgbImage};
+
+use crate::geometry::bounding_volume::AxisAlignedBoundingBox;
+use crate::geometry::color::Color;
+use crate::geometry::matrix::Matrix4;
+use crate::geometry::vector::{Point, Vector3};
+use crate::materials::dielectric::Dielectric;
+use crate::materials::lambertian::Lambertian;
+use crate::materials::light::{DiffuseLight, SpotLight};
+use crate::materials::material::Material;
+use crate::materials::mix::AddMaterial;
+use crate::materials::principled::{constant, Principled};
+use crate::objects::hittable::Hittable;
+use crate::objects::sphere::Sphere;
+use crate::objects::triangle::Triangle;
+use crate::textures::channel::ChannelTexture;
+use crate::textures::image::{ImageTexture, WrapMode};
+use crate::textures::scaled::ScaledTexture;
+use crate::textures::solid::SolidColor;
+use crate::textures::texture::Texture;
+use crate::utils::PI;
+use crate::world::camera::{Camera, OrthographicCamera, PerspectiveCamera};
+use crate::world::hittable_list::HittableList;
+
+pub struct GltfScene {
+    pub objects: Vec<Arc<dyn Hittable>>,
+    pub lights: HittableList,
+    pub camera: Option<Arc<dyn Camera>>,
+}
+
+struct LoadContext<'a> {
+    buffers: &'a [gltf::buffer::Data],
+    // Each material with whether it emits and the texture coordinate set its textures use.
+    materials: Vec<(Arc<dyn Material>, bool, u32)>,
+    default_material: Arc<dyn Material>,
+    aspect_ratio: f32,
+    time0: f32,
+    time1: f32,
+    // Point and spot lights with their intensity and, for spots, the axis and cone angles. They are sized to the
+    // scene, so they wait until all the meshes are in, as do the suns.
+    point_lights: Vec<(Point, Color, Option<(Vector3, f32, f32)>)>,
+    suns: Vec<(Vector3, Color)>,
+}
+
+impl GltfScene {
+    // Radius of the spheres standing in for point and spot lights, as a fraction of the scene's diagonal.
+    const POINT_LIGHT_SIZE: f32 = 0.005;
+    const SUN_ANGULAR_RADIUS: f32 = 0.00465;
+
+    pub fn load(path: &str, aspect_ratio: f32, time0: f32, time1: f32) -> Result<Self, gltf::Error> {
+        return Ok(Self::from_import(gltf::import(path)?, aspect_ratio, time0, time1));
+    }
+
+    // Builds the scene from a document and the buffers and images it refers to, as `gltf::import` returns them.
+    fn from_import(import: (gltf::Document, Vec<gltf::buffer::Data>, Vec<gltf::image::Data>), aspect_ratio: f32, time0: f32, time1: f32) -> Self {
+        let (document, buffers, images) = import;
+        let images: Vec<Arc<RgbImage>> = images.iter().map(Self::convert_image).collect();
+        let materials = document.materials().map(|m| Self::convert_material(&m, &images)).collect();
+
+        let mut context = LoadContext {
+            buffers: &buffers,
+            materials,
+            default_material: Arc::new(Lambertian::new(Color { r: 0.8, g: 0.8, b: 0.8 })),
+            aspect_ratio,
+            time0,
+            time1,
+            point_lights: Vec::new(),
+            suns: Vec::new(),
+        };
+        let mut scene = Self {
+            objects: Vec::new(),
+            lights: HittableList::new(),
+            camera: Option::None,
+        };
+
+        let opt_gltf_scene = document.default_scene().or_else(|| document.scenes().next());
+        if let Some(gltf_scene) = opt_gltf_scene {
+            for node in gltf_scene.nodes() {
+                scene.visit_node(&node, &Matrix4::IDENTITY, &mut context);
+            }
+        }
+
+        let (center, extent) = scene.bounds();
+        for &(position, intensity, spot) in context.point_lights.iter() {
+            scene.add_point_light(position, intensity, spot, Self::POINT_LIGHT_SIZE * extent);
+        }
+        for &(direction, illuminance) in context.suns.iter() {
+            scene.add_sun(direction, illuminance, center, extent);
+        }
+
+        return scene;
+    }
+
+    fn visit_node(&mut self, node: &gltf::Node, parent: &Matrix4, context: &mut LoadContext) {
+        let matrix = *parent * Matrix4::from_column_major(node.transform().matrix());
+
+        if let Some(mesh) = node.mesh() {
+            for primitive in mesh.primitives() {
+                self.add_primitive(&primitive, &matrix, context);
+            }
+        }
+
+        if let Some(camera) = node.camera() {
+            if self.camera.is_none() {
+                self.camera = Self::convert_camera(&camera, &matrix, context);
+            }
+        }
+
+        if let Some(light) = node.light() {
+            let color = light.intensity() * Color { r: light.color()[0], g: light.color()[1], b: light.color()[2] };
+            match light.kind() {
+                Kind::Directional => {
+                    let direction = matrix.transform_vector(Vector3 { x: 0.0, y: 0.0, z: -1.0 }).direction();
+                    context.suns.push((direction, color));
+                }
+                Kind::Point => {
+                    context.point_lights.push((matrix.transform_point(Point::ORIGIN), color, Option::None));
+                }
+                Kind::Spot { inner_cone_angle, outer_cone_angle } => {
+                    let axis = matrix.transform_vector(Vector3 { x: 0.0, y: 0.0, z: -1.0 });
+                    context.point_lights.push((matrix.transform_point(Point::ORIGIN), color, Option::from((axis, inner_cone_angle, outer_cone_angle))));
+                }
+            }
+        }
+
+        for child in node.children() {
+            self.visit_node(&child, &matrix, context);
+        }
+    }
+
+    fn add_primitive(&mut self, primitive: &gltf::Primitive, matrix: &Matrix4, context: &LoadContext) {
+        if primitive.mode() != Mode::Triangles {
+            return;
+        }
+
+        let reader = primitive.reader(|buffer| Some(&context.buffers[buffer.index()]));
+        let positions: Vec<Point> = match reader.read_positions() {
+            Some(iter) => iter.map(|p| matrix.transform_point(Point { x: p[0], y: p[1], z: p[2] })).collect(),
+            None => return,
+        };
+        let normal_matrix = matrix.normal_matrix().unwrap_or(Matrix4::IDENTITY);
+        let normals: Option<Vec<Vector3>> = reader.read_normals().map(|iter| {
+            iter.map(|n| normal_matrix.transform_vector(Vector3 { x: n[0], y: n[1], z: n[2] }).direction()).collect()
+        });
+        let (material, is_emissive, tex_coord) = match primitive.material().index() {
+            Some(idx) => context.materials[idx].clone(),
+            None => (context.default_material.clone(), false, 0),
+        };
+        let uvs: Vec<(f32, f32)> = match reader.read_tex_coords(tex_coord) {
+            Some(iter) => iter.into_f32().map(|uv| (uv[0], 1.0 - uv[1])).collect(),
+            None => vec![(0.0, 0.0); positions.len()],
+        };
+        let indices: Vec<u32> = match reader.read_indices() {
+            Some(iter) => iter.into_u32().collect(),
+            None => (0..positions.len() as u32).collect(),
+        };
+
+        for face in indices.chunks_exact(3) {
+            let (i0, i1, i2) = (face[0] as usize, face[1] as usize, face[2] as usize);
+            let triangle = Arc::new(Triangle {
+                vertices: [positions[i0], positions[i1], positions[i2]],
+                normals: normals.as_ref().map(|n| [n[i0], n[i1], n[i2]]),
+                uvs: [uvs[i0], uvs[i1], uvs[i2]],
+                material: material.clone(),
+            });
+
+            if is_emissive {
+                self.lights.add(triangle.clone());
+            }
+            self.objects.push(triangle);
+        }
+    }
+
+    // The image's aspect ratio wins over the one the camera was authored with, so the render isn't stretched.
+    fn convert_camera(camera: &gltf::Camera, matrix: &Matrix4, context: &LoadContext) -> Option<Arc<dyn Camera>> {
+        let look_from = matrix.transform_point(Point::ORIGIN);
+        let look_at = look_from + matrix.transform_vector(Vector3 { x: 0.0, y: 0.0, z: -1.0 });
+        let v_up = matrix.transform_vector(Vector3 { x: 0.0, y: 1.0, z: 0.0 });
+
+        return match camera.projection() {
+            Projection::Perspective(perspective) => Option::from(Arc::new(PerspectiveCamera::new(
+                look_from,
+                look_at,
+                v_up,
+                perspective.yfov() * 180.0 / PI,
+                context.aspect_ratio,
+                0.0,
+                1.0,
+                context.time0,
+                context.time1,
+            )) as Arc<dyn Camera>),
+            Projection::Orthographic(orthographic) => Option::from(Arc::new(OrthographicCamera::new(
+                look_from,
+                look_at,
+                v_up,
+                2.0 * orthographic.ymag(),
+                context.aspect_ratio,
+                context.time0,
+                context.time1,
+            )) as Arc<dyn Camera>),
+        };
+    }
+
+    // Centre and diagonal of the meshes loaded so far, at least one unit across.
+    fn bounds(&self) -> (Point, f32) {
+        let mut opt_bound_box: Option<AxisAlignedBoundingBox> = Option::None;
+        for obj in self.objects.iter() {
+            if let Some(obj_box) = obj.bounding_box(0.0, 0.0) {
+                opt_bound_box = Option::from(match opt_bound_box {
+                    Some(bound_box) => AxisAlignedBoundingBox::surrounding_box(bound_box, obj_box),
+                    None => obj_box,
+                });
+            }
+        }
+        return match opt_bound_box {
+            Some(bound_box) => (bound_box.centroid(), (bound_box.maximum - bound_box.minimum).length().max(1.0)),
+            None => (Point::ORIGIN, 1.0),
+        };
+    }
+
+    // Point and spot lights become small spherical emitters with the same radiant intensity.
+    fn point_light_radiance(intensity: Color, radius: f32) -> Color {
+        return (1.0 / (PI * radius.powi(2))) * intensity;
+    }
+
+    fn add_point_light(&mut self, position: Point, intensity: Color, spot: Option<(Vector3, f32, f32)>, radius: f32) {
+        let radiance = Self::point_light_radiance(intensity, radius);
+        let material: Arc<dyn Material> = match spot {
+            Some((axis, inner_cone_angle, outer_cone_angle)) => Arc::new(SpotLight::new(radiance, axis, inner_cone_angle, outer_cone_angle)),
+            None => Arc::new(DiffuseLight::new(radiance)),
+        };
+        let light: Arc<dyn Hittable> = Arc::new(Sphere { center: position, radius, material });
+        self.objects.push(light.clone());
+        self.lights.add(light);
+    }
+
+    // Directional lights become a distant sun-sized disc, placed well outside the scene.
+    fn add_sun(&mut self, direction: Vector3, illuminance: Color, center: Point, extent: f32) {
+        let distance = 1000.0 * extent;
+        let sin_alpha = Self::SUN_ANGULAR_RADIUS.sin();
+        let radiance = (1.0 / (PI * sin_alpha.powi(2))) * illuminance;
+        let sun: Arc<dyn Hittable> = Arc::new(Sphere {
+            center: center - distance * direction,
+            radius: distance * Self::SUN_ANGULAR_RADIUS.tan(),
+            material: Arc::new(DiffuseLight::new(radiance)),
+        });
+        self.objects.push(sun.clone());
+        self.lights.add(sun);
+    }
+
+    fn convert_material(material: &gltf::Material, images: &[Arc<RgbImage>]) -> (Arc<dyn Material>, bool, u32) {
+        let surface = Self::convert_surface(material, images);
+
+        // Triangles carry one set of texture coordinates, so all of a material's textures share the set the first
+        // of them names.
+        let pbr = material.pbr_metallic_roughness();
+        let tex_coord = pbr.base_color_texture()
+            .or_else(|| pbr.metallic_roughness_texture())
+            .or_else(|| material.emissive_texture())
+            .map_or(0, |info| info.tex_coord());
+
+        // Emissive materials still reflect, so their light is layered over the surface.
+        let emissive = material.emissive_factor();
+        if emissive.iter().any(|&c| c > 0.0) {
+            let emissive_color = Color { r: emissive[0], g: emissive[1], b: emissive[2] };
+            let emit = Self::texture_or_color(material.emissive_texture(), images, emissive_color);
+            return (Arc::new(AddMaterial { material: surface, emission: Arc::new(DiffuseLight { emit }) }), true, tex_coord);
+        }
+        return (surface, false, tex_coord);
+    }
+
+    fn convert_surface(material: &gltf::Material, images: &[Arc<RgbImage>]) -> Arc<dyn Material> {
+        let pbr = material.pbr_metallic_roughness();
+        let base = pbr.base_color_factor();
+        let base_color = Color { r: base[0], g: base[1], b: base[2] };
+
+        // Smooth glass is traced as a delta; anything else is the principled model glTF's PBR is based on.
+        let transmission = material.transmission().map_or(0.0, |transmission| transmission.transmission_factor());
+        let refractive_index = material.ior().unwrap_or(1.5);
+        if transmission > 0.5 && pbr.metallic_factor() < 0.5 && pbr.roughness_factor() == 0.0 && pbr.metallic_roughness_texture().is_none() {
+            return Arc::new(Dielectric::new(refractive_index));
+        }
+
+        let mut principled = Principled::new(base_color);
+        principled.base_color = Self::texture_or_color(pbr.base_color_texture(), images, base_color);
+        match pbr.metallic_roughness_texture() {
+            // Roughness is in the green channel and metallic in the blue one.
+            Some(info) => {
+                let texture = Self::image_texture(&info, images, false);
+                principled.metallic = Arc::new(ChannelTexture { texture: texture.clone(), channel: 2, scale: pbr.metallic_factor() });
+                principled.roughness = Arc::new(ChannelTexture { texture, channel: 1, scale: pbr.roughness_factor() });
+            }
+            None => {
+                principled.metallic = constant(pbr.metallic_factor());
+                principled.roughness = constant(pbr.roughness_factor());
+            }
+        }
+        principled.specular = constant(((refractive_index - 1.0) / (refractive_index + 1.0)).powi(2) / 0.08);
+        principled.transmission = constant(transmission);
+        return Arc::new(principled);
+    }
+
+    // Colour textures, unlike data ones, are sRGB encoded.
+    fn texture_or_color(info: Option<gltf::texture::Info>, images: &[Arc<RgbImage>], color: Color) -> Arc<dyn Texture> {
+        return match info {
+            Some(info) => Arc::new(ScaledTexture {
+                texture: Self::image_texture(&info, images, true),
+                scale: color,
+            }),
+            None => Arc::new(SolidColor { color }),
+        };
+    }
+
+    fn image_texture(info: &gltf::texture::Info, images: &[Arc<RgbImage>], srgb: bool) -> Arc<dyn Texture> {
+        let texture = info.texture();
+        let wrap = |mode: WrappingMode| match mode {
+            WrappingMode::ClampToEdge => WrapMode::Clamp,
+            WrappingMode::MirroredRepeat => WrapMode::MirroredRepeat,
+            WrappingMode::Repeat => WrapMode::Repeat,
+        };
+        return Arc::new(ImageTexture::shared(images[texture.source().index()].clone())
+            .with_wrap(wrap(texture.sampler().wrap_s()), wrap(texture.sampler().wrap_t()))
+            .with_srgb(srgb));
+    }
+
+    fn convert_image(data: &gltf::image::Data) -> Arc<RgbImage> {
+        let (channels, bytes_per_channel, bgr) = match data.format {
+            Format::R8 => (1, 1, false),
+            Format::R8G8 => (2, 1, false),
+            Format::R8G8B8 => (3, 1, false),
+            Format::R8G8B8A8 => (4, 1, false),
+            Format::B8G8R8 => (3, 1, true),
+            Format::B8G8R8A8 => (4, 1, true),
+            Format::R16 => (1, 2, false),
+            Format::R16G16 => (2, 2, false),
+            Format::R16G16B16 => (3, 2, false),
+            Format::R16G16B16A16 => (4, 2, false),
+        };
+
+        let stride = channels * bytes_per_channel;
+        // 16-bit channels are little-endian, so the high byte comes second.
+        let channel = |pixel: &[u8], c: usize| pixel[c * bytes_per_channel + bytes_per_channel - 1];
+
+        let mut img = RgbImage::new(data.width, data.height);
+        for (idx, pixel) in data.pixels.chunks_exact(stride).enumerate() {
+            let rgb = match channels {
+                1 | 2 => [channel(pixel, 0); 3],
+                _ if bgr => [channel(pixel, 2), channel(pixel, 1), channel(pixel, 0)],
+                _ => [channel(pixel, 0), channel(pixel, 1), channel(pixel, 2)],
+            };
+            let x = idx as u32 % data.width;
+            let y = idx as u32 / data.width;
+            img.put_pixel(x, y, Rgb(rgb));
+        }
+
+        return Arc::new(img);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::ray::Ray;
+    use crate::utils::INF_F32;
+
+    // A triangle two nodes down a translated and scaled hierarchy with an emissive material, a spot light pointing
+    // down and a camera authored for a wide image, with the vertex buffer embedded as a data URI.
+    const FIXTURE: &str = r#"{
+        "asset": { "version": "2.0" },
+        "extensionsUsed": ["KHR_lights_punctual"],
+        "extensions": { "KHR_lights_punctual": { "lights": [
+            { "type": "spot", "color": [1.0, 1.0, 1.0], "intensity": 10.0, "spot": { "innerConeAngle": 0.3, "outerConeAngle": 0.6 } }
+        ] } },
+        "scene": 0,
+        "scenes": [{ "nodes": [0, 2, 3] }],
+        "nodes": [
+            { "translation": [1.0, 0.0, 0.0], "scale": [2.0, 2.0, 2.0], "children": [1] },
+            { "translation": [0.0, 1.0, 0.0], "mesh": 0 },
+            { "translation": [0.0, 5.0, 0.0], "rotation": [-0.70710678, 0.0, 0.0, 0.70710678], "extensions": { "KHR_lights_punctual": { "light": 0 } } },
+            { "translation": [0.0, 0.0, 10.0], "camera": 0 }
+        ],
+        "cameras": [{ "type": "perspective", "perspective": { "yfov": 0.8, "aspectRatio": 2.0, "znear": 0.1 } }],
+        "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "material": 0 }] }],
+        "materials": [{
+            "pbrMetallicRoughness": { "baseColorFactor": [0.5, 0.5, 0.5, 1.0], "metallicFactor": 0.0, "roughnessFactor": 1.0 },
+            "emissiveFactor": [1.0, 0.5, 0.25]
+        }],
+        "accessors": [{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0] }],
+        "bufferViews": [{ "buffer": 0, "byteLength": 36 }],
+        "buffers": [{ "byteLength": 36, "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA" }]
+    }"#;
+
+    fn load_fixture() -> GltfScene {
+        return GltfScene::from_import(gltf::import_slice(FIXTURE.as_bytes()).unwrap(), 1.0, 0.0, 1.0);
+    }
+
+    fn ray(origin: Point, direction: Vector3) -> Ray {
+        return Ray { origin, direction, time: 0.0, wavelengths: None, absorption: None };
+    }
+
+    #[test]
+    fn nodes_compose_their_parents_transforms() {
+        let scene = load_fixture();
+        assert!(scene.camera.is_some());
+        // The triangle and the spot light's sphere, both of which emit.
+        assert_eq!(scene.objects.len(), 2);
+        assert_eq!(scene.lights.objects.len(), 2);
+
+        // Translated up one in the child, then scaled by two and moved along x by the parent.
+        let hit_rec = scene.objects[0].hit(ray(Point { x: 1.5, y: 2.5, z: 5.0 }, Vector3 { x: 0.0, y: 0.0, z: -1.0 }), 0.001, INF_F32).unwrap();
+        assert!((hit_rec.intersection - Point { x: 1.5, y: 2.5, z: 0.0 }).length() < 1e-5);
+        assert!(scene.objects[0].hit(ray(Point { x: 0.5, y: 2.5, z: 5.0 }, Vector3 { x: 0.0, y: 0.0, z: -1.0 }), 0.001, INF_F32).is_none());
+        assert!(scene.objects[0].hit(ray(Point { x: 1.5, y: 3.9, z: 5.0 }, Vector3 { x: 0.0, y: 0.0, z: -1.0 }), 0.001, INF_F32).is_none());
+    }
+
+    // The fixture is loaded for a square image, so the corner of the view is as far up as it is across.
+    #[test]
+    fn camera_takes_the_image_aspect_ratio() {
+        let scene = load_fixture();
+        let corner = scene.camera.unwrap().get_ray(1.0, 1.0).unwrap().direction;
+        assert!((corner.x.abs() - corner.y.abs()).abs() < 1e-4 * corner.length(), "({}, {}, {})", corner.x, corner.y, corner.z);
+    }
+
+    #[test]
+    fn emission_is_layered_over_the_surface_material() {
+        let scene = load_fixture();
+        let in_ray = ray(Point { x: 1.5, y: 2.5, z: 5.0 }, Vector3 { x: 0.0, y: 0.0, z: -1.0 });
+        let hit_rec = scene.objects[0].hit(in_ray, 0.001, INF_F32).unwrap();
+
+        let emitted = hit_rec.material.emitted(in_ray, &hit_rec, hit_rec.u, hit_rec.v, hit_rec.intersection);
+        assert!((emitted.r - 1.0).abs() < 1e-6 && (emitted.g - 0.5).abs() < 1e-6 && (emitted.b - 0.25).abs() < 1e-6);
+        // The glowing surface still reflects light.
+        assert!(hit_rec.material.scatter(in_ray, &hit_rec).is_some());
+    }
+
+    #[test]
+    fn spot_lights_fade_out_across_their_cone() {
+        let scene = load_fixture();
+        let light = &scene.objects[1];
+        // The light is sized to the triangle, which spans (1, 2) to (3, 4) in x and y.
+        let light_box = light.bounding_box(0.0, 0.0).unwrap();
+        let radius = 0.5 * (light_box.maximum.x - light_box.minimum.x);
+        assert!((radius - GltfScene::POINT_LIGHT_SIZE * 8.0f32.sqrt()).abs() < 1e-5, "radius {}", radius);
+        let full = GltfScene::point_light_radiance(Color { r: 10.0, g: 10.0, b: 10.0 }, radius).r;
+        let center = Point { x: 0.0, y: 5.0, z: 0.0 };
+        // Radiance leaving the light towards a point at `angle` radians off the downward axis.
+        let emitted = |angle: f32| -> f32 {
+            let origin = center + Vector3 { x: angle.sin(), y: -angle.cos(), z: 0.0 };
+            let in_ray = ray(origin, center - origin);
+            let hit_rec = light.hit(in_ray, 0.001, INF_F32).unwrap();
+            return hit_rec.material.emitted(in_ray, &hit_rec, hit_rec.u, hit_rec.v, hit_rec.intersection).r;
+        };
+
+        assert!((emitted(0.0) - full).abs() < 1e-3 * full);
+        assert!((emitted(0.25) - full).abs() < 1e-3 * full);
+        let edge = emitted(0.45);
+        assert!(edge > 0.0 && edge < full);
+        assert_eq!(emitted(0.7), 0.0);
+        assert_eq!(emitted(PI / 2.0), 0.0);
+    }
+}
@@ -0,0 +1,2 @@
+pub mod gltf_scene;
+pub mod strands;
@@ -0,0 +1,69 @@
+use std::fs;
+use std::sync::Arc;
+
+use crate::geometry::vector::Point;
+use crate::materials::material::Material;
+use crate::objects::curve::{Curve, CurveType};
+
+// One strand per line: `root_width tip_width  x y z  x y z ...`, passing through two or more points.
+// Blank lines and lines starting with `#` are ignored.
+pub fn load_strands(path: &str, curve_type: CurveType, material: Arc<dyn Material>) -> Result<Vec<Curve>, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+
+    let mut curves: Vec<Curve> = Vec::new();
+    for (line_no, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let values: Vec<f32> = line.split_whitespace()
+            .map(|field| field.parse::<f32>())
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("{}:{}: {}", path, line_no + 1, e))?;
+        if values.len() < 8 || (values.len() - 2) % 3 != 0 {
+            return Err(format!("{}:{}: expected two widths and at least two points, found {} values", path, line_no + 1, values.len()));
+        }
+
+        let points: Vec<Point> = values[2..].chunks(3).map(|c| Point { x: c[0], y: c[1], z: c[2] }).collect();
+        curves.extend(Curve::strand(&points, (values[0], values[1]), curve_type, material.clone()));
+    }
+
+    if curves.is_empty() {
+        return Err(format!("{}: no strands", path));
+    }
+    return Ok(curves);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use crate::geometry::color::Color;
+    use crate::materials::lambertian::Lambertian;
+
+    use super::*;
+
+    fn load(name: &str, contents: &str) -> Result<Vec<Curve>, String> {
+        let path = env::temp_dir().join(name);
+        fs::write(&path, contents).unwrap();
+        let curves = load_strands(path.to_str().unwrap(), CurveType::Round, Arc::new(Lambertian::new(Color::WHITE)));
+        fs::remove_file(&path).unwrap();
+        return curves;
+    }
+
+    #[test]
+    fn strands_become_a_piece_per_segment() {
+        let curves = load("strands_ok.txt", "# two strands\n0.1 0.01  0 0 0  0 1 0  0 2 0\n\n0.1 0.01  1 0 0  1 1 0\n").unwrap();
+        assert_eq!(curves.len(), 3);
+        assert_eq!(curves[1].u_range, (0.5, 1.0));
+    }
+
+    #[test]
+    fn malformed_point_count_is_rejected() {
+        let error = load("strands_bad.txt", "0.1 0.01  0 0 0  0 1 0\n0.1 0.01  0 0 0  0 1 0  0 2\n").err().unwrap();
+        assert!(error.ends_with(":2: expected two widths and at least two points, found 10 values"), "{}", error);
+        assert!(load("strands_short.txt", "0.1 0.01  0 0 0\n").is_err());
+        assert!(load("strands_empty.txt", "# nothing\n").is_err());
+    }
+}
@@ -1,20 +1,29 @@
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 use std::thread;
 
 use image::{ImageBuffer, Rgb, RgbImage};
 
+use geometry::bounding_volume::AxisAlignedBoundingBox;
 use geometry::color::Color;
 use geometry::pdf::{CosinePDF, HittablePDF, MixturePDF, PDF};
 use geometry::ray::Ray;
+use geometry::spectrum::Wavelengths;
 use geometry::vector::{Point, Vector3};
+use loaders::gltf_scene::GltfScene;
 use materials::light::DiffuseLight;
-use objects::hittable::Hittable;
-use objects::rectangle::XZRect;
-use scenes::cornell_box;
-use utils::INF_F32;
+use objects::hittable::{HitRecord, Hittable};
+use objects::quad::Quad;
+use scenes::{cornell_box, forest, shapes, shapes_tube_light, terrain};
+use utils::{degrees_to_radians, INF_F32};
 use utils::random_f32;
+use world::animation::{CameraPath, Track};
+use world::aperture::{Aperture, ApertureMask};
 use world::bvh_node::BVHNode;
-use world::camera::Camera;
+use world::flat_bvh::FlatBVH;
+use world::sah::SplitMethod;
+use world::wide_bvh::WideBVH;
+use world::camera::{Camera, EquirectangularCamera, FisheyeCamera, FisheyeMapping, OrthographicCamera, PerspectiveCamera};
 
 use crate::objects::sphere::Sphere;
 use crate::world::hittable_list::HittableList;
@@ -26,19 +35,46 @@ mod materials;
 mod utils;
 mod textures;
 mod scenes;
+mod loaders;
 
-fn ray_color(ray: Ray, background: Color, world: Arc<dyn Hittable>, lights: Arc<dyn Hittable>, depth: u32) -> Color {
+fn ray_color(ray: Ray, background: Color, world: Arc<dyn Hittable>, lights: Option<Arc<dyn Hittable>>, depth: u32) -> Color {
     if depth == 0 {
         return Color::BLACK;
     }
 
     let opt_hit_rec = world.hit(ray, 0.01, INF_F32);
     if opt_hit_rec.is_none() {
-        return background;
+        return spectral(ray, background);
     }
-    let hit_rec = opt_hit_rec.unwrap();
+    return shade(ray, opt_hit_rec.unwrap(), background, world, lights, depth);
+}
+
+// An RGB colour from a material, texture or the background as seen by the ray: its values at the ray's
+// wavelengths in spectral mode, unchanged otherwise.
+fn spectral(ray: Ray, color: Color) -> Color {
+    return match ray.wavelengths {
+        Some(wavelengths) => wavelengths.from_rgb(color),
+        None => color,
+    };
+}
+
+// What the film records of a camera ray's radiance: spectral samples are converted to RGB.
+fn film_color(ray: Ray, radiance: Color) -> Color {
+    return match ray.wavelengths {
+        Some(wavelengths) => wavelengths.to_rgb(radiance),
+        None => radiance,
+    };
+}
 
-    let emitted = hit_rec.material.emitted(ray, &hit_rec, hit_rec.u, hit_rec.v, hit_rec.intersection);
+// Light reaching the ray's origin from the hit point, which an absorbing medium the ray travels through dims.
+fn shade(ray: Ray, hit_rec: HitRecord, background: Color, world: Arc<dyn Hittable>, lights: Option<Arc<dyn Hittable>>, depth: u32) -> Color {
+    let transmittance = spectral(ray, ray.transmittance(hit_rec.t));
+    return transmittance * surface_radiance(ray, hit_rec, background, world, lights, depth);
+}
+
+// Light leaving the hit point back along the ray, recursing for the scattered ray.
+fn surface_radiance(ray: Ray, hit_rec: HitRecord, background: Color, world: Arc<dyn Hittable>, lights: Option<Arc<dyn Hittable>>, depth: u32) -> Color {
+    let emitted = spectral(ray, hit_rec.material.emitted(ray, &hit_rec, hit_rec.u, hit_rec.v, hit_rec.intersection));
 
     let opt_scatter_rec = hit_rec.material.scatter(ray, &hit_rec);
     if opt_scatter_rec.is_none() {
@@ -46,26 +82,35 @@ fn ray_color(ray: Ray, background: Color, world: Arc<dyn Hittable>, lights: Arc<
     }
     let scatter_rec = opt_scatter_rec.unwrap();
 
-    if scatter_rec.specular_ray.is_some() {
-        return scatter_rec.attenuation * ray_color(scatter_rec.specular_ray.unwrap(), background, world, lights, depth - 1);
+    if let Some(specular_ray) = scatter_rec.specular_ray {
+        let mut attenuation = spectral(ray, scatter_rec.attenuation);
+        if let (Some(wavelengths), Some(next)) = (ray.wavelengths, specular_ray.wavelengths) {
+            attenuation = attenuation * wavelengths.termination_weight(&next);
+        }
+        return attenuation * ray_color(specular_ray, background, world, lights, depth - 1);
     }
-    let light_pdf: Arc<dyn PDF> = Arc::new(HittablePDF {
-        o: hit_rec.intersection,
-        ptr: lights.clone(),
-    });
-    let mix_pdf = MixturePDF {
-        ptr: [light_pdf, scatter_rec.pdf_ptr.unwrap()]
+    let mix_pdf: Arc<dyn PDF> = match lights.as_ref() {
+        Some(light_list) => Arc::new(MixturePDF {
+            ptr: [Arc::new(HittablePDF { o: hit_rec.intersection, ptr: light_list.clone() }), scatter_rec.pdf_ptr.clone().unwrap()]
+        }),
+        None => scatter_rec.pdf_ptr.clone().unwrap(),
     };
 
     let scattered = Ray {
         origin: hit_rec.intersection,
         direction: mix_pdf.generate().direction(),
         time: ray.time,
+        wavelengths: ray.wavelengths,
+        absorption: ray.absorption,
     };
     let pdf_val = mix_pdf.value(scattered.direction);
+    // Glossy lobes can send samples below the surface, where nothing scatters.
+    if pdf_val <= 0.0 {
+        return emitted;
+    }
 
-    return emitted + (hit_rec.material.scattering_pdf(ray, &hit_rec, scattered) / pdf_val) *
-        scatter_rec.attenuation * ray_color(scattered, background, world, lights, depth - 1);
+    return emitted + (1.0 / pdf_val) * spectral(ray, hit_rec.material.scattering(ray, &hit_rec, &scatter_rec, scattered)) *
+        ray_color(scattered, background, world, lights, depth - 1);
 }
 
 struct ImageBlockInfo {
@@ -75,20 +120,51 @@ struct ImageBlockInfo {
     image_width: u32,
     spp: u32,
     max_depth: u32,
+    packets: bool,
+    spectral: bool,
     image_block: Vec<Vec<Rgb<u8>>>,
 }
 
-fn process_block(mut block_info: ImageBlockInfo, image_blocks: Arc<Mutex<Vec<ImageBlockInfo>>>, camera: Camera, world: Arc<dyn Hittable>, lights: Arc<dyn Hittable>, background: Color) {
+fn process_block(mut block_info: ImageBlockInfo, image_blocks: Arc<Mutex<Vec<ImageBlockInfo>>>, camera: Arc<dyn Camera>, world: Arc<dyn Hittable>, lights: Option<Arc<dyn Hittable>>, background: Color) {
+    // In spectral mode every camera ray traces its own set of wavelengths.
+    let spectral_mode = block_info.spectral;
+    let camera_ray = |u: f32, v: f32| -> Option<Ray> {
+        return camera.get_ray(u, v).map(|mut ray| {
+            if spectral_mode {
+                ray.wavelengths = Some(Wavelengths::sample());
+            }
+            ray
+        });
+    };
     for j in block_info.start_row..block_info.end_row {
         let mut row: Vec<Rgb<u8>> = Vec::with_capacity(block_info.image_width as usize);
         for i in 0..block_info.image_width {
             let mut pixel_color = Color { r: 0.0, g: 0.0, b: 0.0 };
-            for _ in 0..block_info.spp {
-                let u = (i as f32 + random_f32()) / (block_info.image_width - 1) as f32;
-                let v = (j as f32 + random_f32()) / (block_info.image_height - 1) as f32;
+            if block_info.packets {
+                // A pixel's camera rays are coherent, so they go through the scene as one packet.
+                let rays: Vec<Ray> = (0..block_info.spp).filter_map(|_| {
+                    let u = (i as f32 + random_f32()) / (block_info.image_width - 1) as f32;
+                    let v = (j as f32 + random_f32()) / (block_info.image_height - 1) as f32;
+                    camera_ray(u, v)
+                }).collect();
+
+                let hit_recs = world.hit_packet(&rays, 0.01, INF_F32);
+                for (ray, opt_hit_rec) in rays.into_iter().zip(hit_recs) {
+                    let radiance = match opt_hit_rec {
+                        Some(hit_rec) => shade(ray, hit_rec, background, world.clone(), lights.clone(), block_info.max_depth),
+                        None => spectral(ray, background),
+                    };
+                    pixel_color += film_color(ray, radiance);
+                }
+            } else {
+                for _ in 0..block_info.spp {
+                    let u = (i as f32 + random_f32()) / (block_info.image_width - 1) as f32;
+                    let v = (j as f32 + random_f32()) / (block_info.image_height - 1) as f32;
 
-                let ray = camera.get_ray(u, v);
-                pixel_color += ray_color(ray, background, world.clone(), lights.clone(), block_info.max_depth);
+                    if let Some(ray) = camera_ray(u, v) {
+                        pixel_color += film_color(ray, ray_color(ray, background, world.clone(), lights.clone(), block_info.max_depth));
+                    }
+                }
             }
             row.push(pixel_color.get_pixel(block_info.spp));
         }
@@ -99,70 +175,33 @@ fn process_block(mut block_info: ImageBlockInfo, image_blocks: Arc<Mutex<Vec<Ima
     image.push(block_info);
 }
 
-fn main() {
-    // Image
-    const IMAGE_WIDTH: u32 = 800;
-    const IMAGE_HEIGHT: u32 = 800;
-    const ASPECT_RATIO: f32 = IMAGE_WIDTH as f32 / IMAGE_HEIGHT as f32;
-    const SAMPLES_PER_PIXEL: u32 = 1000;
-    const MAX_DEPTH: u32 = 25;
-
-
-    //Camera
-    let look_from = Point { x: 278.0, y: 278.0, z: -800.0 };
-    let look_at = Point { x: 278.0, y: 278.0, z: 0.0 };
-    let v_up = Vector3 { x: 0.0, y: 1.0, z: 0.0 };
-    let v_fov = 40.0;
-    let aperture = 0.0;
-    let focus_dist = 10.0;
-
-    let camera = Camera::new(
-        look_from,
-        look_at,
-        v_up,
-        v_fov,
-        ASPECT_RATIO,
-        aperture,
-        focus_dist,
-        0.0,
-        1.0,
-    );
-
-
-    // World
-    let mut world = cornell_box();
-    let world = BVHNode::create_tree(&mut world, 0.0, 1.0);
-    let background = Color::BLACK;
-    let mut lights = HittableList::new();
-    lights.add(Arc::new(XZRect {
-        x: (213.0, 343.0),
-        z: (227.0, 332.0),
-        k: 554.0,
-        material: Arc::new(DiffuseLight::new(Color::WHITE)),
-    }));
-    lights.add(Arc::new(Sphere {
-        center: Point { x: 190.0, y: 90.0, z: 190.0 },
-        radius: 90.0,
-        material: Arc::new(DiffuseLight::new(Color::WHITE)),
-    }));
-    let light_list = Arc::new(lights);
+struct RenderSettings {
+    image_width: u32,
+    image_height: u32,
+    spp: u32,
+    max_depth: u32,
+    packets: bool,
+    spectral: bool,
+}
 
-    // Render
+fn render(settings: &RenderSettings, camera: Arc<dyn Camera>, world: Arc<dyn Hittable>, light_list: Option<Arc<dyn Hittable>>, background: Color) -> RgbImage {
     const NTHREADS: u32 = 10;
     let mut threads: Vec<thread::JoinHandle<()>> = Vec::new();
     let image_blocks: Arc<Mutex<Vec<ImageBlockInfo>>> = Arc::new(Mutex::new(Vec::new()));
 
-    let block_size = IMAGE_HEIGHT / NTHREADS;
-    let end_block_size = block_size + (IMAGE_HEIGHT % NTHREADS);
+    let block_size = settings.image_height / NTHREADS;
+    let end_block_size = block_size + (settings.image_height % NTHREADS);
 
     for i in 0..NTHREADS {
         let block_info = ImageBlockInfo {
             start_row: i * block_size,
             end_row: i * block_size + (if i == NTHREADS - 1 { end_block_size } else { block_size }),
-            image_height: IMAGE_HEIGHT,
-            image_width: IMAGE_WIDTH,
-            spp: SAMPLES_PER_PIXEL,
-            max_depth: MAX_DEPTH,
+            image_height: settings.image_height,
+            image_width: settings.image_width,
+            spp: settings.spp,
+            max_depth: settings.max_depth,
+            packets: settings.packets,
+            spectral: settings.spectral,
             image_block: Vec::with_capacity(block_size as usize),
         };
 
@@ -182,7 +221,7 @@ fn main() {
     }
 
     let final_blocks = image_blocks.lock().unwrap();
-    let mut img_buf: RgbImage = ImageBuffer::new(IMAGE_WIDTH, IMAGE_HEIGHT);
+    let mut img_buf: RgbImage = ImageBuffer::new(settings.image_width, settings.image_height);
 
     for block in final_blocks.iter() {
         for y in 0..block.image_block.len() {
@@ -193,5 +232,320 @@ fn main() {
             }
         }
     }
-    img_buf.save("render.png").unwrap();
+    return img_buf;
+}
+
+// Puts the scene behind a wide SAH BVH, or behind the original median-split tree when there is no split method.
+// Returns it with a summary of the build.
+fn build_bvh(mut objects: Vec<Arc<dyn Hittable>>, time0: f32, time1: f32, split_method: Option<SplitMethod>) -> (Arc<dyn Hittable>, String) {
+    return match split_method {
+        Some(method) => {
+            let (bvh, bvh_stats) = FlatBVH::build(&objects, time0, time1, method);
+            (Arc::new(WideBVH::from_binary(&bvh)), bvh_stats.to_string())
+        }
+        None => {
+            let primitives = objects.len();
+            (BVHNode::create_tree(&mut objects, time0, time1), format!("BVH: {} primitives, median splits", primitives))
+        }
+    };
+}
+
+// Alternatives to the perspective view the built-in scenes are framed for.
+enum Projection {
+    Orthographic,
+    Fisheye(FisheyeMapping),
+    Panorama,
+}
+
+struct Options {
+    scene_path: Option<String>,
+    frames: Option<(u32, u32)>,
+    camera_path: Option<String>,
+    turntable: bool,
+    packets: bool,
+    spectral: bool,
+    forest: Option<u32>,
+    terrain: bool,
+    heightmap: Option<String>,
+    strands: Option<String>,
+    shapes: bool,
+    projection: Option<Projection>,
+    // Focal length in mm and f-number of a physical lens, with the shape of its opening.
+    lens: Option<(f32, f32)>,
+    blades: Option<u32>,
+    aperture_mask: Option<String>,
+    // None builds the original median-split BVHNode tree, for comparison.
+    split_method: Option<SplitMethod>,
+    output: String,
+}
+
+impl Options {
+    const USAGE: &'static str = "usage: RayTracer [scene.gltf] [--frames FIRST..LAST] [--forest TREES | --terrain [--heightmap IMAGE] [--strands FILE] | --shapes] [--turntable | --camera-path FILE] [--lens FOCAL_MM F_NUMBER [--blades N | --aperture-mask IMAGE]] [--orthographic | --fisheye equidistant|equisolid | --panorama] [--bvh median|binned|sweep] [--packets] [--spectral] [--output NAME]";
+
+    fn parse() -> Self {
+        let mut options = Options {
+            scene_path: None,
+            frames: None,
+            camera_path: None,
+            turntable: false,
+            packets: false,
+            spectral: false,
+            forest: None,
+            terrain: false,
+            heightmap: None,
+            strands: None,
+            shapes: false,
+            projection: None,
+            lens: None,
+            blades: None,
+            aperture_mask: None,
+            split_method: Some(SplitMethod::Binned(12)),
+            output: String::from("render"),
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--frames" => {
+                    let range = args.next().expect(Self::USAGE);
+                    let (first, last) = range.split_once("..").expect(Self::USAGE);
+                    let (first, last): (u32, u32) = (first.parse().expect(Self::USAGE), last.parse().expect(Self::USAGE));
+                    if first > last {
+                        panic!("{}", Self::USAGE);
+                    }
+                    options.frames = Some((first, last));
+                }
+                "--camera-path" => options.camera_path = Some(args.next().expect(Self::USAGE)),
+                "--turntable" => options.turntable = true,
+                "--packets" => options.packets = true,
+                "--spectral" => options.spectral = true,
+                "--forest" => options.forest = Some(args.next().expect(Self::USAGE).parse().expect(Self::USAGE)),
+                "--lens" => {
+                    let focal_length = args.next().expect(Self::USAGE).parse().expect(Self::USAGE);
+                    let f_number = args.next().expect(Self::USAGE).parse().expect(Self::USAGE);
+                    options.lens = Some((focal_length, f_number));
+                }
+                "--blades" => options.blades = Some(args.next().expect(Self::USAGE).parse().expect(Self::USAGE)),
+                "--aperture-mask" => options.aperture_mask = Some(args.next().expect(Self::USAGE)),
+                "--orthographic" => options.projection = Some(Projection::Orthographic),
+                "--fisheye" => options.projection = Some(Projection::Fisheye(match args.next().expect(Self::USAGE).as_str() {
+                    "equidistant" => FisheyeMapping::Equidistant,
+                    "equisolid" => FisheyeMapping::Equisolid,
+                    _ => panic!("{}", Self::USAGE),
+                })),
+                "--panorama" => options.projection = Some(Projection::Panorama),
+                "--terrain" => options.terrain = true,
+                "--heightmap" => {
+                    options.terrain = true;
+                    options.heightmap = Some(args.next().expect(Self::USAGE));
+                }
+                "--strands" => {
+                    options.terrain = true;
+                    options.strands = Some(args.next().expect(Self::USAGE));
+                }
+                "--shapes" => options.shapes = true,
+                "--bvh" => options.split_method = match args.next().expect(Self::USAGE).as_str() {
+                    "median" => None,
+                    "binned" => Some(SplitMethod::Binned(12)),
+                    "sweep" => Some(SplitMethod::FullSweep),
+                    _ => panic!("{}", Self::USAGE),
+                },
+                "--output" => options.output = args.next().expect(Self::USAGE),
+                _ if arg.starts_with("--") => panic!("{}", Self::USAGE),
+                _ => options.scene_path = Some(arg),
+            }
+        }
+        if options.lens.is_none() && (options.blades.is_some() || options.aperture_mask.is_some()) {
+            panic!("{}", Self::USAGE);
+        }
+        return options;
+    }
+}
+
+fn main() {
+    // Image
+    const IMAGE_WIDTH: u32 = 800;
+    const IMAGE_HEIGHT: u32 = 800;
+    const ASPECT_RATIO: f32 = IMAGE_WIDTH as f32 / IMAGE_HEIGHT as f32;
+    const SAMPLES_PER_PIXEL: u32 = 1000;
+    const MAX_DEPTH: u32 = 25;
+    // Fraction of a frame the shutter stays open for in sequences.
+    const SHUTTER: f32 = 0.5;
+
+    let options = Options::parse();
+    let settings = RenderSettings {
+        image_width: IMAGE_WIDTH,
+        image_height: IMAGE_HEIGHT,
+        spp: SAMPLES_PER_PIXEL,
+        max_depth: MAX_DEPTH,
+        packets: options.packets,
+        spectral: options.spectral,
+    };
+
+
+    //Camera
+    let (look_from, look_at, v_fov) = match options.forest {
+        Some(trees) => (Point { x: 0.0, y: 6.0, z: -4.0 * (trees as f32).sqrt() - 12.0 }, Point { x: 0.0, y: 2.0, z: 0.0 }, 50.0),
+        None if options.terrain => (Point { x: 0.0, y: 12.0, z: -32.0 }, Point { x: 0.0, y: 2.0, z: 0.0 }, 50.0),
+        None if options.shapes => (Point { x: 0.0, y: 3.2, z: -12.5 }, Point { x: 0.0, y: 0.8, z: 2.5 }, 53.0),
+        None => (Point { x: 278.0, y: 278.0, z: -800.0 }, Point { x: 278.0, y: 278.0, z: 0.0 }, 40.0),
+    };
+    let v_up = Vector3 { x: 0.0, y: 1.0, z: 0.0 };
+    let aperture = 0.0;
+    let focus_dist = 10.0;
+    // The Cornell box is modelled in millimetres, the other scenes in metres.
+    let units_per_mm = if options.forest.is_some() || options.terrain || options.shapes { 0.001 } else { 1.0 };
+
+
+    // World
+    let mut scene_camera: Option<Arc<dyn Camera>> = None;
+    let mut gltf_objects: Option<Vec<Arc<dyn Hittable>>> = None;
+    let mut background = Color::BLACK;
+    let mut lights = HittableList::new();
+    if options.forest.is_some() || options.terrain {
+        background = Color { r: 0.7, g: 0.8, b: 1.0 };
+        lights.add(Arc::new(Sphere {
+            center: Point { x: -3000.0, y: 4000.0, z: -2000.0 },
+            radius: 150.0,
+            material: Arc::new(DiffuseLight::new(Color::WHITE)),
+        }));
+    } else if options.shapes {
+        background = Color { r: 0.05, g: 0.06, b: 0.08 };
+        lights.add(Arc::new(Quad::new(
+            Point { x: -3.0, y: 8.0, z: 0.0 },
+            Vector3 { x: 6.0, y: 0.0, z: 0.0 },
+            Vector3 { x: 0.0, y: 0.0, z: 6.0 },
+            Arc::new(DiffuseLight::new(Color::WHITE)),
+        )));
+        lights.add(shapes_tube_light());
+    } else {
+        lights.add(Arc::new(Quad::new(
+            Point { x: 213.0, y: 554.0, z: 227.0 },
+            Vector3 { x: 130.0, y: 0.0, z: 0.0 },
+            Vector3 { x: 0.0, y: 0.0, z: 105.0 },
+            Arc::new(DiffuseLight::new(Color::WHITE)),
+        )));
+        lights.add(Arc::new(Sphere {
+            center: Point { x: 190.0, y: 90.0, z: 190.0 },
+            radius: 90.0,
+            material: Arc::new(DiffuseLight::new(Color::WHITE)),
+        }));
+    }
+
+    if let Some(path) = options.scene_path.as_ref() {
+        let scene = GltfScene::load(path, ASPECT_RATIO, 0.0, 1.0).unwrap();
+        gltf_objects = Some(scene.objects);
+        lights = scene.lights;
+        scene_camera = scene.camera;
+        if lights.objects.is_empty() {
+            background = Color { r: 0.7, g: 0.8, b: 1.0 };
+        }
+    }
+    // A lens in front of a full-frame sensor as wide as the image, focused where the camera looks.
+    if let Some((focal_length, f_number)) = options.lens {
+        let aperture_shape = match (options.blades, options.aperture_mask.as_ref()) {
+            (_, Some(path)) => Aperture::Mask(Arc::new(ApertureMask::new(path).unwrap())),
+            (Some(blades), None) => Aperture::Polygonal { blades, rotation: 0.0 },
+            (None, None) => Aperture::Circular,
+        };
+        scene_camera = Some(Arc::new(PerspectiveCamera::physical(
+            look_from,
+            look_at,
+            v_up,
+            36.0,
+            36.0 / ASPECT_RATIO,
+            focal_length,
+            f_number,
+            (look_at - look_from).length(),
+            (0.0, 1.0),
+            units_per_mm,
+        ).with_aperture(aperture_shape)));
+    }
+
+    // Other projections look from the built-in scenes' viewpoint and replace any camera a glTF scene brings.
+    // The orthographic view is as tall as the perspective one where it looks at.
+    match options.projection {
+        Some(Projection::Orthographic) => {
+            let view_height = 2.0 * (look_at - look_from).length() * (degrees_to_radians(v_fov) / 2.0).tan();
+            scene_camera = Some(Arc::new(OrthographicCamera::new(look_from, look_at, v_up, view_height, ASPECT_RATIO, 0.0, 1.0)));
+        }
+        Some(Projection::Fisheye(mapping)) => scene_camera = Some(Arc::new(FisheyeCamera::new(look_from, look_at, v_up, 180.0, ASPECT_RATIO, mapping, 0.0, 1.0))),
+        Some(Projection::Panorama) => scene_camera = Some(Arc::new(EquirectangularCamera::new(look_from, look_at, v_up, 0.0, 1.0))),
+        None => {}
+    }
+    let light_list: Option<Arc<dyn Hittable>> = if lights.objects.is_empty() { None } else { Some(Arc::new(lights)) };
+
+    // The Cornell box spins a full turn over a sequence; the other scenes are static.
+    let (first_frame, last_frame) = options.frames.unwrap_or((0, 0));
+    let box_angle = Track::new(vec![(first_frame as f32, 15.0), (last_frame as f32 + 1.0, 375.0)]);
+    // Tree placement is random, so the forest is generated once and shared by every frame.
+    let forest_objects = options.forest.map(forest);
+    let terrain_objects = if options.terrain { Some(terrain(options.heightmap.as_ref(), options.strands.as_ref()).unwrap()) } else { None };
+    let shapes_objects = if options.shapes { Some(shapes()) } else { None };
+    let build_world = |frame: f32| -> Vec<Arc<dyn Hittable>> {
+        return match gltf_objects.as_ref().or(forest_objects.as_ref()).or(terrain_objects.as_ref()).or(shapes_objects.as_ref()) {
+            Some(objects) => objects.clone(),
+            None => cornell_box(box_angle.value_at(frame), options.spectral),
+        };
+    };
+
+
+    // Render
+    let frames = match options.frames {
+        None => {
+            let camera: Arc<dyn Camera> = scene_camera.unwrap_or_else(|| Arc::new(PerspectiveCamera::new(
+                look_from,
+                look_at,
+                v_up,
+                v_fov,
+                ASPECT_RATIO,
+                aperture,
+                focus_dist,
+                0.0,
+                1.0,
+            )));
+            let (world, bvh_stats) = build_bvh(build_world(first_frame as f32), 0.0, 1.0, options.split_method);
+            println!("{}", bvh_stats);
+            render(&settings, camera, world, light_list, background).save(format!("{}.png", options.output)).unwrap();
+            return;
+        }
+        Some((first, last)) => first..=last,
+    };
+
+    let camera_path = if let Some(path) = options.camera_path.as_ref() {
+        Some(CameraPath::load(path, v_up, ASPECT_RATIO, aperture).unwrap())
+    } else if options.turntable {
+        let mut bound_box = AxisAlignedBoundingBox { minimum: look_at, maximum: look_at };
+        for obj in build_world(first_frame as f32).iter() {
+            if let Some(obj_box) = obj.bounding_box(first_frame as f32, first_frame as f32) {
+                bound_box = AxisAlignedBoundingBox::surrounding_box(bound_box, obj_box);
+            }
+        }
+        let center = 0.5 * (bound_box.minimum + bound_box.maximum);
+        let extent = (bound_box.maximum - bound_box.minimum).length();
+        Some(CameraPath::turntable(center, extent, 0.25 * extent, v_fov, first_frame, last_frame, v_up, ASPECT_RATIO))
+    } else {
+        None
+    };
+
+    for frame in frames {
+        let file_name = format!("{}_{:04}.png", options.output, frame);
+        if Path::new(&file_name).exists() {
+            println!("frame {}: {} exists, skipping", frame, file_name);
+            continue;
+        }
+
+        let time0 = frame as f32;
+        let time1 = time0 + SHUTTER;
+        let camera: Arc<dyn Camera> = match (camera_path.as_ref(), scene_camera.as_ref()) {
+            (Some(path), _) => Arc::new(path.camera_at(time0, SHUTTER)),
+            (None, Some(camera)) => camera.with_shutter(time0, time1),
+            (None, None) => Arc::new(PerspectiveCamera::new(look_from, look_at, v_up, v_fov, ASPECT_RATIO, aperture, focus_dist, time0, time1)),
+        };
+
+        let (world, bvh_stats) = build_bvh(build_world(time0), time0, time1, options.split_method);
+        println!("frame {}: {}", frame, bvh_stats);
+        render(&settings, camera, world, light_list.clone(), background).save(&file_name).unwrap();
+        println!("frame {}: wrote {}", frame, file_name);
+    }
 }
@@ -0,0 +1,267 @@
+use std::sync::Arc;
+
+use crate::geometry::color::Color;
+use crate::geometry::onb::ONB;
+use crate::geometry::pdf::PDF;
+use crate::geometry::ray::Ray;
+use crate::geometry::spectrum::Wavelengths;
+use crate::geometry::vector::Vector3;
+use crate::materials::material::{fresnel_dielectric, Material, ScatterRecord};
+use crate::materials::microfacet::{MicrofacetReflectionPDF, reflect, refract, shading_frame, to_local, TrowbridgeReitz};
+use crate::objects::hittable::HitRecord;
+use crate::utils::random_f32;
+
+// Bounces inside the coating before a path still trapped there is given up as absorbed.
+const MAX_INTERNAL_BOUNCES: u32 = 16;
+
+// A dielectric coating over any other material, for car paint, varnished wood and glossy plastic. Light either
+// reflects off the coat or refracts in and random-walks between the base and the underside of the coat, losing
+// some of itself to absorption on every pass and to Fresnel at every attempt to get out, until it leaves.
+// A rough coat's own reflection is a glossy lobe that light sampling can aim at. The walk under the coat is
+// followed stochastically and leaves as a specular ray, so light sampling doesn't reach the base.
+pub struct Coated {
+    pub base: Arc<dyn Material>,
+    pub refractive_index: f32,
+    pub distribution: TrowbridgeReitz,
+    pub thickness: f32,
+    // Absorption coefficient per unit length in the coating, black for a clear coat.
+    pub absorption: Color,
+}
+
+impl Coated {
+    // Clear coat over `base` with perceptual roughness from 0 (polished) to 1.
+    pub fn new(base: Arc<dyn Material>, refractive_index: f32, roughness: f32) -> Self {
+        return Self {
+            base,
+            refractive_index,
+            distribution: TrowbridgeReitz::from_roughness(roughness, roughness),
+            thickness: 0.0,
+            absorption: Color::BLACK,
+        };
+    }
+
+    // Coat of `thickness` that lets through `color` of the light crossing it straight on.
+    pub fn tinted(base: Arc<dyn Material>, refractive_index: f32, roughness: f32, color: Color, thickness: f32) -> Self {
+        let coefficient = |c: f32| -c.clamp(1e-4, 1.0).ln() / thickness;
+        return Self {
+            absorption: Color { r: coefficient(color.r), g: coefficient(color.g), b: coefficient(color.b) },
+            thickness,
+            ..Self::new(base, refractive_index, roughness)
+        };
+    }
+
+    // Fraction of light left after crossing the coating along `w`.
+    fn transmittance(&self, w: Vector3) -> Color {
+        let distance = self.thickness / w.z.abs().max(1e-4);
+        return Color {
+            r: (-self.absorption.r * distance).exp(),
+            g: (-self.absorption.g * distance).exp(),
+            b: (-self.absorption.b * distance).exp(),
+        };
+    }
+
+    // Light arriving from `wo` meets the coat's surface, with `eta` the index ratio across it. Returns the direction
+    // it carries on in, whether it crossed, and the masking weight for that direction.
+    fn interface(&self, wo: Vector3, eta: f32) -> (Vector3, bool, f32) {
+        let h = if self.distribution.is_smooth() {
+            Vector3 { x: 0.0, y: 0.0, z: wo.z.signum() }
+        } else {
+            self.distribution.sample_visible_normal(wo)
+        };
+        let weight = |wi: Vector3| -> f32 {
+            if self.distribution.is_smooth() { 1.0 } else { self.distribution.g1(wi) }
+        };
+
+        if random_f32() >= fresnel_dielectric(wo.dot(h), eta) {
+            if let Some(wi) = refract(wo, h, eta) {
+                if wi.z * wo.z < 0.0 {
+                    return (wi, true, weight(wi));
+                }
+            }
+        }
+        let wi = reflect(wo, h);
+        return (wi, false, weight(wi));
+    }
+
+    // Chance that a rough coat's reflection lobe is sampled rather than the walk beneath it: the coat's Fresnel
+    // reflectance, but at least a quarter so that light sampling finds small lights in the highlight.
+    fn reflection_probability(&self, wo: Vector3) -> f32 {
+        return fresnel_dielectric(wo.z.max(0.0), self.refractive_index).clamp(0.25, 0.75);
+    }
+
+    // Refracts light arriving from `wo` into a rough coat, with the weight of the part that gets through.
+    fn refract_in(&self, wo: Vector3) -> Option<(Vector3, f32)> {
+        let h = self.distribution.sample_visible_normal(wo);
+        let wi = refract(wo, h, self.refractive_index)?;
+        if wi.z >= 0.0 {
+            return Option::None;
+        }
+        return Option::from((wi, (1.0 - fresnel_dielectric(wo.dot(h), self.refractive_index)) * self.distribution.g1(wi)));
+    }
+
+    // Scatters light heading down through the coating along `wi` off the base. Returns the direction it leaves the
+    // base in, the base's weight for it and the wavelengths the base carries on with.
+    fn scatter_base(&self, frame: &ONB, wi: Vector3, time: f32, wavelengths: Option<Wavelengths>, hit_rec: &HitRecord) -> Option<(Vector3, Color, Option<Wavelengths>)> {
+        let base_ray = Ray { origin: hit_rec.intersection, direction: frame.local(wi), time, wavelengths, absorption: None };
+        let scatter_rec = self.base.scatter(base_ray, hit_rec)?;
+        if let Some(specular_ray) = scatter_rec.specular_ray {
+            return Option::from((to_local(frame, specular_ray.direction.direction()), scatter_rec.attenuation, specular_ray.wavelengths));
+        }
+
+        let pdf = scatter_rec.pdf_ptr.clone()?;
+        let direction = pdf.generate().direction();
+        let pdf_val = pdf.value(direction);
+        if pdf_val <= 0.0 {
+            return Option::None;
+        }
+        let scattered_ray = Ray { origin: hit_rec.intersection, direction, time, wavelengths, absorption: None };
+        let weight = (1.0 / pdf_val) * self.base.scattering(base_ray, hit_rec, &scatter_rec, scattered_ray);
+        return Option::from((to_local(frame, direction), weight, wavelengths));
+    }
+}
+
+impl Material for Coated {
+    fn scatter(&self, in_ray: Ray, hit_rec: &HitRecord) -> Option<ScatterRecord> {
+        // The coating only covers the outside.
+        if !hit_rec.front_face {
+            return self.base.scatter(in_ray, hit_rec);
+        }
+
+        let frame = shading_frame(hit_rec);
+        let wo = to_local(&frame, -in_ray.direction.direction());
+        let (mut w, entered, mut attenuation) = if self.distribution.is_smooth() {
+            let (w, entered, weight) = self.interface(wo, self.refractive_index);
+            (w, entered, weight * Color::WHITE)
+        } else {
+            let reflection_probability = self.reflection_probability(wo);
+            if random_f32() < reflection_probability {
+                return Option::from(ScatterRecord {
+                    specular_ray: None,
+                    attenuation: Color::WHITE,
+                    pdf_ptr: Option::from(Arc::new(MicrofacetReflectionPDF { frame, wo, distribution: self.distribution }) as Arc<dyn PDF>),
+                    lobes: Vec::new(),
+                });
+            }
+            let (w, weight) = self.refract_in(wo)?;
+            (w, true, (weight / (1.0 - reflection_probability)) * Color::WHITE)
+        };
+        let mut wavelengths = in_ray.wavelengths;
+
+        if entered {
+            let mut escaped = false;
+            for _ in 0..MAX_INTERNAL_BOUNCES {
+                // Down through the coating onto the base, and back up to the coat's underside.
+                attenuation = attenuation * self.transmittance(w);
+                let (up, base_weight, base_wavelengths) = self.scatter_base(&frame, w, in_ray.time, wavelengths, hit_rec)?;
+                if up.z <= 0.0 {
+                    return Option::None;
+                }
+                attenuation = attenuation * base_weight * self.transmittance(up);
+                wavelengths = base_wavelengths;
+
+                let (next, crossed, interface_weight) = self.interface(-up, 1.0 / self.refractive_index);
+                w = next;
+                attenuation = interface_weight * attenuation;
+                if crossed {
+                    escaped = true;
+                    break;
+                }
+            }
+            if !escaped {
+                return Option::None;
+            }
+        }
+        // Rough coats can reflect below the horizon, where nothing scatters.
+        if w.z <= 0.0 {
+            return Option::None;
+        }
+
+        return Option::from(ScatterRecord {
+            specular_ray: Option::from(Ray { origin: hit_rec.intersection, direction: frame.local(w), time: in_ray.time, wavelengths, absorption: in_ray.absorption }),
+            attenuation,
+            pdf_ptr: None,
+            lobes: Vec::new(),
+        });
+    }
+
+    // Only a rough coat's reflection lobe is valued here; everything else scatters as a specular ray.
+    fn scattering_pdf(&self, in_ray: Ray, hit_rec: &HitRecord, scattered_ray: Ray) -> f32 {
+        if !hit_rec.front_face {
+            return self.base.scattering_pdf(in_ray, hit_rec, scattered_ray);
+        }
+        let frame = shading_frame(hit_rec);
+        let wo = to_local(&frame, -in_ray.direction.direction());
+        return MicrofacetReflectionPDF { frame, wo, distribution: self.distribution }.value(scattered_ray.direction);
+    }
+
+    // D G F / (4 cos_o) of the coat's dielectric reflection, over the chance of having sampled it.
+    fn scattering(&self, in_ray: Ray, hit_rec: &HitRecord, scatter_rec: &ScatterRecord, scattered_ray: Ray) -> Color {
+        if !hit_rec.front_face {
+            return self.base.scattering(in_ray, hit_rec, scatter_rec, scattered_ray);
+        }
+        let frame = shading_frame(hit_rec);
+        let wo = to_local(&frame, -in_ray.direction.direction());
+        let wi = to_local(&frame, scattered_ray.direction.direction());
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Color::BLACK;
+        }
+        let h = (wo + wi).direction();
+        let fresnel = fresnel_dielectric(wo.dot(h), self.refractive_index);
+        let weight = self.distribution.d(h) * self.distribution.g(wo, wi) * fresnel / (4.0 * wo.z);
+        return (weight / self.reflection_probability(wo)) * Color::WHITE;
+    }
+
+    // A rough coat picks between its reflection lobe and the walk beneath it.
+    fn scatters_stochastically(&self) -> bool {
+        return !self.distribution.is_smooth() || self.base.scatters_stochastically();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::vector::Point;
+    use crate::materials::lambertian::Lambertian;
+
+    use super::*;
+
+    fn ray_along(direction: Vector3) -> Ray {
+        return Ray { origin: Point::ORIGIN, direction, time: 0.0, wavelengths: None, absorption: None };
+    }
+
+    // A clear coat over a white base sends back all the light a furnace shines on it when polished, less what
+    // shadowing under a rough coat costs, but never more than arrives.
+    #[test]
+    fn clear_coat_over_white_never_reflects_more_than_arrives() {
+        for &roughness in [0.0, 0.3, 0.8].iter() {
+            let material: Arc<dyn Material> = Arc::new(Coated::new(Arc::new(Lambertian::new(Color::WHITE)), 1.5, roughness));
+            let hit_rec = HitRecord { intersection: Point::ORIGIN, normal: Vector3 { x: 0.0, y: 0.0, z: 1.0 }, material: material.clone(), t: 1.0, u: 0.0, v: 0.0, front_face: true, tangent: None };
+            for &cos_o in [1.0f32, 0.5, 0.1].iter() {
+                let in_ray = ray_along(-Vector3 { x: (1.0 - cos_o * cos_o).sqrt(), y: 0.0, z: cos_o });
+
+                let samples = 50_000;
+                let mut albedo = 0.0;
+                for _ in 0..samples {
+                    let scatter_rec = match material.scatter(in_ray, &hit_rec) {
+                        Some(scatter_rec) => scatter_rec,
+                        None => continue,
+                    };
+                    if scatter_rec.specular_ray.is_some() {
+                        albedo += scatter_rec.attenuation.r;
+                        continue;
+                    }
+                    let pdf = scatter_rec.pdf_ptr.clone().unwrap();
+                    let scattered = ray_along(pdf.generate());
+                    let pdf_value = pdf.value(scattered.direction);
+                    if pdf_value > 0.0 {
+                        albedo += material.scattering(in_ray, &hit_rec, &scatter_rec, scattered).r / pdf_value;
+                    }
+                }
+                albedo /= samples as f32;
+                assert!(albedo < 1.02, "roughness {}, cos_o {}: albedo {}", roughness, cos_o, albedo);
+                if roughness == 0.0 {
+                    assert!(albedo > 0.98, "cos_o {}: albedo {}", cos_o, albedo);
+                }
+            }
+        }
+    }
+}
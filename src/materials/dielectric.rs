@@ -5,15 +5,80 @@ use crate::materials::material::{Material, reflect_ray, reflectance_schlick, ref
 use crate::objects::hittable::HitRecord;
 use crate::utils::random_f32;
 
+// Smooth glass. Light travelling inside it is absorbed by Beer-Lambert's law, so thick tinted glass looks
+// darker than thin glass; `absorption` is the coefficient per unit length for each channel, black for clear glass.
 pub struct Dielectric {
     pub refractive_index: f32,
+    pub absorption: Color,
+    // How the refractive index varies with wavelength, followed in spectral mode. RGB rendering uses
+    // `refractive_index` throughout.
+    pub dispersion: Option<Dispersion>,
+}
+
+// Refractive index as a function of wavelength. Coefficients take wavelengths in micrometres.
+#[derive(Clone, Copy)]
+pub enum Dispersion {
+    // n = a + b / λ², a good fit for most glasses across the visible range.
+    Cauchy { a: f32, b: f32 },
+    // n² = 1 + Σ b_i λ² / (λ² - c_i), as given in optical glass catalogues.
+    Sellmeier { b: [f32; 3], c: [f32; 3] },
+}
+
+impl Dispersion {
+    pub const BK7: Dispersion = Dispersion::Sellmeier {
+        b: [1.039_612_1, 0.231_792_34, 1.010_469_5],
+        c: [0.006_000_699, 0.020_017_914, 103.560_65],
+    };
+    pub const DIAMOND: Dispersion = Dispersion::Sellmeier { b: [4.3356, 0.3306, 0.0], c: [0.011_236, 0.030_625, 0.0] };
+    // Dense flint glass (SF11), fitted with Cauchy's two terms.
+    pub const FLINT: Dispersion = Dispersion::Cauchy { a: 1.728, b: 0.013_42 };
+
+    pub fn refractive_index(&self, lambda_nm: f32) -> f32 {
+        let lambda2 = (lambda_nm / 1000.0).powi(2);
+        return match self {
+            Dispersion::Cauchy { a, b } => a + b / lambda2,
+            Dispersion::Sellmeier { b, c } => {
+                (1.0 + (0..3).map(|i| b[i] * lambda2 / (lambda2 - c[i])).sum::<f32>()).sqrt()
+            }
+        };
+    }
+}
+
+impl Dielectric {
+    pub fn new(refractive_index: f32) -> Self {
+        return Self { refractive_index, absorption: Color::BLACK, dispersion: None };
+    }
+
+    // Glass whose refractive index follows `dispersion`, with its index at 550nm for RGB rendering.
+    pub fn dispersive(dispersion: Dispersion) -> Self {
+        return Self { refractive_index: dispersion.refractive_index(550.0), absorption: Color::BLACK, dispersion: Some(dispersion) };
+    }
+
+    // Glass that lets through `color` of the light after it has travelled `distance` inside. Rays pick up the
+    // absorption when they refract into the glass, so paths that start inside it, or leave a piece of glass nested
+    // in it, go unabsorbed until they next enter.
+    pub fn tinted(refractive_index: f32, color: Color, distance: f32) -> Self {
+        let coefficient = |c: f32| -c.clamp(1e-4, 1.0).ln() / distance;
+        return Self {
+            refractive_index,
+            absorption: Color { r: coefficient(color.r), g: coefficient(color.g), b: coefficient(color.b) },
+            dispersion: None,
+        };
+    }
 }
 
 impl Material for Dielectric {
     fn scatter(&self, in_ray: Ray, hit_rec: &HitRecord) -> Option<ScatterRecord> {
-        let mut ir = 1.0 / self.refractive_index;
+        // A dispersive interface bends each wavelength differently, so only the hero wavelength carries on.
+        let (refractive_index, wavelengths) = match (self.dispersion, in_ray.wavelengths) {
+            (Some(dispersion), Some(wavelengths)) => {
+                (dispersion.refractive_index(wavelengths.hero()), Some(wavelengths.terminate_secondary()))
+            }
+            _ => (self.refractive_index, in_ray.wavelengths),
+        };
+        let mut ir = 1.0 / refractive_index;
         if !hit_rec.front_face {
-            ir = self.refractive_index;
+            ir = refractive_index;
         }
 
         let cos_0 = -in_ray.direction.dot(hit_rec.normal).min(1.0);
@@ -21,16 +86,68 @@ impl Material for Dielectric {
 
         let is_refracted = ir * sin_0 <= 1.0;
         let mut direction = Vector3::ORIGIN;
+        let mut absorption = in_ray.absorption;
         if !is_refracted || reflectance_schlick(cos_0, ir) > random_f32() {
             direction = reflect_ray(in_ray.direction, hit_rec.normal);
         } else {
             direction = refract(in_ray.direction, hit_rec.normal, ir);
+            // Refracting in, the ray takes on the glass's absorption, and refracting out it leaves it behind.
+            // Either way, light is absorbed along each segment of the path through the glass as it is traced.
+            absorption = if hit_rec.front_face { Some(self.absorption) } else { None };
         }
 
         return Option::from(ScatterRecord {
-            specular_ray: Option::from(Ray { origin: hit_rec.intersection, direction, time: in_ray.time }),
+            specular_ray: Option::from(Ray { origin: hit_rec.intersection, direction, time: in_ray.time, wavelengths, absorption }),
             attenuation: Color::WHITE,
             pdf_ptr: None,
+            lobes: Vec::new(),
         });
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn head_on_hit(glass: Arc<Dielectric>, front_face: bool) -> HitRecord {
+        return HitRecord {
+            intersection: Point::ORIGIN,
+            normal: Vector3 { x: 0.0, y: 0.0, z: 1.0 },
+            material: glass,
+            t: 1.0,
+            u: 0.0,
+            v: 0.0,
+            front_face,
+            tangent: None,
+        };
+    }
+
+    // Scatters a downward ray until it carries on down through the surface, or until it turns back up.
+    fn scatter_until(glass: &Arc<Dielectric>, in_ray: Ray, hit_rec: &HitRecord, refracted: bool) -> Ray {
+        loop {
+            let ray = glass.scatter(in_ray, hit_rec).unwrap().specular_ray.unwrap();
+            if (ray.direction.z < 0.0) == refracted {
+                return ray;
+            }
+        }
+    }
+
+    #[test]
+    fn rays_are_absorbed_along_their_path_inside_tinted_glass() {
+        let glass = Arc::new(Dielectric::tinted(1.5, Color { r: 0.5, g: 0.8, b: 1.0 }, 2.0));
+        let in_ray = Ray { origin: Point { x: 0.0, y: 0.0, z: 1.0 }, direction: Vector3 { x: 0.0, y: 0.0, z: -1.0 }, time: 0.0, wavelengths: None, absorption: None };
+
+        let inside = scatter_until(&glass, in_ray, &head_on_hit(glass.clone(), true), true);
+        // Every segment inside counts, e.g. two of length one either side of smoke in the glass.
+        let transmitted = inside.transmittance(1.0) * inside.transmittance(1.0);
+        assert!((transmitted.r - 0.5).abs() < 1e-5 && (transmitted.g - 0.8).abs() < 1e-5 && (transmitted.b - 1.0).abs() < 1e-5);
+
+        // Light reflected inside stays in the glass, and light refracted out is left alone.
+        let reflected = scatter_until(&glass, inside, &head_on_hit(glass.clone(), false), false);
+        assert!((reflected.transmittance(2.0).r - 0.5).abs() < 1e-5);
+        let outside = scatter_until(&glass, inside, &head_on_hit(glass.clone(), false), true);
+        assert!(outside.absorption.is_none());
+        assert_eq!(outside.transmittance(10.0).r, 1.0);
+    }
+}
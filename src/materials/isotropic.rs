@@ -14,9 +14,10 @@ pub struct Isotropic {
 impl Material for Isotropic {
     fn scatter(&self, in_ray: Ray, hit_rec: &HitRecord) -> Option<ScatterRecord> {
         return Option::from(ScatterRecord {
-            specular_ray: Option::from(Ray { origin: hit_rec.intersection, direction: Vector3::random_unit_vector(), time: in_ray.time }),
+            specular_ray: Option::from(Ray { origin: hit_rec.intersection, direction: Vector3::random_unit_vector(), time: in_ray.time, wavelengths: in_ray.wavelengths, absorption: in_ray.absorption }),
             attenuation: self.albedo.color(hit_rec.u, hit_rec.v, hit_rec.intersection),
             pdf_ptr: None,
+            lobes: Vec::new(),
         });
     }
 }
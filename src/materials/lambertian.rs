@@ -29,6 +29,7 @@ impl Material for Lambertian {
             specular_ray: None,
             attenuation: self.albedo.color(hit_rec.u, hit_rec.v, hit_rec.intersection),
             pdf_ptr: Option::from(Arc::new(CosinePDF::new(hit_rec.normal)) as Arc<dyn PDF>),
+            lobes: Vec::new(),
         });
     }
 
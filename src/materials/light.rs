@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use crate::geometry::color::Color;
 use crate::geometry::ray::Ray;
-use crate::geometry::vector::Point;
+use crate::geometry::vector::{Point, Vector3};
 use crate::materials::material::Material;
 use crate::objects::hittable::HitRecord;
 use crate::textures::solid::SolidColor;
@@ -29,3 +29,44 @@ impl Material for DiffuseLight {
         };
     }
 }
+
+// Emits only into a cone around `axis`, fading smoothly from the inner cone's edge out to the outer one's.
+pub struct SpotLight {
+    pub emit: Arc<dyn Texture>,
+    pub axis: Vector3,
+    pub cos_inner: f32,
+    pub cos_outer: f32,
+}
+
+impl SpotLight {
+    // Cone angles are in radians from the axis.
+    pub fn new(color: Color, axis: Vector3, inner_angle: f32, outer_angle: f32) -> Self {
+        return Self {
+            emit: Arc::new(SolidColor { color }),
+            axis: axis.direction(),
+            cos_inner: inner_angle.cos(),
+            cos_outer: outer_angle.cos(),
+        };
+    }
+
+    fn falloff(&self, cos_0: f32) -> f32 {
+        if self.cos_inner <= self.cos_outer {
+            return if cos_0 >= self.cos_outer { 1.0 } else { 0.0 };
+        }
+        let t = ((cos_0 - self.cos_outer) / (self.cos_inner - self.cos_outer)).clamp(0.0, 1.0);
+        return t * t * (3.0 - 2.0 * t);
+    }
+}
+
+impl Material for SpotLight {
+    fn emitted(&self, ray: Ray, hit_rec: &HitRecord, u: f32, v: f32, intersection: Point) -> Color {
+        if !hit_rec.front_face {
+            return Color::BLACK;
+        }
+        let falloff = self.falloff(-ray.direction.direction().dot(self.axis));
+        if falloff <= 0.0 {
+            return Color::BLACK;
+        }
+        return falloff * self.emit.color(u, v, intersection);
+    }
+}
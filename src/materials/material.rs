@@ -10,6 +10,8 @@ pub struct ScatterRecord {
     pub specular_ray: Option<Ray>,
     pub attenuation: Color,
     pub pdf_ptr: Option<Arc<dyn PDF>>,
+    // Lobes of a blended material that `pdf_ptr` mixes, each with its own record and share of the mixture.
+    pub lobes: Vec<(Arc<dyn Material>, ScatterRecord, f32)>,
 }
 
 pub trait Material: Send + Sync {
@@ -19,9 +21,19 @@ pub trait Material: Send + Sync {
     fn scattering_pdf(&self, in_ray: Ray, hit_rec: &HitRecord, scattered_ray: Ray) -> f32 {
         return 0.0;
     }
+    // Light scattered along `scattered_ray` per unit of light arriving from it, i.e. the BSDF times the cosine.
+    // Materials with a plain colour get it from `scattering_pdf`; microfacet materials work out the colour per direction.
+    fn scattering(&self, in_ray: Ray, hit_rec: &HitRecord, scatter_rec: &ScatterRecord, scattered_ray: Ray) -> Color {
+        return self.scattering_pdf(in_ray, hit_rec, scattered_ray) * scatter_rec.attenuation;
+    }
     fn emitted(&self, ray: Ray, hit_rec: &HitRecord, u: f32, v: f32, intersection: Point) -> Color {
         return Color::BLACK;
     }
+    // Whether `scatter` chooses at random between a pdf lobe and a specular ray, so that calling it again for the
+    // same hit can return a different kind of record.
+    fn scatters_stochastically(&self) -> bool {
+        return false;
+    }
 }
 
 pub fn reflect_ray(in_direction: Vector3, normal: Vector3) -> Vector3 {
@@ -39,4 +51,81 @@ pub fn reflectance_schlick(cos_0: f32, ir: f32) -> f32 {
     let mut r0 = (1.0 - ir) / (1.0 + ir);
     r0 = r0.powi(2);
     return r0 + (1.0 - r0) * (1.0 - cos_0).powi(5);
-}
\ No newline at end of file
+}
+
+// Exact Fresnel reflectance of a conductor with complex refractive index eta + ik, for one wavelength.
+pub fn fresnel_conductor(cos_0: f32, eta: f32, k: f32) -> f32 {
+    let cos2 = cos_0.clamp(0.0, 1.0).powi(2);
+    let sin2 = 1.0 - cos2;
+    let t0 = eta.powi(2) - k.powi(2) - sin2;
+    let a2_plus_b2 = (t0.powi(2) + 4.0 * eta.powi(2) * k.powi(2)).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * cos_0 * a;
+    let rs = (t1 - t2) / (t1 + t2);
+    let t3 = cos2 * a2_plus_b2 + sin2.powi(2);
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+    return 0.5 * (rp + rs);
+}
+
+// Complex refractive index giving reflectivity `r` head-on and tinting grazing reflections towards `edge_tint`
+// (Gulbrandsen 2014), so that metals can be picked by colour.
+pub fn artist_conductor(r: f32, edge_tint: f32) -> (f32, f32) {
+    let r = r.clamp(0.0, 0.99);
+    let eta = edge_tint * (1.0 - r) / (1.0 + r) + (1.0 - edge_tint) * (1.0 + r.sqrt()) / (1.0 - r.sqrt());
+    let k = ((r * (eta + 1.0).powi(2) - (eta - 1.0).powi(2)) / (1.0 - r)).max(0.0).sqrt();
+    return (eta, k);
+}
+
+// Exact Fresnel reflectance of unpolarised light meeting a dielectric interface, where `eta` is the refractive
+// index on the far side over the index on the near side. Returns 1 under total internal reflection.
+pub fn fresnel_dielectric(cos_0: f32, eta: f32) -> f32 {
+    let cos_i = cos_0.clamp(0.0, 1.0);
+    let sin2_t = (1.0 - cos_i.powi(2)) / eta.powi(2);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let r_parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    return 0.5 * (r_parallel.powi(2) + r_perpendicular.powi(2));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dielectric_fresnel_known_values() {
+        assert!((fresnel_dielectric(1.0, 1.5) - 0.04).abs() < 1e-6);
+        assert!((fresnel_dielectric(0.0, 1.5) - 1.0).abs() < 1e-6);
+        // At Brewster's angle only the perpendicular polarisation reflects.
+        let brewster = 1.0 / (1.0f32 + 1.5 * 1.5).sqrt();
+        assert!((fresnel_dielectric(brewster, 1.5) - 0.073_964).abs() < 1e-5);
+        // Leaving glass beyond the critical angle reflects everything.
+        assert_eq!(fresnel_dielectric(0.5, 1.0 / 1.5), 1.0);
+    }
+
+    #[test]
+    fn conductor_fresnel_reduces_to_known_cases() {
+        // Head on, ((n - 1)² + k²) / ((n + 1)² + k²).
+        let (eta, k) = (0.18, 3.1);
+        let expected = ((eta - 1.0f32).powi(2) + k * k) / ((eta + 1.0f32).powi(2) + k * k);
+        assert!((fresnel_conductor(1.0, eta, k) - expected).abs() < 1e-5);
+        assert!((fresnel_conductor(0.0, eta, k) - 1.0).abs() < 1e-5);
+
+        // Without absorption it is a dielectric.
+        for &cos in [0.1, 0.4, 0.7, 1.0].iter() {
+            assert!((fresnel_conductor(cos, 1.5, 0.0) - fresnel_dielectric(cos, 1.5)).abs() < 1e-5, "cos {}", cos);
+        }
+    }
+
+    #[test]
+    fn artist_conductor_reflects_its_colour_head_on() {
+        for &(r, edge_tint) in [(0.95, 0.9), (0.56, 0.7), (0.2, 0.1)].iter() {
+            let (eta, k) = artist_conductor(r, edge_tint);
+            assert!((fresnel_conductor(1.0, eta, k) - r).abs() < 1e-4, "r {}, edge tint {}", r, edge_tint);
+        }
+    }
+}
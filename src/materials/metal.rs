@@ -1,22 +1,138 @@
+use std::sync::Arc;
+
 use crate::geometry::color::Color;
 use crate::geometry::ray::Ray;
-use crate::geometry::vector::Point;
-use crate::materials::material::{Material, reflect_ray, ScatterRecord};
+use crate::materials::material::{artist_conductor, fresnel_conductor, Material, reflect_ray, ScatterRecord};
+use crate::materials::microfacet::{MicrofacetReflectionPDF, shading_frame, to_local, TrowbridgeReitz};
 use crate::objects::hittable::HitRecord;
-use crate::utils::random_in_unit_sphere;
 
+// Conductor with GGX microfacets. The colour comes from the Fresnel reflectance of the complex refractive
+// index eta + ik per channel, and roughness may differ along and across the surface tangent.
 pub struct Metal {
-    pub color: Color,
-    pub fuzz: f32,
+    pub eta: Color,
+    pub k: Color,
+    pub distribution: TrowbridgeReitz,
+}
+
+impl Metal {
+    // Metal reflecting `color` head-on, with perceptual roughness from 0 (mirror) to 1.
+    pub fn new(color: Color, roughness: f32) -> Self {
+        return Self::artistic(color, color, roughness, roughness);
+    }
+
+    // Metal reflecting `color` head-on and `edge_tint` towards grazing angles, with separate roughness along
+    // and across the tangent.
+    pub fn artistic(color: Color, edge_tint: Color, roughness_u: f32, roughness_v: f32) -> Self {
+        let (eta_r, k_r) = artist_conductor(color.r, edge_tint.r);
+        let (eta_g, k_g) = artist_conductor(color.g, edge_tint.g);
+        let (eta_b, k_b) = artist_conductor(color.b, edge_tint.b);
+        return Self {
+            eta: Color { r: eta_r, g: eta_g, b: eta_b },
+            k: Color { r: k_r, g: k_g, b: k_b },
+            distribution: TrowbridgeReitz::from_roughness(roughness_u, roughness_v),
+        };
+    }
+
+    // Metal from measured optical constants, e.g. gold at red, green and blue wavelengths.
+    pub fn from_ior(eta: Color, k: Color, roughness_u: f32, roughness_v: f32) -> Self {
+        return Self { eta, k, distribution: TrowbridgeReitz::from_roughness(roughness_u, roughness_v) };
+    }
+
+    fn fresnel(&self, cos_0: f32) -> Color {
+        return Color {
+            r: fresnel_conductor(cos_0, self.eta.r, self.k.r),
+            g: fresnel_conductor(cos_0, self.eta.g, self.k.g),
+            b: fresnel_conductor(cos_0, self.eta.b, self.k.b),
+        };
+    }
 }
 
 impl Material for Metal {
     fn scatter(&self, in_ray: Ray, hit_rec: &HitRecord) -> Option<ScatterRecord> {
-        let reflected_direction = reflect_ray(in_ray.direction, hit_rec.normal) + self.fuzz * random_in_unit_sphere();
+        let in_direction = in_ray.direction.direction();
+
+        if self.distribution.is_smooth() {
+            let reflected_direction = reflect_ray(in_direction, hit_rec.normal);
+            return Option::from(ScatterRecord {
+                specular_ray: Option::from(Ray { origin: hit_rec.intersection, direction: reflected_direction, time: in_ray.time, wavelengths: in_ray.wavelengths, absorption: in_ray.absorption }),
+                attenuation: self.fresnel(-in_direction.dot(hit_rec.normal)),
+                pdf_ptr: None,
+                lobes: Vec::new(),
+            });
+        }
+
+        let frame = shading_frame(hit_rec);
+        let wo = to_local(&frame, -in_direction);
         return Option::from(ScatterRecord {
-            specular_ray: Option::from(Ray { origin: hit_rec.intersection, direction: reflected_direction, time: in_ray.time }),
-            attenuation: self.color,
-            pdf_ptr: None,
+            specular_ray: None,
+            attenuation: Color::WHITE,
+            pdf_ptr: Option::from(Arc::new(MicrofacetReflectionPDF { frame, wo, distribution: self.distribution }) as Arc<_>),
+            lobes: Vec::new(),
         });
     }
-}
\ No newline at end of file
+
+    fn scattering_pdf(&self, in_ray: Ray, hit_rec: &HitRecord, scattered_ray: Ray) -> f32 {
+        let frame = shading_frame(hit_rec);
+        let wo = to_local(&frame, -in_ray.direction.direction());
+        let wi = to_local(&frame, scattered_ray.direction.direction());
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+        let h = (wo + wi).direction();
+        return self.distribution.visible_normal_pdf(wo, h) / (4.0 * wo.dot(h));
+    }
+
+    // D G F / (4 cos_o), the Torrance-Sparrow BRDF times the cosine at the light.
+    fn scattering(&self, in_ray: Ray, hit_rec: &HitRecord, _scatter_rec: &ScatterRecord, scattered_ray: Ray) -> Color {
+        let frame = shading_frame(hit_rec);
+        let wo = to_local(&frame, -in_ray.direction.direction());
+        let wi = to_local(&frame, scattered_ray.direction.direction());
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Color::BLACK;
+        }
+        let h = (wo + wi).direction();
+        let weight = self.distribution.d(h) * self.distribution.g(wo, wi) / (4.0 * wo.z);
+        return weight * self.fresnel(wo.dot(h));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::vector::{Point, Vector3};
+
+    use super::*;
+
+    const GOLD_ETA: Color = Color { r: 0.143, g: 0.374, b: 1.442 };
+    const GOLD_K: Color = Color { r: 3.983, g: 2.385, b: 1.603 };
+
+    // The colour a polished mirror reflects looking straight at it.
+    fn head_on_reflectance(metal: Metal) -> Color {
+        let material: Arc<dyn Material> = Arc::new(metal);
+        let hit_rec = HitRecord { intersection: Point::ORIGIN, normal: Vector3 { x: 0.0, y: 0.0, z: 1.0 }, material: material.clone(), t: 1.0, u: 0.0, v: 0.0, front_face: true, tangent: None };
+        let in_ray = Ray { origin: Point { x: 0.0, y: 0.0, z: 1.0 }, direction: Vector3 { x: 0.0, y: 0.0, z: -1.0 }, time: 0.0, wavelengths: None, absorption: None };
+        return material.scatter(in_ray, &hit_rec).unwrap().attenuation;
+    }
+
+    // Head on, the conductor Fresnel equations reduce to ((eta - 1)² + k²) / ((eta + 1)² + k²).
+    #[test]
+    fn gold_reflects_its_normal_incidence_fresnel() {
+        let reflectance = head_on_reflectance(Metal::from_ior(GOLD_ETA, GOLD_K, 0.0, 0.0));
+        let channels = [(reflectance.r, GOLD_ETA.r, GOLD_K.r), (reflectance.g, GOLD_ETA.g, GOLD_K.g), (reflectance.b, GOLD_ETA.b, GOLD_K.b)];
+        for &(actual, eta, k) in channels.iter() {
+            let expected = ((eta - 1.0).powi(2) + k.powi(2)) / ((eta + 1.0).powi(2) + k.powi(2));
+            assert!((actual - fresnel_conductor(1.0, eta, k)).abs() < 1e-6, "{} vs {}", actual, fresnel_conductor(1.0, eta, k));
+            assert!((actual - expected).abs() < 1e-4, "{} vs {}", actual, expected);
+        }
+        // Gold's yellow: far more red than blue.
+        assert!(reflectance.r > 0.9 && reflectance.b < 0.5);
+    }
+
+    #[test]
+    fn artistic_metal_reflects_its_colour_head_on() {
+        let color = Color { r: 0.95, g: 0.64, b: 0.54 };
+        let reflectance = head_on_reflectance(Metal::new(color, 0.0));
+        for &(actual, expected) in [(reflectance.r, color.r), (reflectance.g, color.g), (reflectance.b, color.b)].iter() {
+            assert!((actual - expected).abs() < 1e-3, "{} vs {}", actual, expected);
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use crate::geometry::onb::ONB;
+use crate::geometry::pdf::PDF;
+use crate::geometry::vector::Vector3;
+use crate::materials::material::fresnel_dielectric;
+use crate::objects::hittable::HitRecord;
+use crate::utils::{PI, random_f32};
+
+// Below this alpha a surface is treated as perfectly smooth and scattered as a delta.
+pub const SMOOTH_ALPHA: f32 = 1e-3;
+
+// Trowbridge-Reitz (GGX) microfacet distribution, with separate roughness along the shading frame's u and v.
+// Vectors are in the local shading frame, where z is the normal.
+#[derive(Clone, Copy)]
+pub struct TrowbridgeReitz {
+    pub alpha_x: f32,
+    pub alpha_y: f32,
+}
+
+impl TrowbridgeReitz {
+    // Perceptual roughness in [0, 1] maps to alpha = roughness², which spreads the highlight more evenly.
+    pub fn from_roughness(roughness_u: f32, roughness_v: f32) -> Self {
+        return Self { alpha_x: roughness_u.powi(2).max(1e-4), alpha_y: roughness_v.powi(2).max(1e-4) };
+    }
+
+    pub fn is_smooth(&self) -> bool {
+        return self.alpha_x.max(self.alpha_y) < SMOOTH_ALPHA;
+    }
+
+    pub fn d(&self, h: Vector3) -> f32 {
+        if h.z <= 0.0 {
+            return 0.0;
+        }
+        let e = (h.x / self.alpha_x).powi(2) + (h.y / self.alpha_y).powi(2) + h.z.powi(2);
+        return 1.0 / (PI * self.alpha_x * self.alpha_y * e.powi(2));
+    }
+
+    fn lambda(&self, w: Vector3) -> f32 {
+        if w.z == 0.0 {
+            return 0.0;
+        }
+        let tan2 = ((self.alpha_x * w.x).powi(2) + (self.alpha_y * w.y).powi(2)) / w.z.powi(2);
+        return 0.5 * ((1.0 + tan2).sqrt() - 1.0);
+    }
+
+    pub fn g1(&self, w: Vector3) -> f32 {
+        return 1.0 / (1.0 + self.lambda(w));
+    }
+
+    // Height-correlated masking and shadowing.
+    pub fn g(&self, wo: Vector3, wi: Vector3) -> f32 {
+        return 1.0 / (1.0 + self.lambda(wo) + self.lambda(wi));
+    }
+
+    // Density of `sample_visible_normal` returning `h` as seen from `wo`.
+    pub fn visible_normal_pdf(&self, wo: Vector3, h: Vector3) -> f32 {
+        if wo.z <= 0.0 {
+            return 0.0;
+        }
+        return self.g1(wo) * wo.dot(h).max(0.0) * self.d(h) / wo.z;
+    }
+
+    // Samples a microfacet normal in proportion to how much of it `wo` sees (Heitz 2018), so that no
+    // samples are spent on facets facing away from the viewer.
+    pub fn sample_visible_normal(&self, wo: Vector3) -> Vector3 {
+        let flip = wo.z < 0.0;
+        let wo = if flip { -wo } else { wo };
+
+        let vh = Vector3 { x: self.alpha_x * wo.x, y: self.alpha_y * wo.y, z: wo.z }.direction();
+        let length_squared = vh.x.powi(2) + vh.y.powi(2);
+        let t1 = if length_squared > 0.0 {
+            Vector3 { x: -vh.y, y: vh.x, z: 0.0 } / length_squared.sqrt()
+        } else {
+            Vector3 { x: 1.0, y: 0.0, z: 0.0 }
+        };
+        let t2 = vh.cross(t1);
+
+        let r = random_f32().sqrt();
+        let phi = 2.0 * PI * random_f32();
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z);
+        let p2 = (1.0 - s) * (1.0 - p1.powi(2)).sqrt() + s * r * phi.sin();
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1.powi(2) - p2.powi(2)).max(0.0).sqrt() * vh;
+
+        let h = Vector3 { x: self.alpha_x * nh.x, y: self.alpha_y * nh.y, z: nh.z.max(1e-6) }.direction();
+        return if flip { -h } else { h };
+    }
+}
+
+// Shading frame with w along the normal and u along the hit's tangent when it has one, so that anisotropic
+// highlights follow strands and brushed directions.
+pub fn shading_frame(hit_rec: &HitRecord) -> ONB {
+    let w = hit_rec.normal;
+    return match hit_rec.tangent {
+        Some(tangent) => {
+            let u = (tangent - tangent.dot(w) * w).direction();
+            ONB { u, v: w.cross(u), w }
+        }
+        None => ONB::build_from_w(w),
+    };
+}
+
+pub fn to_local(frame: &ONB, v: Vector3) -> Vector3 {
+    return Vector3 { x: v.dot(frame.u), y: v.dot(frame.v), z: v.dot(frame.w) };
+}
+
+pub fn reflect(wo: Vector3, h: Vector3) -> Vector3 {
+    return 2.0 * wo.dot(h) * h - wo;
+}
+
+// Refracts `wo` through the microfacet `h` into a medium `eta` times as dense, or None under total internal reflection.
+pub fn refract(wo: Vector3, h: Vector3, eta: f32) -> Option<Vector3> {
+    let cos_i = wo.dot(h);
+    let sin2_t = (1.0 - cos_i.powi(2)) / eta.powi(2);
+    if sin2_t >= 1.0 {
+        return Option::None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    return Option::from(-wo / eta + (cos_i / eta - cos_t) * h);
+}
+
+// The visible microfacet that reflects `wo` into `wi`, if there is one.
+pub fn reflection_half_vector(wo: Vector3, wi: Vector3) -> Option<Vector3> {
+    let h = wo + wi;
+    if h.length_squared() == 0.0 {
+        return Option::None;
+    }
+    let h = h.direction();
+    if h.z <= 0.0 || wo.dot(h) <= 0.0 {
+        return Option::None;
+    }
+    return Option::from(h);
+}
+
+// The visible microfacet that refracts `wo` into `wi` across an interface `eta` times as dense, if there is one.
+pub fn transmission_half_vector(wo: Vector3, wi: Vector3, eta: f32) -> Option<Vector3> {
+    let mut h = wo + eta * wi;
+    if h.length_squared() == 0.0 {
+        return Option::None;
+    }
+    h = h.direction();
+    if h.z < 0.0 {
+        h = -h;
+    }
+    if h.z == 0.0 || wo.dot(h) <= 0.0 || wi.dot(h) >= 0.0 {
+        return Option::None;
+    }
+    return Option::from(h);
+}
+
+// Jacobian from the microfacet normal to the refracted direction, dh/dwi.
+pub fn transmission_jacobian(wo: Vector3, wi: Vector3, h: Vector3, eta: f32) -> f32 {
+    return wi.dot(h).abs() / (wi.dot(h) + wo.dot(h) / eta).powi(2);
+}
+
+// Directions reflected off visible microfacets, for glossy reflection.
+pub struct MicrofacetReflectionPDF {
+    pub frame: ONB,
+    pub wo: Vector3,
+    pub distribution: TrowbridgeReitz,
+}
+
+impl PDF for MicrofacetReflectionPDF {
+    fn value(&self, direction: Vector3) -> f32 {
+        let wi = to_local(&self.frame, direction.direction());
+        if wi.z * self.wo.z <= 0.0 {
+            return 0.0;
+        }
+        let h = (self.wo + wi).direction();
+        return self.distribution.visible_normal_pdf(self.wo, h) / (4.0 * self.wo.dot(h).abs());
+    }
+
+    fn generate(&self) -> Vector3 {
+        let h = self.distribution.sample_visible_normal(self.wo);
+        return self.frame.local(reflect(self.wo, h));
+    }
+}
+
+// Directions reflected or refracted off visible microfacets of a dielectric interface, choosing between the two
+// by the Fresnel reflectance of the sampled facet. `eta` is the far side's refractive index over the near side's.
+pub struct MicrofacetDielectricPDF {
+    pub frame: ONB,
+    pub wo: Vector3,
+    pub distribution: TrowbridgeReitz,
+    pub eta: f32,
+}
+
+impl PDF for MicrofacetDielectricPDF {
+    // Sampled facets can reflect below the surface or refract above it, so either way of reaching a
+    // direction counts towards its density.
+    fn value(&self, direction: Vector3) -> f32 {
+        let wi = to_local(&self.frame, direction.direction());
+        let mut density = 0.0;
+        if let Some(h) = reflection_half_vector(self.wo, wi) {
+            let reflectance = fresnel_dielectric(self.wo.dot(h), self.eta);
+            density += reflectance * self.distribution.visible_normal_pdf(self.wo, h) / (4.0 * self.wo.dot(h));
+        }
+        if let Some(h) = transmission_half_vector(self.wo, wi, self.eta) {
+            let reflectance = fresnel_dielectric(self.wo.dot(h), self.eta);
+            density += (1.0 - reflectance) * self.distribution.visible_normal_pdf(self.wo, h) * transmission_jacobian(self.wo, wi, h, self.eta);
+        }
+        return density;
+    }
+
+    fn generate(&self) -> Vector3 {
+        let h = self.distribution.sample_visible_normal(self.wo);
+        let reflectance = fresnel_dielectric(self.wo.dot(h), self.eta);
+        if random_f32() < reflectance {
+            return self.frame.local(reflect(self.wo, h));
+        }
+        return self.frame.local(refract(self.wo, h, self.eta).unwrap_or_else(|| reflect(self.wo, h)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLES: usize = 400_000;
+
+    // Directions spread uniformly over the hemisphere around +z, which has area 2π.
+    fn uniform_hemisphere() -> Vector3 {
+        let v = Vector3::random_unit_vector();
+        return if v.z < 0.0 { -v } else { v };
+    }
+
+    #[test]
+    fn projected_normals_and_visible_normals_integrate_to_one() {
+        let wo = Vector3 { x: 0.6, y: -0.3, z: 0.74 }.direction();
+        for &(alpha_x, alpha_y) in [(0.3, 0.3), (0.6, 0.15), (0.9, 0.9)].iter() {
+            let distribution = TrowbridgeReitz { alpha_x, alpha_y };
+            let (mut projected, mut visible) = (0.0, 0.0);
+            for _ in 0..SAMPLES {
+                let h = uniform_hemisphere();
+                projected += distribution.d(h) * h.z;
+                visible += distribution.visible_normal_pdf(wo, h);
+            }
+            projected *= 2.0 * PI / SAMPLES as f32;
+            visible *= 2.0 * PI / SAMPLES as f32;
+            assert!((projected - 1.0).abs() < 0.03, "alpha ({}, {}): projected area {}", alpha_x, alpha_y, projected);
+            assert!((visible - 1.0).abs() < 0.03, "alpha ({}, {}): visible normals {}", alpha_x, alpha_y, visible);
+        }
+    }
+
+    #[test]
+    fn reflection_pdf_matches_its_samples_above_the_surface() {
+        let distribution = TrowbridgeReitz { alpha_x: 0.5, alpha_y: 0.5 };
+        let pdf = MicrofacetReflectionPDF { frame: ONB::build_from_w(Vector3 { x: 0.0, y: 0.0, z: 1.0 }), wo: Vector3 { x: 0.8, y: 0.0, z: 0.6 }, distribution };
+
+        // The pdf leaves out reflections that end up below the surface, so it integrates to the share of
+        // samples that stay above it.
+        let integral = 2.0 * PI * (0..SAMPLES).map(|_| pdf.value(uniform_hemisphere())).sum::<f32>() / SAMPLES as f32;
+        let above = (0..SAMPLES).filter(|_| pdf.generate().z > 0.0).count() as f32 / SAMPLES as f32;
+        assert!(above < 1.0 && (integral - above).abs() < 0.03, "integral {}, samples above {}", integral, above);
+    }
+}
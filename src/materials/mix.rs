@@ -0,0 +1,225 @@
+use std::sync::Arc;
+
+use crate::geometry::color::Color;
+use crate::geometry::pdf::{PDF, WeightedMixturePDF};
+use crate::geometry::ray::Ray;
+use crate::geometry::vector::Point;
+use crate::materials::material::{Material, ScatterRecord};
+use crate::objects::hittable::HitRecord;
+use crate::textures::solid::SolidColor;
+use crate::textures::texture::Texture;
+use crate::utils::random_f32;
+
+// Blend of two materials, `second` showing through where the red channel of `mask` is 1, for weathered
+// surfaces such as rust over metal. Each scatter picks one material by the mask and keeps what it scattered.
+// When both materials always scatter into a pdf lobe, the other's lobe is weighed in as well, so light
+// sampling sees the blend's true pdf.
+pub struct MixMaterial {
+    pub first: Arc<dyn Material>,
+    pub second: Arc<dyn Material>,
+    pub mask: Arc<dyn Texture>,
+}
+
+impl MixMaterial {
+    pub fn new(first: Arc<dyn Material>, second: Arc<dyn Material>, weight: f32) -> Self {
+        return Self {
+            first,
+            second,
+            mask: Arc::new(SolidColor { color: Color { r: weight, g: weight, b: weight } }),
+        };
+    }
+
+    pub fn with_mask(mut self, mask: Arc<dyn Texture>) -> Self {
+        self.mask = mask;
+        return self;
+    }
+
+    fn weights(&self, hit_rec: &HitRecord) -> [f32; 2] {
+        let t = self.mask.color(hit_rec.u, hit_rec.v, hit_rec.intersection).r.clamp(0.0, 1.0);
+        return [1.0 - t, t];
+    }
+}
+
+impl Material for MixMaterial {
+    fn scatter(&self, in_ray: Ray, hit_rec: &HitRecord) -> Option<ScatterRecord> {
+        let weights = self.weights(hit_rec);
+        let (chosen, other, chosen_weight, other_weight) = if random_f32() < weights[0] {
+            (&self.first, &self.second, weights[0], weights[1])
+        } else {
+            (&self.second, &self.first, weights[1], weights[0])
+        };
+        let scatter_rec = chosen.scatter(in_ray, hit_rec)?;
+        if scatter_rec.specular_ray.is_some() {
+            return Option::from(scatter_rec);
+        }
+
+        let mut lobes = Vec::new();
+        if scatter_rec.pdf_ptr.is_some() {
+            lobes.push((chosen.clone(), scatter_rec, chosen_weight));
+        }
+        // Had the other material been chosen, it would only have come back with this same lobe if neither of them
+        // picks its kind of scatter at random. Otherwise the pick is left to stand alone.
+        if other_weight > 0.0 && !chosen.scatters_stochastically() && !other.scatters_stochastically() {
+            if let Some(other_rec) = other.scatter(in_ray, hit_rec) {
+                if other_rec.specular_ray.is_none() && other_rec.pdf_ptr.is_some() {
+                    lobes.push((other.clone(), other_rec, other_weight));
+                }
+            }
+        }
+        if lobes.is_empty() {
+            return Option::None;
+        }
+
+        // The lobes are carried in the record, so `scattering` weighs the same ones.
+        let total: f32 = lobes.iter().map(|(_, _, weight)| weight).sum();
+        let lobes: Vec<(Arc<dyn Material>, ScatterRecord, f32)> = lobes.into_iter().map(|(material, lobe_rec, weight)| (material, lobe_rec, weight / total)).collect();
+        return Option::from(ScatterRecord {
+            specular_ray: None,
+            attenuation: Color::WHITE,
+            pdf_ptr: Option::from(Arc::new(WeightedMixturePDF {
+                ptr: lobes.iter().map(|(_, lobe_rec, _)| lobe_rec.pdf_ptr.clone().unwrap()).collect(),
+                weights: lobes.iter().map(|(_, _, weight)| *weight).collect(),
+            }) as Arc<dyn PDF>),
+            lobes,
+        });
+    }
+
+    // Without a scatter record to say which lobes were sampled, both materials are weighed by the mask.
+    fn scattering_pdf(&self, in_ray: Ray, hit_rec: &HitRecord, scattered_ray: Ray) -> f32 {
+        let weights = self.weights(hit_rec);
+        return weights[0] * self.first.scattering_pdf(in_ray, hit_rec, scattered_ray) +
+            weights[1] * self.second.scattering_pdf(in_ray, hit_rec, scattered_ray);
+    }
+
+    // The chance of ending up among the non-specular lobes cancels their combined weight, leaving their shares.
+    fn scattering(&self, in_ray: Ray, hit_rec: &HitRecord, scatter_rec: &ScatterRecord, scattered_ray: Ray) -> Color {
+        let mut color = Color::BLACK;
+        for (material, lobe_rec, weight) in scatter_rec.lobes.iter() {
+            color += *weight * material.scattering(in_ray, hit_rec, lobe_rec, scattered_ray);
+        }
+        return color;
+    }
+
+    fn emitted(&self, ray: Ray, hit_rec: &HitRecord, u: f32, v: f32, intersection: Point) -> Color {
+        let weights = self.weights(hit_rec);
+        return weights[0] * self.first.emitted(ray, hit_rec, u, v, intersection) +
+            weights[1] * self.second.emitted(ray, hit_rec, u, v, intersection);
+    }
+
+    fn scatters_stochastically(&self) -> bool {
+        return true;
+    }
+}
+
+// A material with the light of another added on top, such as glowing paint or embers in ash. Scattering is
+// `material`'s alone; `emission` only contributes what it emits.
+pub struct AddMaterial {
+    pub material: Arc<dyn Material>,
+    pub emission: Arc<dyn Material>,
+}
+
+impl Material for AddMaterial {
+    fn scatter(&self, in_ray: Ray, hit_rec: &HitRecord) -> Option<ScatterRecord> {
+        return self.material.scatter(in_ray, hit_rec);
+    }
+
+    fn scattering_pdf(&self, in_ray: Ray, hit_rec: &HitRecord, scattered_ray: Ray) -> f32 {
+        return self.material.scattering_pdf(in_ray, hit_rec, scattered_ray);
+    }
+
+    fn scattering(&self, in_ray: Ray, hit_rec: &HitRecord, scatter_rec: &ScatterRecord, scattered_ray: Ray) -> Color {
+        return self.material.scattering(in_ray, hit_rec, scatter_rec, scattered_ray);
+    }
+
+    fn emitted(&self, ray: Ray, hit_rec: &HitRecord, u: f32, v: f32, intersection: Point) -> Color {
+        return self.material.emitted(ray, hit_rec, u, v, intersection) + self.emission.emitted(ray, hit_rec, u, v, intersection);
+    }
+
+    fn scatters_stochastically(&self) -> bool {
+        return self.material.scatters_stochastically();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::vector::Vector3;
+    use crate::materials::coated::Coated;
+    use crate::materials::dielectric::Dielectric;
+    use crate::materials::lambertian::Lambertian;
+    use crate::materials::metal::Metal;
+
+    use super::*;
+
+    const SAMPLES: usize = 200_000;
+
+    fn flat_hit(material: Arc<dyn Material>) -> HitRecord {
+        return HitRecord {
+            intersection: Point::ORIGIN,
+            normal: Vector3 { x: 0.0, y: 0.0, z: 1.0 },
+            material,
+            t: 1.0,
+            u: 0.0,
+            v: 0.0,
+            front_face: true,
+            tangent: None,
+        };
+    }
+
+    fn in_ray() -> Ray {
+        return Ray { origin: Point { x: -0.6, y: 0.0, z: 0.8 }, direction: Vector3 { x: 0.6, y: 0.0, z: -0.8 }, time: 0.0, wavelengths: None, absorption: None };
+    }
+
+    // What a path would carry back from one bounce under a white sky, averaged over many scatters.
+    fn albedo(material: Arc<dyn Material>, mut check: impl FnMut(&ScatterRecord)) -> f32 {
+        let hit_rec = flat_hit(material.clone());
+        let mut total = 0.0;
+        for _ in 0..SAMPLES {
+            let scatter_rec = match material.scatter(in_ray(), &hit_rec) {
+                Some(scatter_rec) => scatter_rec,
+                None => continue,
+            };
+            check(&scatter_rec);
+            if scatter_rec.specular_ray.is_some() {
+                total += scatter_rec.attenuation.g;
+                continue;
+            }
+            let pdf = scatter_rec.pdf_ptr.clone().unwrap();
+            let scattered = Ray { origin: Point::ORIGIN, direction: pdf.generate().direction(), time: 0.0, wavelengths: None, absorption: None };
+            let pdf_val = pdf.value(scattered.direction);
+            if pdf_val > 0.0 {
+                total += material.scattering(in_ray(), &hit_rec, &scatter_rec, scattered).g / pdf_val;
+            }
+        }
+        return total / SAMPLES as f32;
+    }
+
+    // A rough coat picks between its lobe and a walk beneath it, so its pick must not be paired with a fresh one.
+    #[test]
+    fn stochastic_material_mixes_with_glass() {
+        let coated: Arc<dyn Material> = Arc::new(Coated::new(Arc::new(Lambertian::new(Color { r: 0.5, g: 0.5, b: 0.5 })), 1.5, 0.3));
+        let mix: Arc<dyn Material> = Arc::new(MixMaterial::new(coated.clone(), Arc::new(Dielectric::new(1.5)), 0.5));
+
+        let coated_albedo = albedo(coated, |_| {});
+        let mix_albedo = albedo(mix, |scatter_rec| {
+            if scatter_rec.specular_ray.is_none() {
+                assert_eq!(scatter_rec.lobes.len(), 1);
+            }
+        });
+        let expected = 0.5 * coated_albedo + 0.5;
+        assert!((mix_albedo - expected).abs() < 0.02, "mix {} vs expected {}", mix_albedo, expected);
+    }
+
+    #[test]
+    fn deterministic_lobes_are_weighed_together() {
+        let mix: Arc<dyn Material> = Arc::new(MixMaterial::new(
+            Arc::new(Lambertian::new(Color { r: 0.8, g: 0.8, b: 0.8 })),
+            Arc::new(Metal::new(Color { r: 0.4, g: 0.4, b: 0.4 }, 0.5)),
+            0.25,
+        ));
+        let hit_rec = flat_hit(mix.clone());
+        let scatter_rec = mix.scatter(in_ray(), &hit_rec).unwrap();
+        assert_eq!(scatter_rec.lobes.len(), 2);
+        let weight_sum: f32 = scatter_rec.lobes.iter().map(|(_, _, weight)| weight).sum();
+        assert!((weight_sum - 1.0).abs() < 1e-5);
+    }
+}
@@ -1,6 +1,13 @@
 pub mod material;
 pub mod lambertian;
 pub mod metal;
+pub mod microfacet;
 pub mod dielectric;
+pub mod rough_dielectric;
+pub mod principled;
+pub mod coated;
+pub mod subsurface;
+pub mod oren_nayar;
+pub mod mix;
 pub mod light;
 pub mod isotropic;
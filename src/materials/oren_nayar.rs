@@ -0,0 +1,169 @@
+use std::sync::Arc;
+
+use crate::geometry::color::Color;
+use crate::geometry::pdf::{CosinePDF, PDF};
+use crate::geometry::ray::Ray;
+use crate::materials::material::{Material, ScatterRecord};
+use crate::objects::hittable::HitRecord;
+use crate::textures::solid::SolidColor;
+use crate::textures::texture::Texture;
+use crate::utils::PI;
+
+// Constants of the energy-preserving model's directional and average albedo.
+const FON_C1: f32 = 0.5 - 2.0 / (3.0 * PI);
+const FON_C2: f32 = 2.0 / 3.0 - 28.0 / (15.0 * PI);
+
+#[derive(Clone, Copy)]
+pub enum DiffuseModel {
+    // Oren and Nayar's qualitative model (1994), with roughness the standard deviation of the facet angle in radians.
+    Qualitative,
+    // Portsmouth et al.'s energy-preserving Oren-Nayar (2024): Fujii's form of the single-scattering lobe plus the
+    // light lost to multiple scattering between facets, so rough white surfaces stay white. Roughness is in [0, 1].
+    EnergyPreserving,
+}
+
+// Diffuse reflection off a surface of tiny Lambertian facets, which flattens and backscatters light as it gets rougher,
+// for clay, concrete and the moon. Roughness is read from the red channel of a texture. Sampled like `Lambertian`.
+pub struct OrenNayar {
+    pub albedo: Arc<dyn Texture>,
+    pub roughness: Arc<dyn Texture>,
+    pub model: DiffuseModel,
+}
+
+impl OrenNayar {
+    pub fn new(color: Color, roughness: f32, model: DiffuseModel) -> Self {
+        return Self {
+            albedo: Arc::new(SolidColor { color }),
+            roughness: Arc::new(SolidColor { color: Color { r: roughness, g: roughness, b: roughness } }),
+            model,
+        };
+    }
+}
+
+// Directional albedo of the energy-preserving model's single-scattering lobe, by a fitted polynomial.
+fn fon_albedo(cos_0: f32, roughness: f32) -> f32 {
+    let mu = 1.0 - cos_0;
+    let g_over_pi = mu * (0.057_108_53 + mu * (0.491_881_87 + mu * (-0.332_181_44 + mu * 0.071_442_995)));
+    return (1.0 + roughness * g_over_pi) / (1.0 + FON_C1 * roughness);
+}
+
+impl Material for OrenNayar {
+    fn scatter(&self, _in_ray: Ray, hit_rec: &HitRecord) -> Option<ScatterRecord> {
+        return Option::from(ScatterRecord {
+            specular_ray: None,
+            attenuation: self.albedo.color(hit_rec.u, hit_rec.v, hit_rec.intersection),
+            pdf_ptr: Option::from(Arc::new(CosinePDF::new(hit_rec.normal)) as Arc<dyn PDF>),
+            lobes: Vec::new(),
+        });
+    }
+
+    fn scattering_pdf(&self, _in_ray: Ray, hit_rec: &HitRecord, scattered_ray: Ray) -> f32 {
+        let cosine = hit_rec.normal.dot(scattered_ray.direction.direction());
+        return if cosine < 0.0 { 0.0 } else { cosine / PI };
+    }
+
+    fn scattering(&self, in_ray: Ray, hit_rec: &HitRecord, scatter_rec: &ScatterRecord, scattered_ray: Ray) -> Color {
+        let wo = -in_ray.direction.direction();
+        let wi = scattered_ray.direction.direction();
+        let cos_o = hit_rec.normal.dot(wo);
+        let cos_i = hit_rec.normal.dot(wi);
+        if cos_o <= 0.0 || cos_i <= 0.0 {
+            return Color::BLACK;
+        }
+        let roughness = self.roughness.color(hit_rec.u, hit_rec.v, hit_rec.intersection).r.max(0.0);
+        let albedo = scatter_rec.attenuation;
+        // sin(θi) sin(θo) cos(φi - φo), how far the two directions lean the same way.
+        let s = wi.dot(wo) - cos_i * cos_o;
+
+        return match self.model {
+            DiffuseModel::Qualitative => {
+                let sigma2 = roughness.powi(2);
+                let a = 1.0 - 0.5 * sigma2 / (sigma2 + 0.33);
+                let b = 0.45 * sigma2 / (sigma2 + 0.09);
+                ((a + b * s.max(0.0) / cos_i.max(cos_o)) * cos_i / PI) * albedo
+            }
+            DiffuseModel::EnergyPreserving => {
+                let roughness = roughness.min(1.0);
+                let s_over_t = if s > 0.0 { s / cos_i.max(cos_o) } else { s };
+                let af = 1.0 / (1.0 + FON_C1 * roughness);
+                let single = (af * (1.0 + roughness * s_over_t) / PI) * albedo;
+
+                // Light that bounced between facets comes out with the colour saturated by each extra bounce.
+                let average = af * (1.0 + FON_C2 * roughness);
+                let saturate = |rho: f32| rho.powi(2) * average / (1.0 - rho * (1.0 - average));
+                let multiple_albedo = Color { r: saturate(albedo.r), g: saturate(albedo.g), b: saturate(albedo.b) };
+                let lost = (1.0 - fon_albedo(cos_o, roughness)).max(1e-7) * (1.0 - fon_albedo(cos_i, roughness)).max(1e-7);
+                let multiple = (lost / ((1.0 - average).max(1e-7) * PI)) * multiple_albedo;
+
+                cos_i * (single + multiple)
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::vector::{Point, Vector3};
+    use crate::materials::lambertian::Lambertian;
+
+    use super::*;
+
+    // A hit on the z = 0 plane from above.
+    fn flat_hit(material: Arc<dyn Material>) -> HitRecord {
+        return HitRecord { intersection: Point::ORIGIN, normal: Vector3 { x: 0.0, y: 0.0, z: 1.0 }, material, t: 1.0, u: 0.0, v: 0.0, front_face: true, tangent: None };
+    }
+
+    fn ray_along(direction: Vector3) -> Ray {
+        return Ray { origin: Point::ORIGIN, direction, time: 0.0, wavelengths: None, absorption: None };
+    }
+
+    fn upper_hemisphere() -> Vector3 {
+        let w = Vector3::random_unit_vector();
+        return Vector3 { x: w.x, y: w.y, z: w.z.abs() };
+    }
+
+    #[test]
+    fn smooth_surface_is_lambertian() {
+        let color = Color { r: 0.8, g: 0.5, b: 0.2 };
+        let lambertian: Arc<dyn Material> = Arc::new(Lambertian::new(color));
+        for &model in [DiffuseModel::Qualitative, DiffuseModel::EnergyPreserving].iter() {
+            let material: Arc<dyn Material> = Arc::new(OrenNayar::new(color, 0.0, model));
+            let hit_rec = flat_hit(material.clone());
+            for _ in 0..1000 {
+                let in_ray = ray_along(-upper_hemisphere());
+                let scattered = ray_along(upper_hemisphere());
+                let expected = lambertian.scattering(in_ray, &hit_rec, &lambertian.scatter(in_ray, &hit_rec).unwrap(), scattered);
+                let actual = material.scattering(in_ray, &hit_rec, &material.scatter(in_ray, &hit_rec).unwrap(), scattered);
+                for &(a, e) in [(actual.r, expected.r), (actual.g, expected.g), (actual.b, expected.b)].iter() {
+                    assert!((a - e).abs() < 1e-5, "{} vs {}", a, e);
+                }
+            }
+        }
+    }
+
+    // A rough white surface lit evenly from every direction reflects all of it, from whichever angle it is seen.
+    #[test]
+    fn white_energy_preserving_surface_passes_the_furnace_test() {
+        for &roughness in [0.5, 1.0].iter() {
+            let material: Arc<dyn Material> = Arc::new(OrenNayar::new(Color::WHITE, roughness, DiffuseModel::EnergyPreserving));
+            let hit_rec = flat_hit(material.clone());
+            for &cos_o in [1.0f32, 0.5, 0.1].iter() {
+                let in_ray = ray_along(-Vector3 { x: (1.0 - cos_o * cos_o).sqrt(), y: 0.0, z: cos_o });
+                let scatter_rec = material.scatter(in_ray, &hit_rec).unwrap();
+                let pdf = scatter_rec.pdf_ptr.clone().unwrap();
+
+                let samples = 200_000;
+                let mut albedo = 0.0;
+                for _ in 0..samples {
+                    let scattered = ray_along(pdf.generate());
+                    let pdf_value = pdf.value(scattered.direction);
+                    if pdf_value > 0.0 {
+                        albedo += material.scattering(in_ray, &hit_rec, &scatter_rec, scattered).r / pdf_value;
+                    }
+                }
+                albedo /= samples as f32;
+                assert!((albedo - 1.0).abs() < 0.02, "roughness {}, cos_o {}: albedo {}", roughness, cos_o, albedo);
+            }
+        }
+    }
+}
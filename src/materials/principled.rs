@@ -0,0 +1,343 @@
+use std::sync::Arc;
+
+use crate::geometry::color::Color;
+use crate::geometry::onb::ONB;
+use crate::geometry::pdf::{PDF, random_cosine_direction};
+use crate::geometry::ray::Ray;
+use crate::geometry::vector::Vector3;
+use crate::materials::material::{fresnel_dielectric, Material, ScatterRecord};
+use crate::materials::microfacet::{reflect, reflection_half_vector, refract, shading_frame, SMOOTH_ALPHA, to_local, transmission_half_vector, transmission_jacobian, TrowbridgeReitz};
+use crate::materials::rough_dielectric::RoughDielectric;
+use crate::objects::hittable::HitRecord;
+use crate::textures::solid::SolidColor;
+use crate::textures::texture::Texture;
+use crate::utils::{PI, random_f32};
+
+// Disney's principled BSDF (Burley 2012, 2015): a Burley diffuse with retro-reflection and sheen, an anisotropic
+// GGX specular lobe, a GTR1 clear coat and rough transmission, mixed by artist-friendly parameters in [0, 1].
+// Every parameter is a texture; the scalar ones read its red channel.
+pub struct Principled {
+    pub base_color: Arc<dyn Texture>,
+    pub metallic: Arc<dyn Texture>,
+    pub roughness: Arc<dyn Texture>,
+    // Head-on reflectance of the dielectric part, where 0.5 is 4% (an index of 1.5) and 1 is 8%.
+    pub specular: Arc<dyn Texture>,
+    pub specular_tint: Arc<dyn Texture>,
+    pub anisotropic: Arc<dyn Texture>,
+    pub sheen: Arc<dyn Texture>,
+    pub sheen_tint: Arc<dyn Texture>,
+    pub clearcoat: Arc<dyn Texture>,
+    pub clearcoat_gloss: Arc<dyn Texture>,
+    pub transmission: Arc<dyn Texture>,
+}
+
+// Uniform texture for a scalar parameter.
+pub fn constant(value: f32) -> Arc<dyn Texture> {
+    return Arc::new(SolidColor { color: Color { r: value, g: value, b: value } });
+}
+
+// Parameters looked up at one hit point.
+struct Parameters {
+    base_color: Color,
+    metallic: f32,
+    roughness: f32,
+    specular: f32,
+    specular_tint: f32,
+    anisotropic: f32,
+    sheen: f32,
+    sheen_tint: f32,
+    clearcoat: f32,
+    clearcoat_gloss: f32,
+    transmission: f32,
+}
+
+impl Parameters {
+    // Base colour normalised to unit luminance, the hue used by the tint parameters.
+    fn tint(&self) -> Color {
+        let luminance = luminance(self.base_color);
+        return if luminance > 0.0 { (1.0 / luminance) * self.base_color } else { Color::WHITE };
+    }
+
+    // Index of refraction matching the specular reflectance, so that transmission agrees with reflection.
+    fn eta(&self) -> f32 {
+        let r0_sqrt = (0.08 * self.specular).clamp(0.0, 0.99).sqrt();
+        return (1.0 + r0_sqrt) / (1.0 - r0_sqrt);
+    }
+
+    fn specular_distribution(&self) -> TrowbridgeReitz {
+        let aspect = (1.0 - 0.9 * self.anisotropic).sqrt();
+        let alpha = self.roughness.powi(2);
+        return TrowbridgeReitz { alpha_x: (alpha / aspect).max(SMOOTH_ALPHA), alpha_y: (alpha * aspect).max(SMOOTH_ALPHA) };
+    }
+
+    fn clearcoat_alpha(&self) -> f32 {
+        return mix(0.1, 0.001, self.clearcoat_gloss);
+    }
+
+    // Probability of sampling the diffuse, specular, clear coat and transmission lobes, roughly in proportion to
+    // how much each reflects.
+    fn lobe_weights(&self) -> [f32; 4] {
+        let dielectric = 1.0 - self.metallic;
+        let weights = [dielectric * (1.0 - self.transmission), 1.0, 0.25 * self.clearcoat, dielectric * self.transmission];
+        let total: f32 = weights.iter().sum();
+        return [weights[0] / total, weights[1] / total, weights[2] / total, weights[3] / total];
+    }
+}
+
+impl Principled {
+    // A dielectric of `base_color` at medium roughness, with the other parameters off.
+    pub fn new(base_color: Color) -> Self {
+        return Self {
+            base_color: Arc::new(SolidColor { color: base_color }),
+            metallic: constant(0.0),
+            roughness: constant(0.5),
+            specular: constant(0.5),
+            specular_tint: constant(0.0),
+            anisotropic: constant(0.0),
+            sheen: constant(0.0),
+            sheen_tint: constant(0.5),
+            clearcoat: constant(0.0),
+            clearcoat_gloss: constant(1.0),
+            transmission: constant(0.0),
+        };
+    }
+
+    fn parameters(&self, hit_rec: &HitRecord) -> Parameters {
+        let scalar = |texture: &Arc<dyn Texture>| texture.color(hit_rec.u, hit_rec.v, hit_rec.intersection).r.clamp(0.0, 1.0);
+        return Parameters {
+            base_color: self.base_color.color(hit_rec.u, hit_rec.v, hit_rec.intersection),
+            metallic: scalar(&self.metallic),
+            roughness: scalar(&self.roughness),
+            specular: scalar(&self.specular),
+            specular_tint: scalar(&self.specular_tint),
+            anisotropic: scalar(&self.anisotropic),
+            sheen: scalar(&self.sheen),
+            sheen_tint: scalar(&self.sheen_tint),
+            clearcoat: scalar(&self.clearcoat),
+            clearcoat_gloss: scalar(&self.clearcoat_gloss),
+            transmission: scalar(&self.transmission),
+        };
+    }
+
+    // Light that got into a transmissive material is inside a rough dielectric until it leaves again. Back faces
+    // of opaque ones, such as single-sided leaves and cloth, shade like the front.
+    fn is_interior(parameters: &Parameters, hit_rec: &HitRecord) -> bool {
+        return !hit_rec.front_face && (1.0 - parameters.metallic) * parameters.transmission > 0.0;
+    }
+
+    fn interior(&self, parameters: &Parameters) -> RoughDielectric {
+        return RoughDielectric { refractive_index: parameters.eta(), roughness: self.roughness.clone() };
+    }
+
+    fn pdf(&self, parameters: &Parameters, frame: ONB, wo: Vector3) -> PrincipledPDF {
+        return PrincipledPDF {
+            frame,
+            wo,
+            weights: parameters.lobe_weights(),
+            specular: parameters.specular_distribution(),
+            clearcoat_alpha: parameters.clearcoat_alpha(),
+            eta: parameters.eta(),
+        };
+    }
+}
+
+impl Material for Principled {
+    fn scatter(&self, in_ray: Ray, hit_rec: &HitRecord) -> Option<ScatterRecord> {
+        let parameters = self.parameters(hit_rec);
+        if Self::is_interior(&parameters, hit_rec) {
+            return self.interior(&parameters).scatter(in_ray, hit_rec);
+        }
+
+        let frame = shading_frame(hit_rec);
+        let wo = to_local(&frame, -in_ray.direction.direction());
+        return Option::from(ScatterRecord {
+            specular_ray: None,
+            attenuation: Color::WHITE,
+            pdf_ptr: Option::from(Arc::new(self.pdf(&parameters, frame, wo)) as Arc<dyn PDF>),
+            lobes: Vec::new(),
+        });
+    }
+
+    fn scattering_pdf(&self, in_ray: Ray, hit_rec: &HitRecord, scattered_ray: Ray) -> f32 {
+        let parameters = self.parameters(hit_rec);
+        if Self::is_interior(&parameters, hit_rec) {
+            return self.interior(&parameters).scattering_pdf(in_ray, hit_rec, scattered_ray);
+        }
+        let frame = shading_frame(hit_rec);
+        let wo = to_local(&frame, -in_ray.direction.direction());
+        return self.pdf(&parameters, frame, wo).value(scattered_ray.direction);
+    }
+
+    fn scattering(&self, in_ray: Ray, hit_rec: &HitRecord, scatter_rec: &ScatterRecord, scattered_ray: Ray) -> Color {
+        let parameters = self.parameters(hit_rec);
+        if Self::is_interior(&parameters, hit_rec) {
+            return self.interior(&parameters).scattering(in_ray, hit_rec, scatter_rec, scattered_ray);
+        }
+
+        let frame = shading_frame(hit_rec);
+        let wo = to_local(&frame, -in_ray.direction.direction());
+        let wi = to_local(&frame, scattered_ray.direction.direction());
+        if wo.z <= 0.0 || wi.z == 0.0 {
+            return Color::BLACK;
+        }
+        let dielectric = 1.0 - parameters.metallic;
+
+        // Rough transmission into the material, tinted by the base colour on the way in.
+        if wi.z < 0.0 {
+            let eta = parameters.eta();
+            let opt_half = transmission_half_vector(wo, wi, eta);
+            if opt_half.is_none() {
+                return Color::BLACK;
+            }
+            let h = opt_half.unwrap();
+            let distribution = parameters.specular_distribution();
+            let transmittance = 1.0 - fresnel_dielectric(wo.dot(h), eta);
+            let weight = distribution.d(h) * distribution.g(wo, wi) * transmittance * wo.dot(h) *
+                transmission_jacobian(wo, wi, h, eta) / (wo.z * eta.powi(2));
+            return (dielectric * parameters.transmission * weight) * parameters.base_color;
+        }
+
+        let opt_half = reflection_half_vector(wo, wi);
+        if opt_half.is_none() {
+            return Color::BLACK;
+        }
+        let h = opt_half.unwrap();
+        let cos_d = wi.dot(h);
+        let tint = parameters.tint();
+
+        // Burley diffuse, whose retro-reflection brightens rough surfaces at grazing angles, plus sheen.
+        let fl = schlick_weight(wi.z);
+        let fv = schlick_weight(wo.z);
+        let rr = 2.0 * parameters.roughness * cos_d.powi(2);
+        let diffuse = ((1.0 - 0.5 * fl) * (1.0 - 0.5 * fv) + rr * (fl + fv + fl * fv * (rr - 1.0))) / PI;
+        let sheen_color = mix_color(Color::WHITE, tint, parameters.sheen_tint);
+        let sheen = (parameters.sheen * schlick_weight(cos_d)) * sheen_color;
+        let diffuse_lobe = (dielectric * (1.0 - parameters.transmission)) * (diffuse * parameters.base_color + sheen);
+
+        // Specular reflectance blends from a (possibly tinted) dielectric's towards the base colour as it turns metal.
+        let specular_color = mix_color((0.08 * parameters.specular) * mix_color(Color::WHITE, tint, parameters.specular_tint), parameters.base_color, parameters.metallic);
+        let fresnel = mix_color(specular_color, Color::WHITE, schlick_weight(wo.dot(h)));
+        let distribution = parameters.specular_distribution();
+        let specular_lobe = (distribution.d(h) * distribution.g(wo, wi) / (4.0 * wo.z * wi.z)) * fresnel;
+
+        // Clear coat: a fixed index of 1.5 with long-tailed GTR1 highlights and fixed-roughness shadowing.
+        let coat_shadowing = TrowbridgeReitz { alpha_x: 0.25, alpha_y: 0.25 }.g(wo, wi);
+        let coat_fresnel = mix(0.04, 1.0, schlick_weight(wo.dot(h)));
+        let coat = 0.25 * parameters.clearcoat * gtr1(h.z, parameters.clearcoat_alpha()) * coat_fresnel * coat_shadowing / (4.0 * wo.z * wi.z);
+
+        return wi.z * (diffuse_lobe + specular_lobe + coat * Color::WHITE);
+    }
+}
+
+// Picks one of the principled lobes by its weight, and values a direction by every lobe that could have made it.
+pub struct PrincipledPDF {
+    pub frame: ONB,
+    pub wo: Vector3,
+    pub weights: [f32; 4],
+    pub specular: TrowbridgeReitz,
+    pub clearcoat_alpha: f32,
+    pub eta: f32,
+}
+
+impl PDF for PrincipledPDF {
+    fn value(&self, direction: Vector3) -> f32 {
+        let wi = to_local(&self.frame, direction.direction());
+        let [diffuse, specular, clearcoat, transmission] = self.weights;
+        let mut density = 0.0;
+        if wi.z > 0.0 {
+            density += diffuse * wi.z / PI;
+        }
+        // Steep facets can reflect below the surface, where transmission reaches too, so every lobe counts
+        // wherever it can send a direction.
+        if let Some(h) = reflection_half_vector(self.wo, wi) {
+            density += specular * self.specular.visible_normal_pdf(self.wo, h) / (4.0 * self.wo.dot(h));
+            density += clearcoat * gtr1(h.z, self.clearcoat_alpha) * h.z / (4.0 * self.wo.dot(h));
+        }
+        if let Some(h) = transmission_half_vector(self.wo, wi, self.eta) {
+            density += transmission * self.specular.visible_normal_pdf(self.wo, h) * transmission_jacobian(self.wo, wi, h, self.eta);
+        }
+        return density;
+    }
+
+    fn generate(&self) -> Vector3 {
+        let [diffuse, specular, clearcoat, _] = self.weights;
+        let u = random_f32();
+        let wi = if u < diffuse {
+            random_cosine_direction()
+        } else if u < diffuse + specular {
+            reflect(self.wo, self.specular.sample_visible_normal(self.wo))
+        } else if u < diffuse + specular + clearcoat {
+            reflect(self.wo, sample_gtr1(self.clearcoat_alpha))
+        } else {
+            let h = self.specular.sample_visible_normal(self.wo);
+            refract(self.wo, h, self.eta).unwrap_or_else(|| reflect(self.wo, h))
+        };
+        return self.frame.local(wi);
+    }
+}
+
+// Generalised Trowbridge-Reitz with gamma 1, whose long tail gives the clear coat its haze.
+fn gtr1(cos_h: f32, alpha: f32) -> f32 {
+    if cos_h <= 0.0 {
+        return 0.0;
+    }
+    let a2 = alpha.powi(2);
+    return (a2 - 1.0) / (PI * a2.ln() * (1.0 + (a2 - 1.0) * cos_h.powi(2)));
+}
+
+// Microfacet normal with density gtr1(h.z) h.z.
+fn sample_gtr1(alpha: f32) -> Vector3 {
+    let a2 = alpha.powi(2);
+    let cos_h = ((1.0 - a2.powf(1.0 - random_f32())) / (1.0 - a2)).max(0.0).sqrt();
+    let sin_h = (1.0 - cos_h.powi(2)).max(0.0).sqrt();
+    let phi = 2.0 * PI * random_f32();
+    return Vector3 { x: sin_h * phi.cos(), y: sin_h * phi.sin(), z: cos_h };
+}
+
+fn schlick_weight(cos_0: f32) -> f32 {
+    return (1.0 - cos_0.clamp(0.0, 1.0)).powi(5);
+}
+
+fn luminance(color: Color) -> f32 {
+    return 0.2126 * color.r + 0.7152 * color.g + 0.0722 * color.b;
+}
+
+fn mix(a: f32, b: f32, t: f32) -> f32 {
+    return (1.0 - t) * a + t * b;
+}
+
+fn mix_color(a: Color, b: Color, t: f32) -> Color {
+    return (1.0 - t) * a + t * b;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pdf_integrates_to_one_over_the_sphere() {
+        let principled = Principled::new(Color::WHITE);
+        let wo = Vector3 { x: 0.8, y: 0.0, z: 0.6 };
+        for &(roughness, transmission) in [(0.3, 0.0), (0.7, 0.5), (0.9, 1.0)].iter() {
+            let parameters = Parameters {
+                base_color: Color { r: 0.8, g: 0.6, b: 0.4 },
+                metallic: 0.0,
+                roughness,
+                specular: 0.5,
+                specular_tint: 0.0,
+                anisotropic: 0.0,
+                sheen: 0.0,
+                sheen_tint: 0.5,
+                clearcoat: 1.0,
+                clearcoat_gloss: 0.0,
+                transmission,
+            };
+            let pdf = principled.pdf(&parameters, ONB::build_from_w(Vector3 { x: 0.0, y: 0.0, z: 1.0 }), wo);
+
+            // Uniform directions over the sphere, weighted by its area.
+            let samples = 400_000;
+            let integral = 4.0 * PI * (0..samples).map(|_| pdf.value(Vector3::random_unit_vector())).sum::<f32>() / samples as f32;
+            assert!((integral - 1.0).abs() < 0.03, "roughness {}, transmission {}: {}", roughness, transmission, integral);
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use std::sync::Arc;
+
+use crate::geometry::color::Color;
+use crate::geometry::pdf::PDF;
+use crate::geometry::ray::Ray;
+use crate::materials::material::{fresnel_dielectric, Material, reflect_ray, refract, ScatterRecord};
+use crate::materials::microfacet::{MicrofacetDielectricPDF, reflection_half_vector, shading_frame, to_local, transmission_half_vector, transmission_jacobian, TrowbridgeReitz};
+use crate::objects::hittable::HitRecord;
+use crate::textures::solid::SolidColor;
+use crate::textures::texture::Texture;
+use crate::utils::random_f32;
+
+// Glass with a GGX microfacet surface (Walter et al. 2007) that both reflects and transmits, for frosted
+// glass and etched acrylic. Roughness is read from the red channel of a texture, so parts can be polished.
+pub struct RoughDielectric {
+    pub refractive_index: f32,
+    pub roughness: Arc<dyn Texture>,
+}
+
+impl RoughDielectric {
+    pub fn new(refractive_index: f32, roughness: f32) -> Self {
+        return Self {
+            refractive_index,
+            roughness: Arc::new(SolidColor { color: Color { r: roughness, g: roughness, b: roughness } }),
+        };
+    }
+
+    fn distribution(&self, hit_rec: &HitRecord) -> TrowbridgeReitz {
+        let roughness = self.roughness.color(hit_rec.u, hit_rec.v, hit_rec.intersection).r.clamp(0.0, 1.0);
+        return TrowbridgeReitz::from_roughness(roughness, roughness);
+    }
+
+    // Refractive index beyond the surface over the index on the ray's side.
+    fn eta(&self, hit_rec: &HitRecord) -> f32 {
+        return if hit_rec.front_face { self.refractive_index } else { 1.0 / self.refractive_index };
+    }
+}
+
+impl Material for RoughDielectric {
+    fn scatter(&self, in_ray: Ray, hit_rec: &HitRecord) -> Option<ScatterRecord> {
+        let in_direction = in_ray.direction.direction();
+        let distribution = self.distribution(hit_rec);
+        let eta = self.eta(hit_rec);
+
+        if distribution.is_smooth() {
+            let cos_0 = -in_direction.dot(hit_rec.normal);
+            let direction = if random_f32() < fresnel_dielectric(cos_0, eta) {
+                reflect_ray(in_direction, hit_rec.normal)
+            } else {
+                refract(in_direction, hit_rec.normal, 1.0 / eta)
+            };
+            return Option::from(ScatterRecord {
+                specular_ray: Option::from(Ray { origin: hit_rec.intersection, direction, time: in_ray.time, wavelengths: in_ray.wavelengths, absorption: in_ray.absorption }),
+                attenuation: Color::WHITE,
+                pdf_ptr: None,
+                lobes: Vec::new(),
+            });
+        }
+
+        let frame = shading_frame(hit_rec);
+        let wo = to_local(&frame, -in_direction);
+        return Option::from(ScatterRecord {
+            specular_ray: None,
+            attenuation: Color::WHITE,
+            pdf_ptr: Option::from(Arc::new(MicrofacetDielectricPDF { frame, wo, distribution, eta }) as Arc<dyn PDF>),
+            lobes: Vec::new(),
+        });
+    }
+
+    fn scattering_pdf(&self, in_ray: Ray, hit_rec: &HitRecord, scattered_ray: Ray) -> f32 {
+        let frame = shading_frame(hit_rec);
+        let wo = to_local(&frame, -in_ray.direction.direction());
+        let pdf = MicrofacetDielectricPDF { frame, wo, distribution: self.distribution(hit_rec), eta: self.eta(hit_rec) };
+        return pdf.value(scattered_ray.direction);
+    }
+
+    // Torrance-Sparrow reflection plus Walter's transmission, both times the cosine at the far direction.
+    // Transmitted radiance is scaled by 1/eta² as it is squeezed into a narrower cone.
+    fn scattering(&self, in_ray: Ray, hit_rec: &HitRecord, _scatter_rec: &ScatterRecord, scattered_ray: Ray) -> Color {
+        let frame = shading_frame(hit_rec);
+        let wo = to_local(&frame, -in_ray.direction.direction());
+        let wi = to_local(&frame, scattered_ray.direction.direction());
+        let distribution = self.distribution(hit_rec);
+        let eta = self.eta(hit_rec);
+
+        // Reflection stays above the surface and transmission goes below it.
+        let reflected = wi.z > 0.0;
+        let opt_half = if reflected { reflection_half_vector(wo, wi) } else { transmission_half_vector(wo, wi, eta) };
+        if wo.z <= 0.0 || opt_half.is_none() {
+            return Color::BLACK;
+        }
+
+        let h = opt_half.unwrap();
+        let reflectance = fresnel_dielectric(wo.dot(h), eta);
+        let dg = distribution.d(h) * distribution.g(wo, wi);
+        let weight = if reflected {
+            dg * reflectance / (4.0 * wo.z)
+        } else {
+            dg * (1.0 - reflectance) * wo.dot(h) * transmission_jacobian(wo, wi, h, eta) / (wo.z * eta.powi(2))
+        };
+        return weight * Color::WHITE;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::onb::ONB;
+    use crate::geometry::vector::{Point, Vector3};
+    use crate::utils::PI;
+
+    const SAMPLES: usize = 1_000_000;
+
+    // A hit on the z = 0 plane from above its front or its back.
+    fn flat_hit(material: Arc<dyn Material>, front_face: bool) -> HitRecord {
+        return HitRecord {
+            intersection: Point::ORIGIN,
+            normal: Vector3 { x: 0.0, y: 0.0, z: 1.0 },
+            material,
+            t: 1.0,
+            u: 0.0,
+            v: 0.0,
+            front_face,
+            tangent: None,
+        };
+    }
+
+    // Both into denser glass and out of it, where steep facets reflect everything.
+    #[test]
+    fn pdf_integrates_to_one_over_the_sphere() {
+        let wo = Vector3 { x: 0.8, y: 0.0, z: 0.6 };
+        for &eta in [1.5, 1.0 / 1.5].iter() {
+            for &roughness in [0.6, 0.9].iter() {
+                let pdf = MicrofacetDielectricPDF {
+                    frame: ONB::build_from_w(Vector3 { x: 0.0, y: 0.0, z: 1.0 }),
+                    wo,
+                    distribution: TrowbridgeReitz::from_roughness(roughness, roughness),
+                    eta,
+                };
+                let integral = 4.0 * PI * (0..SAMPLES).map(|_| pdf.value(Vector3::random_unit_vector())).sum::<f32>() / SAMPLES as f32;
+                assert!((integral - 1.0).abs() < 0.03, "eta {}, roughness {}: {}", eta, roughness, integral);
+            }
+        }
+    }
+
+    // A fairly smooth surface loses little to shadowing, so seen head on it passes on close to the smooth
+    // interface's reflectance plus its transmittance, squeezed by 1/eta².
+    #[test]
+    fn nearly_smooth_glass_scatters_as_fresnel_predicts() {
+        for &front_face in [true, false].iter() {
+            let material: Arc<dyn Material> = Arc::new(RoughDielectric::new(1.5, 0.15));
+            let hit_rec = flat_hit(material.clone(), front_face);
+            let in_ray = Ray { origin: Point { x: -0.2, y: 0.0, z: 1.0 }, direction: Vector3 { x: 0.2, y: 0.0, z: -1.0 }, time: 0.0, wavelengths: None, absorption: None };
+            let scatter_rec = material.scatter(in_ray, &hit_rec).unwrap();
+            let pdf = scatter_rec.pdf_ptr.clone().unwrap();
+
+            let samples = 200_000;
+            let mut albedo = 0.0;
+            for _ in 0..samples {
+                let scattered = Ray { origin: Point::ORIGIN, direction: pdf.generate(), time: 0.0, wavelengths: None, absorption: None };
+                let pdf_value = pdf.value(scattered.direction);
+                if pdf_value > 0.0 {
+                    albedo += material.scattering(in_ray, &hit_rec, &scatter_rec, scattered).r / pdf_value;
+                }
+            }
+            albedo /= samples as f32;
+
+            let eta = if front_face { 1.5 } else { 1.0 / 1.5 };
+            let reflectance = fresnel_dielectric(-in_ray.direction.direction().z, eta);
+            let expected = reflectance + (1.0 - reflectance) / (eta * eta);
+            assert!((albedo - expected).abs() < 0.02 * expected, "front face {}: albedo {}, expected {}", front_face, albedo, expected);
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use std::sync::Arc;
+
+use crate::geometry::color::Color;
+use crate::geometry::ray::Ray;
+use crate::geometry::vector::Vector3;
+use crate::materials::material::{fresnel_dielectric, Material, reflect_ray, refract, ScatterRecord};
+use crate::objects::hittable::{HitRecord, Hittable};
+use crate::objects::medium::sample_free_path;
+use crate::utils::{INF_F32, random_f32};
+
+// Scattering events a path may take under the surface before it is given up as absorbed.
+const MAX_WALK_STEPS: u32 = 1024;
+// Nearest boundary crossing counted while walking, so the walk doesn't stop at the point it started from.
+const WALK_EPSILON: f32 = 1e-3;
+
+// Translucent material for skin, wax, marble and milk: light refracts in and random-walks through a scattering
+// volume until it refracts back out. Built by `SubsurfaceMedium`, which keeps the boundary in world space.
+pub struct Subsurface {
+    pub boundary: Arc<dyn Hittable>,
+    pub refractive_index: f32,
+    pub albedo: Color,
+    pub mean_free_path: Color,
+}
+
+impl Subsurface {
+    // Extinction coefficient of each channel.
+    fn extinction(&self) -> [f32; 3] {
+        return [1.0 / self.mean_free_path.r, 1.0 / self.mean_free_path.g, 1.0 / self.mean_free_path.b];
+    }
+
+    // Throughput of one flight of `distance`, sampled for a random channel. Weighting by the average density of
+    // all three channels keeps each channel's estimate unbiased whichever one picked the distance.
+    fn flight_weight(&self, distance: f32, scattered: bool) -> Color {
+        let sigma_t = self.extinction();
+        let transmittance = [(-sigma_t[0] * distance).exp(), (-sigma_t[1] * distance).exp(), (-sigma_t[2] * distance).exp()];
+        if scattered {
+            let density = Color { r: sigma_t[0] * transmittance[0], g: sigma_t[1] * transmittance[1], b: sigma_t[2] * transmittance[2] };
+            let pdf = (density.r + density.g + density.b) / 3.0;
+            return (1.0 / pdf) * density * self.albedo;
+        }
+        let probability = (transmittance[0] + transmittance[1] + transmittance[2]) / 3.0;
+        return (1.0 / probability) * Color { r: transmittance[0], g: transmittance[1], b: transmittance[2] };
+    }
+}
+
+// Reflects or refracts `direction` at a smooth interface whose normal faces it, choosing by the Fresnel reflectance.
+// `eta` is the index beyond the surface over the index on the ray's side. Returns the new direction and whether it crossed.
+fn cross_interface(direction: Vector3, normal: Vector3, eta: f32) -> (Vector3, bool) {
+    let cos_0 = -direction.dot(normal);
+    if random_f32() < fresnel_dielectric(cos_0, eta) {
+        return (reflect_ray(direction, normal), false);
+    }
+    return (refract(direction, normal, 1.0 / eta), true);
+}
+
+impl Material for Subsurface {
+    fn scatter(&self, in_ray: Ray, hit_rec: &HitRecord) -> Option<ScatterRecord> {
+        let in_direction = in_ray.direction.direction();
+        let eta = if hit_rec.front_face { self.refractive_index } else { 1.0 / self.refractive_index };
+        let (mut direction, crossed) = cross_interface(in_direction, hit_rec.normal, eta);
+        let mut origin = hit_rec.intersection;
+        let mut attenuation = Color::WHITE;
+
+        // Rays that reflect off the surface, or come from inside and leave, carry on in the scene straight away.
+        if crossed == hit_rec.front_face {
+            let sigma_t = self.extinction();
+            let mut escaped = false;
+            for _ in 0..MAX_WALK_STEPS {
+                let walk_ray = Ray { origin, direction, time: in_ray.time, wavelengths: in_ray.wavelengths, absorption: in_ray.absorption };
+                // A walk that slips through the boundary numerically leaves where it is.
+                let boundary_rec = match self.boundary.hit(walk_ray, WALK_EPSILON, INF_F32) {
+                    Some(boundary_rec) => boundary_rec,
+                    None => {
+                        escaped = true;
+                        break;
+                    }
+                };
+                let distance = sample_free_path(-1.0 / sigma_t[(random_f32() * 3.0).min(2.0) as usize]);
+
+                if distance < boundary_rec.t {
+                    attenuation = attenuation * self.flight_weight(distance, true);
+                    origin = walk_ray.at_distance(distance);
+                    direction = Vector3::random_unit_vector();
+                    continue;
+                }
+
+                attenuation = attenuation * self.flight_weight(boundary_rec.t, false);
+                origin = boundary_rec.intersection;
+                let (next, crossed_out) = cross_interface(direction, boundary_rec.normal, 1.0 / self.refractive_index);
+                direction = next;
+                if crossed_out {
+                    escaped = true;
+                    break;
+                }
+            }
+            if !escaped {
+                return Option::None;
+            }
+        }
+
+        return Option::from(ScatterRecord {
+            specular_ray: Option::from(Ray { origin, direction, time: in_ray.time, wavelengths: in_ray.wavelengths, absorption: in_ray.absorption }),
+            attenuation,
+            pdf_ptr: None,
+            lobes: Vec::new(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::vector::Point;
+    use crate::materials::lambertian::Lambertian;
+    use crate::objects::boxes::AxisAlignedBox;
+    use crate::objects::instances::RotateY;
+    use crate::objects::medium::SubsurfaceMedium;
+    use crate::objects::sphere::Sphere;
+
+    // A slab one unit thick and wide enough that walks don't reach its sides, with nothing absorbed inside.
+    fn white_slab(mean_free_path: Color) -> SubsurfaceMedium {
+        let boundary = Arc::new(AxisAlignedBox::new(
+            Point { x: -100.0, y: -1.0, z: -100.0 },
+            Point { x: 100.0, y: 0.0, z: 100.0 },
+            Arc::new(Lambertian::new(Color::WHITE)),
+        ));
+        return SubsurfaceMedium::new(boundary, 1.4, Color::WHITE, mean_free_path).unwrap();
+    }
+
+    // Whatever isn't reflected off the top comes out of the top or the bottom, so on average all of it leaves.
+    #[test]
+    fn white_slab_conserves_energy() {
+        let slab = white_slab(Color { r: 0.3, g: 0.25, b: 0.2 });
+        for &direction in [Vector3 { x: 0.0, y: -1.0, z: 0.0 }, Vector3 { x: 0.8, y: -0.6, z: 0.0 }].iter() {
+            let in_ray = Ray { origin: Point { x: 0.0, y: 1.0, z: 0.0 } - 2.0 * direction, direction, time: 0.0, wavelengths: None, absorption: None };
+            let hit_rec = slab.hit(in_ray, 0.001, INF_F32).unwrap();
+
+            let samples = 20_000;
+            let mut total = Color::BLACK;
+            for _ in 0..samples {
+                if let Some(scatter_rec) = hit_rec.material.scatter(in_ray, &hit_rec) {
+                    total += scatter_rec.attenuation;
+                }
+            }
+            let average = (1.0 / samples as f32) * total;
+            for &channel in [average.r, average.g, average.b].iter() {
+                assert!((channel - 1.0).abs() < 0.04, "({}, {}, {})", average.r, average.g, average.b);
+            }
+        }
+    }
+
+    #[test]
+    fn wrapped_boundaries_are_rejected() {
+        let sphere: Arc<dyn Hittable> = Arc::new(Sphere { center: Point::ORIGIN, radius: 1.0, material: Arc::new(Lambertian::new(Color::WHITE)) });
+        assert!(SubsurfaceMedium::new(sphere.clone(), 1.4, Color::WHITE, Color::WHITE).is_ok());
+        assert!(SubsurfaceMedium::new(Arc::new(RotateY::new(sphere, 30.0)), 1.4, Color::WHITE, Color::WHITE).is_err());
+    }
+}
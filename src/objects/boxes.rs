@@ -2,10 +2,11 @@ use std::sync::Arc;
 
 use crate::geometry::bounding_volume::AxisAlignedBoundingBox;
 use crate::geometry::ray::Ray;
-use crate::geometry::vector::Point;
+use crate::geometry::vector::{Point, Vector3};
 use crate::materials::material::Material;
 use crate::objects::hittable::{HitRecord, Hittable};
-use crate::objects::rectangle::{XYRect, XZRect, YZRect};
+use crate::objects::quad::Quad;
+use crate::utils::INF_F32;
 use crate::world::hittable_list::HittableList;
 
 pub struct AxisAlignedBox {
@@ -16,51 +17,17 @@ pub struct AxisAlignedBox {
 
 impl AxisAlignedBox {
     pub fn new(point_min: Point, point_max: Point, mat: Arc<dyn Material>) -> Self {
-        let mut faces: Vec<Arc<dyn Hittable>> = Vec::new();
-        faces.push(Arc::new(XYRect {
-            x: (point_min.x, point_max.x),
-            y: (point_min.y, point_max.y),
-            k: point_max.z,
-            material: mat.clone(),
-        }));
-        faces.push(Arc::new(XYRect {
-            x: (point_min.x, point_max.x),
-            y: (point_min.y, point_max.y),
-            k: point_min.z,
-            material: mat.clone(),
-        }));
-
-
-        faces.push(Arc::new(XZRect {
-            x: (point_min.x, point_max.x),
-            z: (point_min.z, point_max.z),
-            k: point_min.y,
-            material: mat.clone(),
-        }));
-        faces.push(Arc::new(XZRect {
-            x: (point_min.x, point_max.x),
-            z: (point_min.z, point_max.z),
-            k: point_max.y,
-            material: mat.clone(),
-        }));
-
-        faces.push(Arc::new(YZRect {
-            y: (point_min.y, point_max.y),
-            z: (point_min.z, point_max.z),
-            k: point_max.x,
-            material: mat.clone(),
-        }));
-        faces.push(Arc::new(YZRect {
-            y: (point_min.y, point_max.y),
-            z: (point_min.z, point_max.z),
-            k: point_min.x,
-            material: mat.clone(),
-        }));
+        let size = point_max - point_min;
+        let edges = [
+            Vector3 { x: size.x, y: 0.0, z: 0.0 },
+            Vector3 { x: 0.0, y: size.y, z: 0.0 },
+            Vector3 { x: 0.0, y: 0.0, z: size.z },
+        ];
 
         return Self {
             box_min: point_min,
             box_max: point_max,
-            faces: HittableList { objects: faces },
+            faces: box_faces(point_min, edges, mat),
         };
     }
 }
@@ -76,4 +43,99 @@ impl Hittable for AxisAlignedBox {
             maximum: self.box_max,
         });
     }
-}
\ No newline at end of file
+}
+
+// Parallelepiped spanned by three edges from `corner`, e.g. a box turned about any axis without a Transform.
+pub struct OrientedBox {
+    pub corner: Point,
+    pub edges: [Vector3; 3],
+    pub faces: HittableList,
+}
+
+impl OrientedBox {
+    pub fn new(corner: Point, edges: [Vector3; 3], mat: Arc<dyn Material>) -> Self {
+        return Self {
+            corner,
+            edges,
+            faces: box_faces(corner, edges, mat),
+        };
+    }
+}
+
+impl Hittable for OrientedBox {
+    fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        return self.faces.hit(ray, t_min, t_max);
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AxisAlignedBoundingBox> {
+        let mut min = Point { x: INF_F32, y: INF_F32, z: INF_F32 };
+        let mut max = Point { x: -INF_F32, y: -INF_F32, z: -INF_F32 };
+
+        for i in 0..8 {
+            let mut vertex = self.corner;
+            for (bit, edge) in self.edges.iter().enumerate() {
+                if i & (1 << bit) != 0 {
+                    vertex += *edge;
+                }
+            }
+            for c in 0..3 {
+                min[c] = min[c].min(vertex[c]);
+                max[c] = max[c].max(vertex[c]);
+            }
+        }
+
+        return Option::from(AxisAlignedBoundingBox { minimum: min, maximum: max });
+    }
+}
+
+// Each face's edges are ordered so that its normal points out of the box.
+fn box_faces(corner: Point, edges: [Vector3; 3], mat: Arc<dyn Material>) -> HittableList {
+    let [mut a, mut b, c] = edges;
+    if a.cross(b).dot(c) < 0.0 {
+        std::mem::swap(&mut a, &mut b);
+    }
+
+    let mut faces: Vec<Arc<dyn Hittable>> = Vec::new();
+    faces.push(Arc::new(Quad::new(corner + c, a, b, mat.clone())));
+    faces.push(Arc::new(Quad::new(corner, b, a, mat.clone())));
+    faces.push(Arc::new(Quad::new(corner + a, b, c, mat.clone())));
+    faces.push(Arc::new(Quad::new(corner, c, b, mat.clone())));
+    faces.push(Arc::new(Quad::new(corner + b, c, a, mat.clone())));
+    faces.push(Arc::new(Quad::new(corner, a, c, mat)));
+    return HittableList { objects: faces };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::color::Color;
+    use crate::materials::lambertian::Lambertian;
+
+    use super::*;
+
+    // Rays aimed at the centre from all around enter through a front face, and rays from the centre leave
+    // through a back face, whichever way round the edges are given.
+    #[test]
+    fn faces_point_out_of_the_box() {
+        let material = Arc::new(Lambertian::new(Color::WHITE));
+        let corner = Point { x: 1.0, y: -2.0, z: 0.5 };
+        let edges = [Vector3 { x: 1.0, y: 0.5, z: 0.0 }, Vector3 { x: -0.4, y: 1.2, z: 0.3 }, Vector3 { x: 0.1, y: -0.2, z: 0.9 }];
+        for &swapped in [false, true].iter() {
+            let edges = if swapped { [edges[1], edges[0], edges[2]] } else { edges };
+            let oriented = OrientedBox::new(corner, edges, material.clone());
+            let center = corner + 0.5 * (edges[0] + edges[1] + edges[2]);
+
+            for _ in 0..1000 {
+                let direction = Vector3::random_unit_vector();
+                let inward = Ray { origin: center + 5.0 * direction, direction: -direction, time: 0.0, wavelengths: None, absorption: None };
+                let entry = oriented.hit(inward, 0.001, INF_F32).unwrap();
+                assert!(entry.front_face);
+
+                let outward = Ray { origin: center, direction, time: 0.0, wavelengths: None, absorption: None };
+                let exit = oriented.hit(outward, 0.001, INF_F32).unwrap();
+                assert!(!exit.front_face);
+                // The normal faces the ray, so the outward normal of the exit face is its opposite.
+                assert!((-exit.normal).dot(exit.intersection - center) > 0.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use std::sync::Arc;
+
+use crate::geometry::bounding_volume::AxisAlignedBoundingBox;
+use crate::geometry::ray::Ray;
+use crate::geometry::vector::Point;
+use crate::objects::hittable::{HitRecord, Hittable};
+use crate::utils::INF_F32;
+
+#[derive(Clone, Copy)]
+pub enum CSGOperation {
+    Union,
+    Intersection,
+    // Left with right carved out of it.
+    Difference,
+}
+
+impl CSGOperation {
+    fn contains(&self, in_left: bool, in_right: bool) -> bool {
+        return match self {
+            CSGOperation::Union => in_left || in_right,
+            CSGOperation::Intersection => in_left && in_right,
+            CSGOperation::Difference => in_left && !in_right,
+        };
+    }
+}
+
+// Boolean combination of two closed shapes. Both children's crossings along the ray are merged in order,
+// tracking whether the ray is inside each, and only those that change the combined inside/outside state are kept.
+pub struct CSG {
+    pub left: Arc<dyn Hittable>,
+    pub right: Arc<dyn Hittable>,
+    pub operation: CSGOperation,
+}
+
+impl CSG {
+    pub fn new(operation: CSGOperation, left: Arc<dyn Hittable>, right: Arc<dyn Hittable>) -> Self {
+        return Self { left, right, operation };
+    }
+}
+
+impl Hittable for CSG {
+    fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        return self.hit_all(ray, t_min, t_max).into_iter().next();
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AxisAlignedBoundingBox> {
+        let left_box = self.left.bounding_box(t0, t1);
+        return match self.operation {
+            CSGOperation::Union => Option::from(AxisAlignedBoundingBox::surrounding_box(left_box?, self.right.bounding_box(t0, t1)?)),
+            CSGOperation::Intersection => match (left_box, self.right.bounding_box(t0, t1)) {
+                (Some(a), Some(b)) => {
+                    let minimum = Point { x: a.minimum.x.max(b.minimum.x), y: a.minimum.y.max(b.minimum.y), z: a.minimum.z.max(b.minimum.z) };
+                    let maximum = Point { x: a.maximum.x.min(b.maximum.x), y: a.maximum.y.min(b.maximum.y), z: a.maximum.z.min(b.maximum.z) };
+                    // Disjoint boxes collapse to a flat box instead of an inverted one.
+                    Option::from(AxisAlignedBoundingBox { minimum, maximum: Point { x: maximum.x.max(minimum.x), y: maximum.y.max(minimum.y), z: maximum.z.max(minimum.z) } })
+                }
+                (a, b) => a.or(b),
+            },
+            CSGOperation::Difference => left_box,
+        };
+    }
+
+    fn hit_all(&self, ray: Ray, t_min: f32, t_max: f32) -> Vec<HitRecord> {
+        // Children are searched to infinity so a ray starting inside one still sees its exit,
+        // which tells us it began inside.
+        let left_hits = self.left.hit_all(ray, t_min, INF_F32);
+        let right_hits = self.right.hit_all(ray, t_min, INF_F32);
+
+        let mut in_left = left_hits.first().map_or(false, |h| !h.front_face);
+        let mut in_right = right_hits.first().map_or(false, |h| !h.front_face);
+        let mut inside = self.operation.contains(in_left, in_right);
+
+        let mut hit_recs: Vec<HitRecord> = Vec::new();
+        let mut left_iter = left_hits.into_iter().peekable();
+        let mut right_iter = right_hits.into_iter().peekable();
+        loop {
+            let take_left = match (left_iter.peek(), right_iter.peek()) {
+                (Some(l), Some(r)) => l.t <= r.t,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => break,
+            };
+
+            let mut hit_rec = if take_left {
+                let hit_rec = left_iter.next().unwrap();
+                in_left = hit_rec.front_face;
+                hit_rec
+            } else {
+                let hit_rec = right_iter.next().unwrap();
+                in_right = hit_rec.front_face;
+                hit_rec
+            };
+            if hit_rec.t > t_max {
+                break;
+            }
+
+            let now_inside = self.operation.contains(in_left, in_right);
+            if now_inside != inside {
+                // The normal already faces the ray; whether this is an entry now depends on the combined shape,
+                // which turns carved-out surfaces inside out.
+                hit_rec.front_face = now_inside;
+                hit_recs.push(hit_rec);
+                inside = now_inside;
+            }
+        }
+
+        return hit_recs;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::color::Color;
+    use crate::geometry::vector::Vector3;
+    use crate::materials::lambertian::Lambertian;
+    use crate::objects::sphere::Sphere;
+
+    use super::*;
+
+    // Unit spheres at x = 0 and x = 1, which overlap over x in [0, 1] along the x axis.
+    fn overlapping(operation: CSGOperation) -> CSG {
+        let material = Arc::new(Lambertian::new(Color::WHITE));
+        return CSG::new(
+            operation,
+            Arc::new(Sphere { center: Point::ORIGIN, radius: 1.0, material: material.clone() }),
+            Arc::new(Sphere { center: Point { x: 1.0, y: 0.0, z: 0.0 }, radius: 1.0, material }),
+        );
+    }
+
+    fn along_x(x: f32, direction: f32) -> Ray {
+        return Ray { origin: Point { x, y: 0.0, z: 0.0 }, direction: Vector3 { x: direction, y: 0.0, z: 0.0 }, time: 0.0, wavelengths: None, absorption: None };
+    }
+
+    // Crossings as (t, entering, x component of the normal pointing out of the combined shape).
+    fn crossings(csg: &CSG, ray: Ray) -> Vec<(f32, bool, f32)> {
+        return csg.hit_all(ray, 0.001, INF_F32).iter().map(|hit_rec| {
+            let outward = if hit_rec.front_face { hit_rec.normal } else { -hit_rec.normal };
+            (hit_rec.t, hit_rec.front_face, outward.x)
+        }).collect();
+    }
+
+    fn assert_crossings(actual: Vec<(f32, bool, f32)>, expected: &[(f32, bool, f32)]) {
+        assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert!((a.0 - e.0).abs() < 1e-4 && a.1 == e.1 && (a.2 - e.2).abs() < 1e-4, "{:?} vs {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn union_spans_both_spheres() {
+        let union = overlapping(CSGOperation::Union);
+        assert_crossings(crossings(&union, along_x(-5.0, 1.0)), &[(4.0, true, -1.0), (7.0, false, 1.0)]);
+        assert!((union.hit(along_x(-5.0, 1.0), 0.001, INF_F32).unwrap().t - 4.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn intersection_keeps_the_overlap() {
+        let intersection = overlapping(CSGOperation::Intersection);
+        assert_crossings(crossings(&intersection, along_x(-5.0, 1.0)), &[(5.0, true, -1.0), (6.0, false, 1.0)]);
+        // Starting in the overlap, only the way out is left.
+        assert_crossings(crossings(&intersection, along_x(0.5, 1.0)), &[(0.5, false, 1.0)]);
+    }
+
+    // The carved-out surface is the right sphere turned inside out: its normals point into the hollow.
+    #[test]
+    fn difference_normals_point_out_of_the_result() {
+        let difference = overlapping(CSGOperation::Difference);
+        assert_crossings(crossings(&difference, along_x(-5.0, 1.0)), &[(4.0, true, -1.0), (5.0, false, 1.0)]);
+        assert_crossings(crossings(&difference, along_x(5.0, -1.0)), &[(5.0, true, 1.0), (6.0, false, -1.0)]);
+        // Starting in the hollow, the ray never gets back into the result.
+        assert!(difference.hit(along_x(0.5, 1.0), 0.001, INF_F32).is_none());
+    }
+}
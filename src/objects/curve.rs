@@ -0,0 +1,302 @@
+use std::sync::Arc;
+
+use crate::geometry::bounding_volume::AxisAlignedBoundingBox;
+use crate::geometry::onb::ONB;
+use crate::geometry::ray::Ray;
+use crate::geometry::vector::{Point, Vector3};
+use crate::materials::material::Material;
+use crate::objects::hittable::{HitRecord, Hittable};
+use crate::utils::{INF_F32, random_f32, random_f32_range};
+use crate::world::flat_bvh::FlatBVH;
+use crate::world::sah::SplitMethod;
+
+const MAX_SUBDIVISIONS: u32 = 10;
+// Pieces per grown strand.
+const GROWN_SEGMENTS: u32 = 3;
+
+#[derive(Clone, Copy)]
+pub enum CurveType {
+    // Ribbon that always turns to face the ray, for strands too thin to show their roundness.
+    Flat,
+    // Ribbon facing the ray whose normals wrap around the strand across its width, shading it like a tube.
+    Round,
+}
+
+// Cubic Bézier strand whose width changes linearly from the first control point to the last. Hits carry the
+// strand's tangent, u along the strand and v across it.
+pub struct Curve {
+    pub points: [Point; 4],
+    pub width: (f32, f32),
+    pub curve_type: CurveType,
+    pub material: Arc<dyn Material>,
+    // The part of a longer strand this piece covers, so that u runs from root to tip across all pieces.
+    pub u_range: (f32, f32),
+}
+
+impl Curve {
+    // Cubic pieces passing through `points` with Catmull-Rom tangents, the width narrowing from root to tip.
+    pub fn strand(points: &[Point], width: (f32, f32), curve_type: CurveType, material: Arc<dyn Material>) -> Vec<Curve> {
+        assert!(points.len() >= 2, "strand needs at least two points");
+
+        let segments = points.len() - 1;
+        let at = |i: isize| points[i.clamp(0, segments as isize) as usize];
+        let width_at = |u: f32| width.0 + u * (width.1 - width.0);
+
+        return (0..segments).map(|i| {
+            let i = i as isize;
+            let u_range = (i as f32 / segments as f32, (i + 1) as f32 / segments as f32);
+            Curve {
+                points: [
+                    at(i),
+                    at(i) + (at(i + 1) - at(i - 1)) / 6.0,
+                    at(i + 1) - (at(i + 2) - at(i)) / 6.0,
+                    at(i + 1),
+                ],
+                width: (width_at(u_range.0), width_at(u_range.1)),
+                curve_type,
+                material: material.clone(),
+                u_range,
+            }
+        }).collect();
+    }
+
+    fn max_width(&self) -> f32 {
+        return self.width.0.max(self.width.1);
+    }
+
+    // Splits the hit test into pieces flat enough to treat as straight, so that the chord error stays
+    // well under the curve's width.
+    fn subdivisions(points: &[Point; 4], width: f32) -> u32 {
+        let mut l0: f32 = 0.0;
+        for window in points.windows(3) {
+            let second_difference = window[0] - 2.0 * window[1] + window[2];
+            l0 = l0.max(second_difference.x.abs()).max(second_difference.y.abs()).max(second_difference.z.abs());
+        }
+        if l0 == 0.0 {
+            return 0;
+        }
+
+        let epsilon = width / 20.0;
+        let depth = (std::f32::consts::SQRT_2 * 6.0 * l0 / (8.0 * epsilon)).log2() / 2.0;
+        return depth.clamp(0.0, MAX_SUBDIVISIONS as f32) as u32;
+    }
+
+    // Nearest hit on the piece with control points `cp` in ray space, where the ray runs from the origin
+    // along +z. Returns the depth, u on this curve and the offset of the hit from the centre line.
+    fn hit_piece(&self, cp: [Point; 4], u_range: (f32, f32), depth: u32, z_range: (f32, f32)) -> Option<(f32, f32, Point)> {
+        let half_width = 0.5 * self.max_width();
+        let mut min = Point { x: INF_F32, y: INF_F32, z: INF_F32 };
+        let mut max = Point { x: -INF_F32, y: -INF_F32, z: -INF_F32 };
+        for point in cp.iter() {
+            for c in 0..3 {
+                min[c] = min[c].min(point[c] - half_width);
+                max[c] = max[c].max(point[c] + half_width);
+            }
+        }
+        if min.x > 0.0 || max.x < 0.0 || min.y > 0.0 || max.y < 0.0 || min.z > z_range.1 || max.z < z_range.0 {
+            return Option::None;
+        }
+
+        if depth > 0 {
+            let halves = split_bezier(cp);
+            let u_mid = 0.5 * (u_range.0 + u_range.1);
+            let first = self.hit_piece(halves.0, (u_range.0, u_mid), depth - 1, z_range);
+            let z_max = first.map_or(z_range.1, |hit| hit.0);
+            let second = self.hit_piece(halves.1, (u_mid, u_range.1), depth - 1, (z_range.0, z_max));
+            return second.or(first);
+        }
+
+        // Rays passing beyond either end of the piece, past the perpendicular at its end point, miss it.
+        if (cp[1].y - cp[0].y) * -cp[0].y + cp[0].x * (cp[0].x - cp[1].x) < 0.0 {
+            return Option::None;
+        }
+        if (cp[2].y - cp[3].y) * -cp[3].y + cp[3].x * (cp[3].x - cp[2].x) < 0.0 {
+            return Option::None;
+        }
+
+        let chord = cp[3] - cp[0];
+        let chord_length_squared = chord.x.powi(2) + chord.y.powi(2);
+        if chord_length_squared == 0.0 {
+            return Option::None;
+        }
+        let w = ((-cp[0].x * chord.x - cp[0].y * chord.y) / chord_length_squared).clamp(0.0, 1.0);
+        let u = u_range.0 + w * (u_range.1 - u_range.0);
+        let hit_width = self.width.0 + u * (self.width.1 - self.width.0);
+
+        let center = bezier(&cp, w);
+        if center.x.powi(2) + center.y.powi(2) > 0.25 * hit_width.powi(2) {
+            return Option::None;
+        }
+        if center.z < z_range.0 || center.z > z_range.1 {
+            return Option::None;
+        }
+        return Option::from((center.z, u, center));
+    }
+}
+
+impl Hittable for Curve {
+    fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let speed = ray.direction.length();
+        let onb = ONB::build_from_w(ray.direction);
+        let to_ray_space = |p: Point| {
+            let d = p - ray.origin;
+            Point { x: d.dot(onb.u), y: d.dot(onb.v), z: d.dot(onb.w) }
+        };
+        let cp = [to_ray_space(self.points[0]), to_ray_space(self.points[1]), to_ray_space(self.points[2]), to_ray_space(self.points[3])];
+
+        let depth = Self::subdivisions(&cp, self.max_width());
+        let (z, u, center) = self.hit_piece(cp, (0.0, 1.0), depth, (t_min * speed, t_max * speed))?;
+        let t = z / speed;
+        let hit_width = self.width.0 + u * (self.width.1 - self.width.0);
+
+        // The ribbon faces the ray; `side` runs across it, towards where the ray passed the centre line.
+        let tangent = bezier_derivative(&self.points, u).direction();
+        let view = -onb.w;
+        let facing = (view - view.dot(tangent) * tangent).direction();
+        let side = onb.w.cross(tangent).direction();
+        let offset_dir = -(center.x * onb.u + center.y * onb.v);
+        let offset = (offset_dir.dot(side) / (0.5 * hit_width)).clamp(-1.0, 1.0);
+
+        let normal = match self.curve_type {
+            CurveType::Flat => facing,
+            CurveType::Round => ((1.0 - offset.powi(2)).sqrt() * facing + offset * side).direction(),
+        };
+
+        let mut hit_rec = HitRecord {
+            intersection: ray.at_distance(t),
+            normal,
+            material: self.material.clone(),
+            t,
+            u: self.u_range.0 + u * (self.u_range.1 - self.u_range.0),
+            v: 0.5 + 0.5 * offset,
+            front_face: false,
+            tangent: Option::from(tangent),
+        };
+        hit_rec.set_face_normal(ray);
+        return Option::from(hit_rec);
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AxisAlignedBoundingBox> {
+        let half_width = 0.5 * self.max_width();
+        let mut min = Point { x: INF_F32, y: INF_F32, z: INF_F32 };
+        let mut max = Point { x: -INF_F32, y: -INF_F32, z: -INF_F32 };
+        for point in self.points.iter() {
+            for c in 0..3 {
+                min[c] = min[c].min(point[c] - half_width);
+                max[c] = max[c].max(point[c] + half_width);
+            }
+        }
+        return Option::from(AxisAlignedBoundingBox { minimum: min, maximum: max });
+    }
+}
+
+// Puts the curves behind their own BVH, so that a head of hair or a patch of grass is a single object.
+pub fn strands_bvh(curves: Vec<Curve>) -> Arc<dyn Hittable> {
+    let objects: Vec<Arc<dyn Hittable>> = curves.into_iter().map(|curve| Arc::new(curve) as Arc<dyn Hittable>).collect();
+    return Arc::new(FlatBVH::build(&objects, 0.0, 1.0, SplitMethod::Binned(12)).0);
+}
+
+// Grows `count` strands on the parts of `surface` seen from straight above, such as
+// grass on a ground quad or a heightfield. Each strand rises along the surface normal and bends over by up to
+// `bend` times its length in a random direction.
+pub fn grow_strands(surface: &dyn Hittable, count: u32, length: f32, width: (f32, f32), bend: f32, curve_type: CurveType, material: Arc<dyn Material>) -> Vec<Curve> {
+    let bound_box = surface.bounding_box(0.0, 1.0).expect("strands need a surface with a bounding box");
+    let down = Vector3 { x: 0.0, y: -1.0, z: 0.0 };
+
+    let mut curves: Vec<Curve> = Vec::new();
+    for _ in 0..count {
+        let origin = Point {
+            x: random_f32_range(bound_box.minimum.x, bound_box.maximum.x),
+            y: bound_box.maximum.y + 1.0,
+            z: random_f32_range(bound_box.minimum.z, bound_box.maximum.z),
+        };
+        let opt_hit_rec = surface.hit(Ray { origin, direction: down, time: 0.0, wavelengths: None, absorption: None }, 0.0, INF_F32);
+        if opt_hit_rec.is_none() {
+            continue;
+        }
+
+        let hit_rec = opt_hit_rec.unwrap();
+        let strand_length = length * random_f32_range(0.7, 1.3);
+        let angle = 2.0 * std::f32::consts::PI * random_f32();
+        let lean = bend * random_f32() * Vector3 { x: angle.cos(), y: 0.0, z: angle.sin() };
+        let points: Vec<Point> = (0..=GROWN_SEGMENTS).map(|k| {
+            let s = k as f32 / GROWN_SEGMENTS as f32;
+            hit_rec.intersection + strand_length * (s * hit_rec.normal + s * s * lean)
+        }).collect();
+        curves.extend(Curve::strand(&points, width, curve_type, material.clone()));
+    }
+    return curves;
+}
+
+fn bezier(cp: &[Point; 4], u: f32) -> Point {
+    let s = 1.0 - u;
+    return s * s * s * cp[0] + 3.0 * s * s * u * cp[1] + 3.0 * s * u * u * cp[2] + u * u * u * cp[3];
+}
+
+fn bezier_derivative(cp: &[Point; 4], u: f32) -> Vector3 {
+    let s = 1.0 - u;
+    return 3.0 * (s * s * (cp[1] - cp[0]) + 2.0 * s * u * (cp[2] - cp[1]) + u * u * (cp[3] - cp[2]));
+}
+
+// De Casteljau split at the midpoint.
+fn split_bezier(cp: [Point; 4]) -> ([Point; 4], [Point; 4]) {
+    let p01 = 0.5 * (cp[0] + cp[1]);
+    let p12 = 0.5 * (cp[1] + cp[2]);
+    let p23 = 0.5 * (cp[2] + cp[3]);
+    let p012 = 0.5 * (p01 + p12);
+    let p123 = 0.5 * (p12 + p23);
+    let mid = 0.5 * (p012 + p123);
+    return ([cp[0], p01, p012, mid], [mid, p123, p23, cp[3]]);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::color::Color;
+    use crate::materials::lambertian::Lambertian;
+
+    use super::*;
+
+    // The second half of a straight strand along x, its control points evenly spaced so u runs with x.
+    fn straight(curve_type: CurveType) -> Curve {
+        return Curve {
+            points: [
+                Point { x: -1.0, y: 0.0, z: 0.0 },
+                Point { x: -1.0 / 3.0, y: 0.0, z: 0.0 },
+                Point { x: 1.0 / 3.0, y: 0.0, z: 0.0 },
+                Point { x: 1.0, y: 0.0, z: 0.0 },
+            ],
+            width: (0.2, 0.2),
+            curve_type,
+            material: Arc::new(Lambertian::new(Color::WHITE)),
+            u_range: (0.5, 1.0),
+        };
+    }
+
+    fn assert_close(actual: Vector3, expected: Vector3) {
+        assert!((actual - expected).length() < 1e-4, "{:?} vs {:?}", (actual.x, actual.y, actual.z), (expected.x, expected.y, expected.z));
+    }
+
+    #[test]
+    fn straight_strand_hit_runs_along_and_across_it() {
+        // Passes a quarter of the width above the centre line, with a direction of length 2.
+        let ray = Ray { origin: Point { x: 0.3, y: 0.05, z: 5.0 }, direction: Vector3 { x: 0.0, y: 0.0, z: -2.0 }, time: 0.0, wavelengths: None, absorption: None };
+        let tube_normal = Vector3 { x: 0.0, y: 0.5, z: 0.75f32.sqrt() };
+        for &(curve_type, normal) in [(CurveType::Flat, Vector3 { x: 0.0, y: 0.0, z: 1.0 }), (CurveType::Round, tube_normal)].iter() {
+            let hit_rec = straight(curve_type).hit(ray, 0.001, INF_F32).unwrap();
+            assert!((hit_rec.t - 2.5).abs() < 1e-4, "t {}", hit_rec.t);
+            assert!((hit_rec.u - 0.825).abs() < 1e-3, "u {}", hit_rec.u);
+            assert!((hit_rec.v - 0.25).abs() < 1e-3, "v {}", hit_rec.v);
+            assert_close(hit_rec.tangent.unwrap(), Vector3 { x: 1.0, y: 0.0, z: 0.0 });
+            assert_close(hit_rec.normal, normal);
+        }
+    }
+
+    #[test]
+    fn rays_beside_or_beyond_the_strand_miss() {
+        let curve = straight(CurveType::Round);
+        let down = |x: f32, y: f32| Ray { origin: Point { x, y, z: 5.0 }, direction: Vector3 { x: 0.0, y: 0.0, z: -1.0 }, time: 0.0, wavelengths: None, absorption: None };
+        assert!(curve.hit(down(0.3, 0.15), 0.001, INF_F32).is_none());
+        assert!(curve.hit(down(1.05, 0.0), 0.001, INF_F32).is_none());
+        assert!(curve.hit(down(0.3, 0.0), 0.001, 4.0).is_none());
+    }
+}
@@ -0,0 +1,264 @@
+use std::sync::Arc;
+
+use image::{ImageBuffer, Luma};
+
+use crate::geometry::bounding_volume::AxisAlignedBoundingBox;
+use crate::geometry::ray::Ray;
+use crate::geometry::vector::{Point, Vector3};
+use crate::materials::material::Material;
+use crate::objects::hittable::{HitRecord, Hittable};
+use crate::objects::triangle::hit_triangle;
+use crate::utils::INF_F32;
+
+const STACK_SIZE: usize = 128;
+
+// Distance, triangle vertices and their barycentric weights.
+type CellHit = (f32, [(usize, usize); 3], [f32; 3]);
+
+// Terrain from a grayscale heightmap, one grid vertex per pixel. Black is at `corner` height and white at
+// `size.y` above it, spread over `size.x` by `size.z`. The image's top row is the far (+z) edge, matching how
+// image textures are mapped, so u and v run along x and z.
+pub struct Heightfield {
+    pub corner: Point,
+    pub size: Vector3,
+    pub material: Arc<dyn Material>,
+    // Vertex counts along x and z.
+    width: usize,
+    depth: usize,
+    // Vertex heights in world units above `corner`, row by row along +z.
+    heights: Vec<f32>,
+    // Min/max height of each cell, then of each 2x2 block of the level below, up to a single root.
+    levels: Vec<HeightLevel>,
+}
+
+struct HeightLevel {
+    width: usize,
+    depth: usize,
+    bounds: Vec<(f32, f32)>,
+}
+
+impl Heightfield {
+    pub fn new(path: &str, corner: Point, size: Vector3, material: Arc<dyn Material>) -> Result<Self, String> {
+        let img = image::open(path).map_err(|e| format!("{}: {}", path, e))?.to_luma16();
+        if img.width() < 2 || img.height() < 2 {
+            return Err(format!("{}: heightmap needs at least 2x2 pixels", path));
+        }
+        return Ok(Self::from_image(img, corner, size, material));
+    }
+
+    pub fn from_image(img: ImageBuffer<Luma<u16>, Vec<u16>>, corner: Point, size: Vector3, material: Arc<dyn Material>) -> Self {
+        let width = img.width() as usize;
+        let depth = img.height() as usize;
+        assert!(width >= 2 && depth >= 2, "heightmap needs at least 2x2 pixels");
+
+        let mut heights: Vec<f32> = Vec::with_capacity(width * depth);
+        for k in 0..depth {
+            let row = (depth - 1 - k) as u32;
+            for i in 0..width {
+                heights.push(img.get_pixel(i as u32, row)[0] as f32 / u16::MAX as f32 * size.y);
+            }
+        }
+
+        let mut cells = HeightLevel { width: width - 1, depth: depth - 1, bounds: Vec::with_capacity((width - 1) * (depth - 1)) };
+        for k in 0..depth - 1 {
+            for i in 0..width - 1 {
+                let corners = [heights[k * width + i], heights[k * width + i + 1], heights[(k + 1) * width + i], heights[(k + 1) * width + i + 1]];
+                cells.bounds.push((corners.iter().cloned().fold(INF_F32, f32::min), corners.iter().cloned().fold(-INF_F32, f32::max)));
+            }
+        }
+
+        let mut levels = vec![cells];
+        while levels.last().unwrap().width > 1 || levels.last().unwrap().depth > 1 {
+            let below = levels.last().unwrap();
+            let mut level = HeightLevel { width: (below.width + 1) / 2, depth: (below.depth + 1) / 2, bounds: Vec::new() };
+            for k in 0..level.depth {
+                for i in 0..level.width {
+                    let mut bound = (INF_F32, -INF_F32);
+                    for (ci, ck) in Self::children(below, i, k) {
+                        let child = below.bounds[ck * below.width + ci];
+                        bound = (bound.0.min(child.0), bound.1.max(child.1));
+                    }
+                    level.bounds.push(bound);
+                }
+            }
+            levels.push(level);
+        }
+
+        return Self { corner, size, material, width, depth, heights, levels };
+    }
+
+    fn children(below: &HeightLevel, i: usize, k: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        return (2 * k..(2 * k + 2).min(below.depth))
+            .flat_map(move |ck| (2 * i..(2 * i + 2).min(below.width)).map(move |ci| (ci, ck)));
+    }
+
+    fn cell_size(&self) -> (f32, f32) {
+        return (self.size.x / (self.width - 1) as f32, self.size.z / (self.depth - 1) as f32);
+    }
+
+    fn vertex(&self, i: usize, k: usize) -> Point {
+        let (dx, dz) = self.cell_size();
+        return self.corner + Vector3 { x: i as f32 * dx, y: self.heights[k * self.width + i], z: k as f32 * dz };
+    }
+
+    // Central differences of the heights, one-sided at the edges.
+    fn vertex_normal(&self, i: usize, k: usize) -> Vector3 {
+        let (dx, dz) = self.cell_size();
+        let (i0, i1) = (i.saturating_sub(1), (i + 1).min(self.width - 1));
+        let (k0, k1) = (k.saturating_sub(1), (k + 1).min(self.depth - 1));
+        let slope_x = (self.heights[k * self.width + i1] - self.heights[k * self.width + i0]) / ((i1 - i0) as f32 * dx);
+        let slope_z = (self.heights[k1 * self.width + i] - self.heights[k0 * self.width + i]) / ((k1 - k0) as f32 * dz);
+        return Vector3 { x: -slope_x, y: 1.0, z: -slope_z }.direction();
+    }
+
+    // Cells covered by node (i, k) of `level`, as half-open ranges of cell indices.
+    fn node_cells(&self, level: usize, i: usize, k: usize) -> ((usize, usize), (usize, usize)) {
+        let span = 1 << level;
+        let cells = &self.levels[0];
+        return ((i * span, ((i + 1) * span).min(cells.width)), (k * span, ((k + 1) * span).min(cells.depth)));
+    }
+
+    // Nearest hit on the cell's two triangles, split along the p00-p11 diagonal.
+    fn hit_cell(&self, ray: Ray, i: usize, k: usize, t_min: f32, t_max: f32) -> Option<CellHit> {
+        let mut closest = Option::None;
+        let mut t_max = t_max;
+        for corners in [[(i, k), (i + 1, k + 1), (i + 1, k)], [(i, k), (i, k + 1), (i + 1, k + 1)]].iter() {
+            let [a, b, c] = *corners;
+            if let Some((t, b1, b2)) = hit_triangle(ray, self.vertex(a.0, a.1), self.vertex(b.0, b.1), self.vertex(c.0, c.1), t_min, t_max) {
+                t_max = t;
+                closest = Option::from((t, *corners, [1.0 - b1 - b2, b1, b2]));
+            }
+        }
+        return closest;
+    }
+}
+
+impl Hittable for Heightfield {
+    // Walks the min/max quadtree nearest child first, only visiting blocks whose height range the ray passes through.
+    fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let inv_direction = Vector3 { x: 1.0 / ray.direction.x, y: 1.0 / ray.direction.y, z: 1.0 / ray.direction.z };
+        let (dx, dz) = self.cell_size();
+        let flip_x = ray.direction.x < 0.0;
+        let flip_z = ray.direction.z < 0.0;
+
+        let mut closest = Option::None;
+        let mut t_closest = t_max;
+
+        let mut stack: [(usize, usize, usize); STACK_SIZE] = [(0, 0, 0); STACK_SIZE];
+        stack[0] = (self.levels.len() - 1, 0, 0);
+        let mut stack_len = 1;
+
+        while stack_len > 0 {
+            stack_len -= 1;
+            let (level, i, k) = stack[stack_len];
+            let node = &self.levels[level];
+            let (min_height, max_height) = node.bounds[k * node.width + i];
+            let ((x0, x1), (z0, z1)) = self.node_cells(level, i, k);
+
+            let node_box = AxisAlignedBoundingBox {
+                minimum: self.corner + Vector3 { x: x0 as f32 * dx, y: min_height - 0.0001, z: z0 as f32 * dz },
+                maximum: self.corner + Vector3 { x: x1 as f32 * dx, y: max_height + 0.0001, z: z1 as f32 * dz },
+            };
+            if !node_box.hit_precomputed(ray.origin, inv_direction, t_min, t_closest) {
+                continue;
+            }
+
+            if level == 0 {
+                if let Some(hit) = self.hit_cell(ray, i, k, t_min, t_closest) {
+                    t_closest = hit.0;
+                    closest = Option::from(hit);
+                }
+                continue;
+            }
+
+            // Pushed farthest first so the child nearest the ray origin is popped next.
+            let below = &self.levels[level - 1];
+            let xs = if flip_x { [2 * i + 1, 2 * i] } else { [2 * i, 2 * i + 1] };
+            let zs = if flip_z { [2 * k + 1, 2 * k] } else { [2 * k, 2 * k + 1] };
+            for &(ci, ck) in [(xs[1], zs[1]), (xs[0], zs[1]), (xs[1], zs[0]), (xs[0], zs[0])].iter() {
+                if ci < below.width && ck < below.depth {
+                    stack[stack_len] = (level - 1, ci, ck);
+                    stack_len += 1;
+                }
+            }
+        }
+
+        let (t, corners, weights) = closest?;
+        let mut normal = Vector3 { x: 0.0, y: 0.0, z: 0.0 };
+        for (&(i, k), &weight) in corners.iter().zip(weights.iter()) {
+            normal += weight * self.vertex_normal(i, k);
+        }
+        let normal = normal.direction();
+        let intersection = ray.at_distance(t);
+        let u = ((intersection.x - self.corner.x) / self.size.x).clamp(0.0, 1.0);
+        let v = ((intersection.z - self.corner.z) / self.size.z).clamp(0.0, 1.0);
+
+        let mut hit_rec = HitRecord { intersection, normal, material: self.material.clone(), t, u, v, front_face: false, tangent: Option::None };
+        hit_rec.set_face_normal(ray);
+        return Option::from(hit_rec);
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AxisAlignedBoundingBox> {
+        let (min_height, max_height) = self.levels.last().unwrap().bounds[0];
+        return Option::from(AxisAlignedBoundingBox {
+            minimum: self.corner + Vector3 { x: 0.0, y: min_height - 0.0001, z: 0.0 },
+            maximum: self.corner + Vector3 { x: self.size.x, y: max_height + 0.0001, z: self.size.z },
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::color::Color;
+    use crate::materials::lambertian::Lambertian;
+    use crate::objects::triangle::Triangle;
+    use crate::utils::random_f32_range;
+    use crate::world::hittable_list::HittableList;
+
+    use super::*;
+
+    #[test]
+    fn hits_match_the_triangle_mesh() {
+        // Uneven sizes leave partial blocks at the edge of every quadtree level.
+        let (width, depth) = (37, 23);
+        let img = ImageBuffer::from_fn(width, depth, |i, j| {
+            let height = 0.5 + 0.25 * (i as f32 * 0.7).sin() + 0.25 * (j as f32 * 0.45).cos();
+            Luma([(height * u16::MAX as f32) as u16])
+        });
+        let material = Arc::new(Lambertian::new(Color::WHITE));
+        let terrain = Heightfield::from_image(img, Point { x: -4.0, y: 1.0, z: -3.0 }, Vector3 { x: 8.0, y: 2.0, z: 6.0 }, material.clone());
+
+        let mut mesh = HittableList::new();
+        for k in 0..depth as usize - 1 {
+            for i in 0..width as usize - 1 {
+                let [p00, p10, p01, p11] = [terrain.vertex(i, k), terrain.vertex(i + 1, k), terrain.vertex(i, k + 1), terrain.vertex(i + 1, k + 1)];
+                mesh.add(Arc::new(Triangle::new([p00, p11, p10], material.clone())));
+                mesh.add(Arc::new(Triangle::new([p00, p01, p11], material.clone())));
+            }
+        }
+
+        let mut hits = 0;
+        for n in 0..5000 {
+            // Steep rays from above, and grazing ones that cross many blocks.
+            let origin = Point { x: random_f32_range(-6.0, 6.0), y: if n % 2 == 0 { 6.0 } else { 2.6 }, z: random_f32_range(-5.0, 5.0) };
+            let target = Point { x: random_f32_range(-4.0, 4.0), y: 1.0, z: random_f32_range(-3.0, 3.0) };
+            let ray = Ray { origin, direction: (target - origin).direction(), time: 0.0, wavelengths: None, absorption: None };
+            let expected = mesh.hit(ray, 0.001, INF_F32).map(|hit_rec| hit_rec.t);
+            let actual = terrain.hit(ray, 0.001, INF_F32).map(|hit_rec| hit_rec.t);
+            match (expected, actual) {
+                (Some(e), Some(a)) => {
+                    assert!((e - a).abs() < 1e-4, "{} != {}", e, a);
+                    hits += 1;
+                }
+                (None, None) => {}
+                _ => panic!("mesh {:?}, heightfield {:?}", expected, actual),
+            }
+        }
+        assert!(hits > 2500);
+    }
+
+    #[test]
+    fn missing_heightmap_is_an_error() {
+        assert!(Heightfield::new("no/such/heightmap.png", Point::ORIGIN, Vector3 { x: 1.0, y: 1.0, z: 1.0 }, Arc::new(Lambertian::new(Color::WHITE))).is_err());
+    }
+}
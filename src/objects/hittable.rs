@@ -14,8 +14,13 @@ pub struct HitRecord {
     pub u: f32,
     pub v: f32,
     pub front_face: bool,
+    // Unit direction along the surface for strand-like shapes such as curves, for shading that needs it.
+    pub tangent: Option<Vector3>,
 }
 
+// Relative step past each crossing found by the default `hit_all`, so the same surface isn't reported twice.
+const HIT_ALL_EPSILON: f32 = 1e-4;
+
 pub trait Hittable: Send + Sync {
     fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
         return Option::None;
@@ -29,25 +34,28 @@ pub trait Hittable: Send + Sync {
     fn random(&self, o: Vector3) -> Vector3 {
         return Vector3 { x: 1.0, y: 0.0, z: 0.0 };
     }
-}
-
-pub struct FlipFace {
-    pub object: Arc<dyn Hittable>,
-}
-
-impl Hittable for FlipFace {
-    fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
-        let opt_hit_rec = self.object.hit(ray, t_min, t_max);
-        if opt_hit_rec.is_none() {
-            return opt_hit_rec;
+    // Whether hits are found by carrying rays into a wrapped object's own space, as transforms and instances do.
+    fn is_transformed(&self) -> bool {
+        return false;
+    }
+    // Every surface crossing in [t_min, t_max], nearest first. Found by repeated `hit` calls unless overridden.
+    fn hit_all(&self, ray: Ray, t_min: f32, t_max: f32) -> Vec<HitRecord> {
+        let mut hit_recs: Vec<HitRecord> = Vec::new();
+        let mut t_start = t_min;
+        while t_start < t_max {
+            match self.hit(ray, t_start, t_max) {
+                Some(hit_rec) => {
+                    t_start = hit_rec.t + HIT_ALL_EPSILON * hit_rec.t.abs().max(1.0);
+                    hit_recs.push(hit_rec);
+                }
+                None => break,
+            }
         }
-        let mut hit_rec = opt_hit_rec.unwrap();
-        hit_rec.front_face = !hit_rec.front_face;
-        return Option::from(hit_rec);
+        return hit_recs;
     }
-
-    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AxisAlignedBoundingBox> {
-        return self.object.bounding_box(t0, t1);
+    // Intersects a batch of coherent rays; acceleration structures can share traversal work between them.
+    fn hit_packet(&self, rays: &[Ray], t_min: f32, t_max: f32) -> Vec<Option<HitRecord>> {
+        return rays.iter().map(|ray| self.hit(*ray, t_min, t_max)).collect();
     }
 }
 
@@ -1,8 +1,11 @@
 use std::sync::Arc;
 
 use crate::geometry::bounding_volume::AxisAlignedBoundingBox;
+use crate::geometry::matrix::Matrix4;
+use crate::geometry::quaternion::Quaternion;
 use crate::geometry::ray::Ray;
-use crate::geometry::vector::Point;
+use crate::geometry::vector::{Point, Vector3};
+use crate::materials::material::Material;
 use crate::objects::hittable::{HitRecord, Hittable};
 use crate::utils::{degrees_to_radians, INF_F32};
 
@@ -17,6 +20,8 @@ impl Translate {
             origin: in_ray.origin - self.offset,
             direction: in_ray.direction,
             time: in_ray.time,
+            wavelengths: in_ray.wavelengths,
+            absorption: in_ray.absorption,
         };
     }
 }
@@ -46,6 +51,10 @@ impl Hittable for Translate {
             maximum: bound_box.maximum + self.offset,
         });
     }
+
+    fn is_transformed(&self) -> bool {
+        return true;
+    }
 }
 
 pub struct RotateY {
@@ -113,7 +122,7 @@ impl Hittable for RotateY {
         direction[0] = self.cos_theta * ray.direction[0] - self.sin_theta * ray.direction[2];
         direction[2] = self.sin_theta * ray.direction[0] + self.cos_theta * ray.direction[2];
 
-        let rotated_ray = Ray { origin, direction, time: ray.time };
+        let rotated_ray = Ray { origin, direction, time: ray.time, wavelengths: ray.wavelengths, absorption: ray.absorption };
         let opt_hit_rec = self.object.hit(rotated_ray, t_min, t_max);
         if opt_hit_rec.is_none() {
             return Option::None;
@@ -131,6 +140,11 @@ impl Hittable for RotateY {
 
         hit_rec.intersection = intersection;
         hit_rec.normal = normal;
+        hit_rec.tangent = hit_rec.tangent.map(|tangent| Vector3 {
+            x: self.cos_theta * tangent.x + self.sin_theta * tangent.z,
+            y: tangent.y,
+            z: -self.sin_theta * tangent.x + self.cos_theta * tangent.z,
+        });
         hit_rec.set_face_normal(rotated_ray);
 
         return Option::from(hit_rec);
@@ -139,4 +153,361 @@ impl Hittable for RotateY {
     fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AxisAlignedBoundingBox> {
         return self.bound_box.clone();
     }
+
+    fn is_transformed(&self) -> bool {
+        return true;
+    }
+}
+
+pub struct Transform {
+    object: Arc<dyn Hittable>,
+    matrix: Matrix4,
+    inverse: Matrix4,
+    normal_matrix: Matrix4,
+    bound_box: Option<AxisAlignedBoundingBox>,
+}
+
+impl Transform {
+    pub fn new(object: Arc<dyn Hittable>, matrix: Matrix4) -> Self {
+        let inverse = matrix.inverse().expect("transform matrix must be invertible");
+        let bound_box = object.bounding_box(0.0, 1.0).map(|b| matrix.transform_box(&b));
+
+        return Self {
+            object,
+            matrix,
+            inverse,
+            normal_matrix: inverse.transpose(),
+            bound_box,
+        };
+    }
+}
+
+impl Hittable for Transform {
+    fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        return transformed_hit(&self.object, &self.matrix, &self.inverse, &self.normal_matrix, ray, t_min, t_max);
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AxisAlignedBoundingBox> {
+        return self.bound_box.clone();
+    }
+
+    fn pdf_value(&self, o: Point, v: Vector3) -> f32 {
+        return transformed_pdf_value(&self.object, &self.inverse, o, v);
+    }
+
+    fn random(&self, o: Vector3) -> Vector3 {
+        let object_origin = self.inverse.transform_point(o);
+        return self.matrix.transform_vector(self.object.random(object_origin));
+    }
+
+    fn is_transformed(&self) -> bool {
+        return true;
+    }
+}
+
+// One placement of a shared shape, usually a bottom-level BVH built once for all of its instances.
+// The shape is referenced rather than copied, and the instance can swap in its own material.
+pub struct Instance {
+    transform: Transform,
+    material: Option<Arc<dyn Material>>,
+}
+
+impl Instance {
+    pub fn new(shape: Arc<dyn Hittable>, matrix: Matrix4) -> Self {
+        return Self { transform: Transform::new(shape, matrix), material: Option::None };
+    }
+
+    pub fn with_material(mut self, material: Arc<dyn Material>) -> Self {
+        self.material = Option::from(material);
+        return self;
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let mut hit_rec = self.transform.hit(ray, t_min, t_max)?;
+        if let Some(material) = self.material.as_ref() {
+            hit_rec.material = material.clone();
+        }
+        return Option::from(hit_rec);
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AxisAlignedBoundingBox> {
+        return self.transform.bounding_box(t0, t1);
+    }
+
+    fn pdf_value(&self, o: Point, v: Vector3) -> f32 {
+        return self.transform.pdf_value(o, v);
+    }
+
+    fn random(&self, o: Vector3) -> Vector3 {
+        return self.transform.random(o);
+    }
+
+    fn is_transformed(&self) -> bool {
+        return true;
+    }
+}
+
+// Object-space rays keep a unit direction, so distances are rescaled by the direction's stretch.
+fn transformed_hit(object: &Arc<dyn Hittable>, matrix: &Matrix4, inverse: &Matrix4, normal_matrix: &Matrix4, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    let direction = inverse.transform_vector(ray.direction);
+    let stretch = direction.length();
+    let object_ray = Ray {
+        origin: inverse.transform_point(ray.origin),
+        direction: direction / stretch,
+        time: ray.time,
+        wavelengths: ray.wavelengths,
+        absorption: ray.absorption,
+    };
+
+    let opt_hit_rec = object.hit(object_ray, t_min * stretch, t_max * stretch);
+    if opt_hit_rec.is_none() {
+        return Option::None;
+    }
+
+    let mut hit_rec = opt_hit_rec.unwrap();
+    hit_rec.t /= stretch;
+    hit_rec.intersection = ray.at_distance(hit_rec.t);
+    hit_rec.normal = normal_matrix.transform_vector(hit_rec.normal).direction();
+    hit_rec.tangent = hit_rec.tangent.map(|tangent| matrix.transform_vector(tangent).direction());
+    return Option::from(hit_rec);
+}
+
+fn transformed_pdf_value(object: &Arc<dyn Hittable>, inverse: &Matrix4, o: Point, v: Vector3) -> f32 {
+    let object_origin = inverse.transform_point(o);
+    let object_direction = inverse.transform_vector(v.direction());
+    let stretch = object_direction.length();
+
+    // Solid angle Jacobian of the direction map w -> Aw / |Aw|.
+    let jacobian = inverse.determinant3().abs() / stretch.powi(3);
+    return object.pdf_value(object_origin, object_direction / stretch) * jacobian;
+}
+
+#[derive(Clone, Copy)]
+pub struct TransformKeyframe {
+    pub time: f32,
+    pub translation: Vector3,
+    pub rotation: Quaternion,
+    pub scale: Vector3,
+}
+
+impl TransformKeyframe {
+    pub fn matrix(&self) -> Matrix4 {
+        return Matrix4::translation(self.translation) * Matrix4::from_quaternion(self.rotation) * Matrix4::scale(self.scale);
+    }
+
+    fn lerp(&self, other: &Self, s: f32) -> Self {
+        return Self {
+            time: self.time + s * (other.time - self.time),
+            translation: self.translation + s * (other.translation - self.translation),
+            rotation: self.rotation.slerp(other.rotation, s),
+            scale: self.scale + s * (other.scale - self.scale),
+        };
+    }
+}
+
+// Keyframed translation, rotation and scale, interpolated at each ray's time.
+// Rotations take the shorter arc between keyframes, so spins of half a turn or more need extra keyframes.
+// Not a valid light: sampling only sees the pose at time zero, so animated emitters must stay out of light lists.
+pub struct AnimatedTransform {
+    object: Arc<dyn Hittable>,
+    keyframes: Vec<TransformKeyframe>,
+}
+
+impl AnimatedTransform {
+    const BOX_SAMPLES: u32 = 32;
+
+    pub fn new(object: Arc<dyn Hittable>, mut keyframes: Vec<TransformKeyframe>) -> Self {
+        assert!(!keyframes.is_empty(), "animated transform needs at least one keyframe");
+        keyframes.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
+        return Self { object, keyframes };
+    }
+
+    pub fn keyframe_at(&self, time: f32) -> TransformKeyframe {
+        let first = &self.keyframes[0];
+        let last = &self.keyframes[self.keyframes.len() - 1];
+        if time <= first.time {
+            return *first;
+        }
+        if time >= last.time {
+            return *last;
+        }
+
+        let idx = self.keyframes.iter().position(|k| k.time > time).unwrap();
+        let k0 = &self.keyframes[idx - 1];
+        let k1 = &self.keyframes[idx];
+        return k0.lerp(k1, (time - k0.time) / (k1.time - k0.time));
+    }
+}
+
+impl Hittable for AnimatedTransform {
+    fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let matrix = self.keyframe_at(ray.time).matrix();
+        let inverse = matrix.inverse().expect("transform matrix must be invertible");
+        return transformed_hit(&self.object, &matrix, &inverse, &inverse.transpose(), ray, t_min, t_max);
+    }
+
+    // Unions the boxes at sampled times and keyframes, padded for the arc swept between samples.
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AxisAlignedBoundingBox> {
+        let object_box = self.object.bounding_box(t0, t1)?;
+
+        let mut times: Vec<f32> = (0..=Self::BOX_SAMPLES)
+            .map(|i| t0 + (t1 - t0) * i as f32 / Self::BOX_SAMPLES as f32)
+            .collect();
+        times.extend(self.keyframes.iter().map(|k| k.time).filter(|&t| t > t0 && t < t1));
+        times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let mut corner_radius: f32 = 0.0;
+        for c in 0..3 {
+            corner_radius += object_box.minimum[c].abs().max(object_box.maximum[c].abs()).powi(2);
+        }
+        corner_radius = corner_radius.sqrt();
+
+        let mut prev = self.keyframe_at(times[0]);
+        let mut bound_box = prev.matrix().transform_box(&object_box);
+        for &time in times.iter().skip(1) {
+            let key = self.keyframe_at(time);
+            let max_scale = key.scale.x.abs().max(key.scale.y.abs()).max(key.scale.z.abs())
+                .max(prev.scale.x.abs().max(prev.scale.y.abs()).max(prev.scale.z.abs()));
+            let pad = max_scale * corner_radius * (1.0 - (0.5 * prev.rotation.angle_to(key.rotation)).cos());
+            let pad_vec = Vector3 { x: pad, y: pad, z: pad };
+
+            let key_box = key.matrix().transform_box(&object_box);
+            bound_box = AxisAlignedBoundingBox::surrounding_box(bound_box, AxisAlignedBoundingBox {
+                minimum: key_box.minimum - pad_vec,
+                maximum: key_box.maximum + pad_vec,
+            });
+            prev = key;
+        }
+
+        return Option::from(bound_box);
+    }
+
+    // Light sampling has no ray time, so these use the pose at time zero and disagree with hits at any other time.
+    fn pdf_value(&self, o: Point, v: Vector3) -> f32 {
+        let inverse = self.keyframe_at(0.0).matrix().inverse().expect("transform matrix must be invertible");
+        return transformed_pdf_value(&self.object, &inverse, o, v);
+    }
+
+    fn random(&self, o: Vector3) -> Vector3 {
+        let matrix = self.keyframe_at(0.0).matrix();
+        let inverse = matrix.inverse().expect("transform matrix must be invertible");
+        return matrix.transform_vector(self.object.random(inverse.transform_point(o)));
+    }
+
+    fn is_transformed(&self) -> bool {
+        return true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::color::Color;
+    use crate::materials::lambertian::Lambertian;
+    use crate::objects::boxes::AxisAlignedBox;
+    use crate::objects::sphere::Sphere;
+    use crate::utils::PI;
+
+    fn unit_sphere() -> Arc<dyn Hittable> {
+        return Arc::new(Sphere {
+            center: Point::ORIGIN,
+            radius: 1.0,
+            material: Arc::new(Lambertian::new(Color { r: 0.5, g: 0.5, b: 0.5 })),
+        });
+    }
+
+    fn sheared() -> Matrix4 {
+        return Matrix4::translation(Vector3 { x: 0.5, y: 1.0, z: -0.5 })
+            * Matrix4::shear(0.6, 0.0, 0.0, 0.3, 0.0, 0.0)
+            * Matrix4::scale(Vector3 { x: 2.0, y: 1.0, z: 0.5 });
+    }
+
+    #[test]
+    fn sheared_normals_stay_perpendicular_to_the_surface() {
+        let matrix = sheared();
+        let transform = Transform::new(unit_sphere(), matrix);
+        let inverse = matrix.inverse().unwrap();
+
+        for _ in 0..100 {
+            let target = matrix.transform_point(0.5 * Vector3::random_unit_vector());
+            let origin = target + 10.0 * Vector3::random_unit_vector();
+            let ray = Ray { origin, direction: target - origin, time: 0.0, wavelengths: None, absorption: None };
+            let hit_rec = transform.hit(ray, 0.001, INF_F32).unwrap();
+
+            // Directions along the sphere at the object-space point, carried out to world space.
+            let object_point = inverse.transform_point(hit_rec.intersection);
+            let helper = if object_point.x.abs() < 0.9 { Vector3 { x: 1.0, y: 0.0, z: 0.0 } } else { Vector3 { x: 0.0, y: 1.0, z: 0.0 } };
+            let tangent = object_point.cross(helper).direction();
+            let bitangent = object_point.cross(tangent).direction();
+            for &along in [tangent, bitangent].iter() {
+                let world_tangent = matrix.transform_vector(along).direction();
+                assert!(hit_rec.normal.dot(world_tangent).abs() < 1e-4, "normal . tangent = {}", hit_rec.normal.dot(world_tangent));
+            }
+            assert!((hit_rec.normal.length() - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn hit_distance_is_along_the_world_ray() {
+        // A unit sphere stretched to half-width 2 along x and moved to x = 5.
+        let matrix = Matrix4::translation(Vector3 { x: 5.0, y: 0.0, z: 0.0 }) * Matrix4::scale(Vector3 { x: 2.0, y: 1.0, z: 1.0 });
+        let transform = Transform::new(unit_sphere(), matrix);
+
+        let ray = Ray { origin: Point::ORIGIN, direction: Vector3 { x: 2.0, y: 0.0, z: 0.0 }, time: 0.0, wavelengths: None, absorption: None };
+        let hit_rec = transform.hit(ray, 0.001, INF_F32).unwrap();
+        // The near side is at x = 3, one and a half direction lengths out.
+        assert!((hit_rec.t - 1.5).abs() < 1e-5, "t {}", hit_rec.t);
+        assert!((hit_rec.intersection - Point { x: 3.0, y: 0.0, z: 0.0 }).length() < 1e-5);
+        assert!((hit_rec.normal - Vector3 { x: -1.0, y: 0.0, z: 0.0 }).length() < 1e-5);
+
+        // The bounds apply to world distances.
+        assert!(transform.hit(ray, 0.001, 1.4).is_none());
+        assert!((transform.hit(ray, 1.6, INF_F32).unwrap().t - 3.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn pdf_integrates_to_one_over_the_sphere() {
+        let transform = Transform::new(unit_sphere(), sheared());
+        let o = Point { x: 3.0, y: 4.0, z: -2.0 };
+        let samples = 400_000;
+        let integral: f32 = (0..samples).map(|_| transform.pdf_value(o, Vector3::random_unit_vector())).sum::<f32>() * 4.0 * PI / samples as f32;
+        assert!((integral - 1.0).abs() < 0.03, "integral {}", integral);
+    }
+
+    // A box off to the side of the pivot, swinging most of the way round it while sliding along.
+    #[test]
+    fn padded_box_contains_the_object_along_a_rotation() {
+        let minimum = Point { x: 1.0, y: -0.5, z: -0.5 };
+        let maximum = Point { x: 2.0, y: 0.5, z: 0.5 };
+        let cube: Arc<dyn Hittable> = Arc::new(AxisAlignedBox::new(minimum, maximum, Arc::new(Lambertian::new(Color { r: 0.5, g: 0.5, b: 0.5 }))));
+        let y_axis = Vector3 { x: 0.0, y: 1.0, z: 0.0 };
+        let key = |time: f32, x: f32, angle: f32| TransformKeyframe {
+            time,
+            translation: Vector3 { x, y: 0.0, z: 0.0 },
+            rotation: Quaternion::from_axis_angle(y_axis, angle),
+            scale: Vector3 { x: 1.0, y: 1.0, z: 1.0 },
+        };
+        let animated = AnimatedTransform::new(cube, vec![key(0.0, 0.0, 0.0), key(0.5, 0.5, 120.0), key(1.0, 1.0, 240.0)]);
+
+        for &(t0, t1) in [(0.0, 1.0), (0.2, 0.7)].iter() {
+            let bound_box = animated.bounding_box(t0, t1).unwrap();
+            for i in 0..=1000 {
+                let time = t0 + (t1 - t0) * i as f32 / 1000.0;
+                let matrix = animated.keyframe_at(time).matrix();
+                for corner in 0..8 {
+                    let object_point = Point {
+                        x: if corner & 1 == 0 { minimum.x } else { maximum.x },
+                        y: if corner & 2 == 0 { minimum.y } else { maximum.y },
+                        z: if corner & 4 == 0 { minimum.z } else { maximum.z },
+                    };
+                    let p = matrix.transform_point(object_point);
+                    for c in 0..3 {
+                        assert!(p[c] >= bound_box.minimum[c] - 1e-4 && p[c] <= bound_box.maximum[c] + 1e-4, "corner {} escapes the box at time {}", corner, time);
+                    }
+                }
+            }
+        }
+    }
 }
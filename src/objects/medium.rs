@@ -8,6 +8,12 @@ use crate::geometry::vector::Vector3;
 use crate::geometry::color::Color;
 use crate::textures::solid::SolidColor;
 use crate::materials::isotropic::Isotropic;
+use crate::materials::subsurface::Subsurface;
+
+// Distance to the next collision in a medium of density -1 / `neg_inv_density`, exponentially distributed.
+pub fn sample_free_path(neg_inv_density: f32) -> f32 {
+    return neg_inv_density * random_f32().ln();
+}
 
 pub struct ConstMedium {
     pub boundary: Arc<dyn Hittable>,
@@ -48,7 +54,7 @@ impl Hittable for ConstMedium {
         if hit_rec1.t < 0.0 { hit_rec1.t = 0.0 }
 
         let dist_inside_boundary = hit_rec2.t - hit_rec1.t;
-        let hit_dist = self.neg_inv_density * random_f32().ln();
+        let hit_dist = sample_free_path(self.neg_inv_density);
 
         if hit_dist > dist_inside_boundary {
             return Option::None;
@@ -62,9 +68,42 @@ impl Hittable for ConstMedium {
             t,
             u: 0.0,
             v: 0.0,
-            front_face: true
+            front_face: true,
+            tangent: Option::None,
+        });
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AxisAlignedBoundingBox> {
+        return self.boundary.bounding_box(t0, t1);
+    }
+}
+
+// A boundary filled with a scattering volume that light enters and leaves through the surface, walked by the
+// `Subsurface` material rather than by ray hits. The walk traces the boundary with world-space hit points, so
+// neither this nor its boundary may be wrapped in a transform or instance; place the boundary shape directly.
+pub struct SubsurfaceMedium {
+    pub boundary: Arc<dyn Hittable>,
+    pub material: Arc<dyn Material>,
+}
+
+impl SubsurfaceMedium {
+    pub fn new(boundary: Arc<dyn Hittable>, refractive_index: f32, albedo: Color, mean_free_path: Color) -> Result<Self, String> {
+        if boundary.is_transformed() {
+            return Err(String::from("subsurface boundary must be placed directly, not wrapped in a transform"));
+        }
+        return Ok(Self {
+            boundary: boundary.clone(),
+            material: Arc::new(Subsurface { boundary, refractive_index, albedo, mean_free_path }),
         });
     }
+}
+
+impl Hittable for SubsurfaceMedium {
+    fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let mut hit_rec = self.boundary.hit(ray, t_min, t_max)?;
+        hit_rec.material = self.material.clone();
+        return Option::from(hit_rec);
+    }
 
     fn bounding_box(&self, t0: f32, t1: f32) -> Option<AxisAlignedBoundingBox> {
         return self.boundary.bounding_box(t0, t1);
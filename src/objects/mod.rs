@@ -1,6 +1,12 @@
 pub mod hittable;
 pub mod sphere;
-pub mod rectangle;
+pub mod quad;
 pub mod boxes;
 pub mod instances;
-pub mod medium;
\ No newline at end of file
+pub mod medium;
+pub mod triangle;
+pub mod csg;
+pub mod quadrics;
+pub mod sdf;
+pub mod heightfield;
+pub mod curve;
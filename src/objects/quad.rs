@@ -0,0 +1,142 @@
+use std::sync::Arc;
+
+use crate::geometry::bounding_volume::AxisAlignedBoundingBox;
+use crate::geometry::ray::Ray;
+use crate::geometry::vector::{Point, Vector3};
+use crate::materials::material::Material;
+use crate::objects::hittable::{HitRecord, Hittable};
+use crate::utils::{INF_F32, random_f32};
+
+// Parallelogram spanned by the edges `u` and `v` from `corner`. It faces along u × v, and the texture
+// coordinates are the fractions of `u` and `v` travelled from the corner.
+pub struct Quad {
+    pub corner: Point,
+    pub u: Vector3,
+    pub v: Vector3,
+    pub material: Arc<dyn Material>,
+}
+
+impl Quad {
+    pub fn new(corner: Point, u: Vector3, v: Vector3, material: Arc<dyn Material>) -> Self {
+        return Self { corner, u, v, material };
+    }
+
+    fn geometric_normal(&self) -> Vector3 {
+        return self.u.cross(self.v).direction();
+    }
+
+    fn area(&self) -> f32 {
+        return self.u.cross(self.v).length();
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let n = self.u.cross(self.v);
+        let denom = n.dot(ray.direction);
+        if denom.abs() < 1e-8 {
+            return Option::None;
+        }
+
+        let t = n.dot(self.corner - ray.origin) / denom;
+        if t < t_min || t > t_max {
+            return Option::None;
+        }
+
+        // Coordinates of the hit point along the two edges.
+        let w = n / n.dot(n);
+        let planar = ray.origin + t * ray.direction - self.corner;
+        let alpha = w.dot(planar.cross(self.v));
+        let beta = w.dot(self.u.cross(planar));
+        if alpha < 0.0 || alpha > 1.0 || beta < 0.0 || beta > 1.0 {
+            return Option::None;
+        }
+
+        let intersection = ray.at_distance(t);
+        let normal = n.direction();
+        let material = self.material.clone();
+
+        let mut hit_rec = HitRecord { intersection, normal, material, t, u: alpha, v: beta, front_face: false, tangent: Option::None };
+        hit_rec.set_face_normal(ray);
+        return Option::from(hit_rec);
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AxisAlignedBoundingBox> {
+        let mut min = Point { x: INF_F32, y: INF_F32, z: INF_F32 };
+        let mut max = Point { x: -INF_F32, y: -INF_F32, z: -INF_F32 };
+
+        let corners = [self.corner, self.corner + self.u, self.corner + self.v, self.corner + self.u + self.v];
+        for corner in corners.iter() {
+            for c in 0..3 {
+                min[c] = min[c].min(corner[c] - 0.0001);
+                max[c] = max[c].max(corner[c] + 0.0001);
+            }
+        }
+
+        return Option::from(AxisAlignedBoundingBox { minimum: min, maximum: max });
+    }
+
+    fn pdf_value(&self, origin: Point, v: Vector3) -> f32 {
+        let opt_hit_rec = self.hit(Ray { origin, direction: v, time: 0.0, wavelengths: None, absorption: None }, 0.001, INF_F32);
+        if opt_hit_rec.is_none() {
+            return 0.0;
+        }
+
+        let hit_rec = opt_hit_rec.unwrap();
+        let dist_squared = hit_rec.t.powi(2) * v.length_squared();
+        let cosine = (v.dot(self.geometric_normal()) / v.length()).abs();
+
+        return dist_squared / (cosine * self.area());
+    }
+
+    fn random(&self, origin: Vector3) -> Vector3 {
+        let random_point = self.corner + random_f32() * self.u + random_f32() * self.v;
+        return random_point - origin;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::color::Color;
+    use crate::materials::lambertian::Lambertian;
+
+    use super::*;
+
+    // 2 by 3, lying in y = 0 and facing down along u × v.
+    fn quad() -> Quad {
+        return Quad::new(Point { x: 1.0, y: 0.0, z: 0.0 }, Vector3 { x: 2.0, y: 0.0, z: 0.0 }, Vector3 { x: 0.0, y: 0.0, z: 3.0 }, Arc::new(Lambertian::new(Color::WHITE)));
+    }
+
+    fn ray(origin: Point, direction: Vector3) -> Ray {
+        return Ray { origin, direction, time: 0.0, wavelengths: None, absorption: None };
+    }
+
+    #[test]
+    fn hit_is_textured_by_the_edges_travelled() {
+        let up = Vector3 { x: 0.0, y: 1.0, z: 0.0 };
+        let hit_rec = quad().hit(ray(Point { x: 2.5, y: -5.0, z: 0.75 }, up), 0.001, INF_F32).unwrap();
+        assert!((hit_rec.t - 5.0).abs() < 1e-5);
+        assert!((hit_rec.u - 0.75).abs() < 1e-5 && (hit_rec.v - 0.25).abs() < 1e-5);
+        assert!(hit_rec.front_face);
+        assert!((hit_rec.normal + up).length() < 1e-5);
+
+        // From above it is seen from behind.
+        assert!(!quad().hit(ray(Point { x: 2.5, y: 5.0, z: 0.75 }, -up), 0.001, INF_F32).unwrap().front_face);
+        assert!(quad().hit(ray(Point { x: 3.5, y: -5.0, z: 0.75 }, up), 0.001, INF_F32).is_none());
+    }
+
+    #[test]
+    fn pdf_is_distance_squared_over_projected_area() {
+        let o = Point { x: 2.0, y: -4.0, z: 1.0 };
+        let target = Point { x: 2.5, y: 0.0, z: 0.75 };
+        let to_target = target - o;
+        let cosine = to_target.direction().y.abs();
+        let expected = to_target.length_squared() / (6.0 * cosine);
+        // However long the direction.
+        for &scale in [1.0, 0.1, 3.0].iter() {
+            let pdf = quad().pdf_value(o, scale * to_target);
+            assert!((pdf - expected).abs() < 1e-4 * expected, "{} != {}", pdf, expected);
+        }
+        assert_eq!(quad().pdf_value(o, Vector3 { x: 5.0, y: 4.0, z: 0.0 }), 0.0);
+    }
+}
@@ -0,0 +1,645 @@
+use std::sync::Arc;
+
+use crate::geometry::bounding_volume::AxisAlignedBoundingBox;
+use crate::geometry::ray::Ray;
+use crate::geometry::vector::{Point, Vector3};
+use crate::materials::material::Material;
+use crate::objects::hittable::{HitRecord, Hittable};
+use crate::utils::{INF_F32, PI, random_f32};
+
+// All shapes here stand on `center` with their axis along +y; use a Transform to orient them.
+
+pub struct Cylinder {
+    pub center: Point,
+    pub radius: f32,
+    pub height: f32,
+    // Closed with disks at both ends, which makes it usable in CSG.
+    pub capped: bool,
+    pub material: Arc<dyn Material>,
+}
+
+impl Cylinder {
+    fn area(&self) -> f32 {
+        let caps = if self.capped { 2.0 * PI * self.radius.powi(2) } else { 0.0 };
+        return 2.0 * PI * self.radius * self.height + caps;
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let o = ray.origin - self.center;
+        let d = ray.direction;
+        let mut closest: Option<(f32, Vector3, (f32, f32))> = Option::None;
+
+        let a = d.x.powi(2) + d.z.powi(2);
+        let half_b = o.x * d.x + o.z * d.z;
+        let c = o.x.powi(2) + o.z.powi(2) - self.radius.powi(2);
+        let (roots, count) = quadratic_roots(a, half_b, c);
+        for &t in roots[..count].iter() {
+            let p = o + t * d;
+            if p.y >= 0.0 && p.y <= self.height {
+                let normal = Vector3 { x: p.x, y: 0.0, z: p.z } / self.radius;
+                keep_nearest(&mut closest, t, t_min, t_max, normal, (azimuth(p), p.y / self.height));
+            }
+        }
+
+        if self.capped {
+            for &(y, normal_y) in [(0.0, -1.0), (self.height, 1.0)].iter() {
+                if let Some((t, p)) = plane_hit(o, d, y) {
+                    let rho = (p.x.powi(2) + p.z.powi(2)).sqrt();
+                    if rho <= self.radius {
+                        let normal = Vector3 { x: 0.0, y: normal_y, z: 0.0 };
+                        keep_nearest(&mut closest, t, t_min, t_max, normal, (azimuth(p), rho / self.radius));
+                    }
+                }
+            }
+        }
+
+        let (t, normal, uv) = closest?;
+        return Option::from(surface_hit(ray, t, normal, uv, &self.material));
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AxisAlignedBoundingBox> {
+        return Option::from(AxisAlignedBoundingBox {
+            minimum: self.center - Vector3 { x: self.radius, y: 0.0, z: self.radius },
+            maximum: self.center + Vector3 { x: self.radius, y: self.height, z: self.radius },
+        });
+    }
+
+    fn pdf_value(&self, o: Point, v: Vector3) -> f32 {
+        return area_pdf_value(self, self.area(), o, v);
+    }
+
+    fn random(&self, o: Vector3) -> Vector3 {
+        let side = 2.0 * PI * self.radius * self.height;
+        let phi = 2.0 * PI * random_f32();
+        let local = if random_f32() * self.area() < side {
+            Vector3 { x: self.radius * phi.cos(), y: self.height * random_f32(), z: self.radius * phi.sin() }
+        } else {
+            let rho = self.radius * random_f32().sqrt();
+            let y = if random_f32() < 0.5 { 0.0 } else { self.height };
+            Vector3 { x: rho * phi.cos(), y, z: rho * phi.sin() }
+        };
+        return self.center + local - o;
+    }
+}
+
+// Narrows from `radius` at the base to a point `height` above it.
+pub struct Cone {
+    pub center: Point,
+    pub radius: f32,
+    pub height: f32,
+    // Closed with a disk at the base.
+    pub capped: bool,
+    pub material: Arc<dyn Material>,
+}
+
+impl Cone {
+    fn side_area(&self) -> f32 {
+        return PI * self.radius * (self.radius.powi(2) + self.height.powi(2)).sqrt();
+    }
+
+    fn area(&self) -> f32 {
+        let cap = if self.capped { PI * self.radius.powi(2) } else { 0.0 };
+        return self.side_area() + cap;
+    }
+}
+
+impl Hittable for Cone {
+    fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let o = ray.origin - self.center;
+        let d = ray.direction;
+        let mut closest: Option<(f32, Vector3, (f32, f32))> = Option::None;
+
+        // x² + z² = (k (h - y))², with k the radius lost per unit of height.
+        let k2 = (self.radius / self.height).powi(2);
+        let oy = self.height - o.y;
+        let a = d.x.powi(2) + d.z.powi(2) - k2 * d.y.powi(2);
+        let half_b = o.x * d.x + o.z * d.z + k2 * oy * d.y;
+        let c = o.x.powi(2) + o.z.powi(2) - k2 * oy.powi(2);
+        let (roots, count) = quadratic_roots(a, half_b, c);
+        for &t in roots[..count].iter() {
+            let p = o + t * d;
+            if p.y >= 0.0 && p.y <= self.height {
+                let normal = Vector3 { x: p.x, y: k2 * (self.height - p.y), z: p.z }.direction();
+                keep_nearest(&mut closest, t, t_min, t_max, normal, (azimuth(p), p.y / self.height));
+            }
+        }
+
+        if self.capped {
+            if let Some((t, p)) = plane_hit(o, d, 0.0) {
+                let rho = (p.x.powi(2) + p.z.powi(2)).sqrt();
+                if rho <= self.radius {
+                    let normal = Vector3 { x: 0.0, y: -1.0, z: 0.0 };
+                    keep_nearest(&mut closest, t, t_min, t_max, normal, (azimuth(p), rho / self.radius));
+                }
+            }
+        }
+
+        let (t, normal, uv) = closest?;
+        return Option::from(surface_hit(ray, t, normal, uv, &self.material));
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AxisAlignedBoundingBox> {
+        return Option::from(AxisAlignedBoundingBox {
+            minimum: self.center - Vector3 { x: self.radius, y: 0.0, z: self.radius },
+            maximum: self.center + Vector3 { x: self.radius, y: self.height, z: self.radius },
+        });
+    }
+
+    fn pdf_value(&self, o: Point, v: Vector3) -> f32 {
+        return area_pdf_value(self, self.area(), o, v);
+    }
+
+    fn random(&self, o: Vector3) -> Vector3 {
+        let phi = 2.0 * PI * random_f32();
+        let local = if random_f32() * self.area() < self.side_area() {
+            // The side's area grows linearly with distance from the apex.
+            let s = random_f32().sqrt();
+            Vector3 { x: s * self.radius * phi.cos(), y: self.height * (1.0 - s), z: s * self.radius * phi.sin() }
+        } else {
+            let rho = self.radius * random_f32().sqrt();
+            Vector3 { x: rho * phi.cos(), y: 0.0, z: rho * phi.sin() }
+        };
+        return self.center + local - o;
+    }
+}
+
+// Flat ring facing +y; an `inner_radius` of zero gives a full disk.
+pub struct Disk {
+    pub center: Point,
+    pub radius: f32,
+    pub inner_radius: f32,
+    pub material: Arc<dyn Material>,
+}
+
+impl Disk {
+    fn area(&self) -> f32 {
+        return PI * (self.radius.powi(2) - self.inner_radius.powi(2));
+    }
+}
+
+impl Hittable for Disk {
+    fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let o = ray.origin - self.center;
+        let (t, p) = plane_hit(o, ray.direction, 0.0)?;
+        if t < t_min || t > t_max {
+            return Option::None;
+        }
+
+        let rho = (p.x.powi(2) + p.z.powi(2)).sqrt();
+        if rho > self.radius || rho < self.inner_radius {
+            return Option::None;
+        }
+
+        let v = (self.radius - rho) / (self.radius - self.inner_radius);
+        return Option::from(surface_hit(ray, t, Vector3 { x: 0.0, y: 1.0, z: 0.0 }, (azimuth(p), v), &self.material));
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AxisAlignedBoundingBox> {
+        return Option::from(AxisAlignedBoundingBox {
+            minimum: self.center - Vector3 { x: self.radius, y: 0.0001, z: self.radius },
+            maximum: self.center + Vector3 { x: self.radius, y: 0.0001, z: self.radius },
+        });
+    }
+
+    fn pdf_value(&self, o: Point, v: Vector3) -> f32 {
+        return area_pdf_value(self, self.area(), o, v);
+    }
+
+    fn random(&self, o: Vector3) -> Vector3 {
+        let phi = 2.0 * PI * random_f32();
+        let rho = (self.inner_radius.powi(2) + random_f32() * (self.radius.powi(2) - self.inner_radius.powi(2))).sqrt();
+        return self.center + Vector3 { x: rho * phi.cos(), y: 0.0, z: rho * phi.sin() } - o;
+    }
+}
+
+// Ring of radius `minor_radius` swept around the y axis at distance `major_radius`.
+pub struct Torus {
+    pub center: Point,
+    pub major_radius: f32,
+    pub minor_radius: f32,
+    pub material: Arc<dyn Material>,
+}
+
+impl Torus {
+    fn area(&self) -> f32 {
+        return 4.0 * PI.powi(2) * self.major_radius * self.minor_radius;
+    }
+}
+
+impl Hittable for Torus {
+    fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        // The quartic is solved along a unit direction and its roots scaled back to the ray's own t.
+        let o = ray.origin - self.center;
+        let scale = ray.direction.length();
+        let d = ray.direction / scale;
+
+        // Solving from the point on the ray closest to the centre keeps the coefficients small.
+        let t_shift = -o.dot(d);
+        let shifted = o + t_shift * d;
+        let bound = self.major_radius + self.minor_radius;
+        if shifted.length_squared() > bound.powi(2) {
+            return Option::None;
+        }
+
+        let (ox, oy, oz) = (shifted.x as f64, shifted.y as f64, shifted.z as f64);
+        let (dx, dy, dz) = (d.x as f64, d.y as f64, d.z as f64);
+        let major2 = (self.major_radius as f64).powi(2);
+        let s = ox * ox + oy * oy + oz * oz + major2 - (self.minor_radius as f64).powi(2);
+        let b = ox * dx + oy * dy + oz * dz;
+        let (roots, count) = solve_quartic(
+            4.0 * b,
+            4.0 * b * b + 2.0 * s - 4.0 * major2 * (dx * dx + dz * dz),
+            4.0 * b * s - 8.0 * major2 * (ox * dx + oz * dz),
+            s * s - 4.0 * major2 * (ox * ox + oz * oz),
+        );
+
+        let distance = roots[..count].iter()
+            .map(|&root| root as f32 + t_shift)
+            .filter(|&distance| distance >= t_min * scale && distance <= t_max * scale)
+            .fold(INF_F32, f32::min);
+        if distance == INF_F32 {
+            return Option::None;
+        }
+
+        let t = distance / scale;
+        let p = o + distance * d;
+        let rho = (p.x.powi(2) + p.z.powi(2)).sqrt();
+        let ring_center = (self.major_radius / rho) * Vector3 { x: p.x, y: 0.0, z: p.z };
+        let normal = (p - ring_center).direction();
+        let theta = p.y.atan2(rho - self.major_radius);
+        let v = (theta + PI) / (2.0 * PI);
+        return Option::from(surface_hit(ray, t, normal, (azimuth(p), v), &self.material));
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AxisAlignedBoundingBox> {
+        let extent = self.major_radius + self.minor_radius;
+        let half = Vector3 { x: extent, y: self.minor_radius, z: extent };
+        return Option::from(AxisAlignedBoundingBox {
+            minimum: self.center - half,
+            maximum: self.center + half,
+        });
+    }
+
+    fn pdf_value(&self, o: Point, v: Vector3) -> f32 {
+        return area_pdf_value(self, self.area(), o, v);
+    }
+
+    // The outer side of the tube has more area than the inner side, so the tube angle is drawn by rejection.
+    fn random(&self, o: Vector3) -> Vector3 {
+        let (major, minor) = (self.major_radius, self.minor_radius);
+        let mut theta = 2.0 * PI * random_f32();
+        while random_f32() * (major + minor) > major + minor * theta.cos() {
+            theta = 2.0 * PI * random_f32();
+        }
+
+        let phi = 2.0 * PI * random_f32();
+        let rho = major + minor * theta.cos();
+        return self.center + Vector3 { x: rho * phi.cos(), y: minor * theta.sin(), z: rho * phi.sin() } - o;
+    }
+}
+
+// Open bowl with its vertex at `center`, widening to `radius` at `height`.
+pub struct Paraboloid {
+    pub center: Point,
+    pub radius: f32,
+    pub height: f32,
+    pub material: Arc<dyn Material>,
+}
+
+impl Paraboloid {
+    fn area(&self) -> f32 {
+        let (r, h) = (self.radius, self.height);
+        return PI * r / (6.0 * h.powi(2)) * ((r.powi(2) + 4.0 * h.powi(2)).powf(1.5) - r.powi(3));
+    }
+}
+
+impl Hittable for Paraboloid {
+    fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let o = ray.origin - self.center;
+        let d = ray.direction;
+        let mut closest: Option<(f32, Vector3, (f32, f32))> = Option::None;
+
+        // y = k (x² + z²)
+        let k = self.height / self.radius.powi(2);
+        let a = k * (d.x.powi(2) + d.z.powi(2));
+        let half_b = k * (o.x * d.x + o.z * d.z) - 0.5 * d.y;
+        let c = k * (o.x.powi(2) + o.z.powi(2)) - o.y;
+        let (roots, count) = quadratic_roots(a, half_b, c);
+        for &t in roots[..count].iter() {
+            let p = o + t * d;
+            if p.y <= self.height {
+                let normal = Vector3 { x: 2.0 * k * p.x, y: -1.0, z: 2.0 * k * p.z }.direction();
+                keep_nearest(&mut closest, t, t_min, t_max, normal, (azimuth(p), p.y / self.height));
+            }
+        }
+
+        let (t, normal, uv) = closest?;
+        return Option::from(surface_hit(ray, t, normal, uv, &self.material));
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AxisAlignedBoundingBox> {
+        return Option::from(AxisAlignedBoundingBox {
+            minimum: self.center - Vector3 { x: self.radius, y: 0.0001, z: self.radius },
+            maximum: self.center + Vector3 { x: self.radius, y: self.height, z: self.radius },
+        });
+    }
+
+    fn pdf_value(&self, o: Point, v: Vector3) -> f32 {
+        return area_pdf_value(self, self.area(), o, v);
+    }
+
+    // Inverts the area CDF over the distance from the axis, which is (1 + (2kρ)²)^(3/2) up to scale.
+    fn random(&self, o: Vector3) -> Vector3 {
+        let k2 = 2.0 * self.height / self.radius.powi(2);
+        let outer = (1.0 + (k2 * self.radius).powi(2)).powf(1.5);
+        let w = 1.0 + random_f32() * (outer - 1.0);
+        let rho = (w.powf(2.0 / 3.0) - 1.0).max(0.0).sqrt() / k2;
+
+        let phi = 2.0 * PI * random_f32();
+        let y = self.height * (rho / self.radius).powi(2);
+        return self.center + Vector3 { x: rho * phi.cos(), y, z: rho * phi.sin() } - o;
+    }
+}
+
+fn surface_hit(ray: Ray, t: f32, outward_normal: Vector3, (u, v): (f32, f32), material: &Arc<dyn Material>) -> HitRecord {
+    let mut hit_rec = HitRecord {
+        intersection: ray.at_distance(t),
+        normal: outward_normal,
+        material: material.clone(),
+        t,
+        u,
+        v,
+        front_face: false,
+        tangent: Option::None,
+    };
+    hit_rec.set_face_normal(ray);
+    return hit_rec;
+}
+
+fn keep_nearest(closest: &mut Option<(f32, Vector3, (f32, f32))>, t: f32, t_min: f32, t_max: f32, normal: Vector3, uv: (f32, f32)) {
+    if t >= t_min && t <= t_max && closest.as_ref().map_or(true, |c| t < c.0) {
+        *closest = Option::from((t, normal, uv));
+    }
+}
+
+// Real roots of a t² + 2 half_b t + c = 0, and how many of the two slots hold one.
+fn quadratic_roots(a: f32, half_b: f32, c: f32) -> ([f32; 2], usize) {
+    if a.abs() < 1e-12 {
+        return if half_b.abs() < 1e-12 { ([0.0; 2], 0) } else { ([-c / (2.0 * half_b), 0.0], 1) };
+    }
+
+    let discriminant = half_b.powi(2) - a * c;
+    if discriminant < 0.0 {
+        return ([0.0; 2], 0);
+    }
+    let root = discriminant.sqrt();
+    return ([(-half_b - root) / a, (-half_b + root) / a], 2);
+}
+
+fn plane_hit(o: Vector3, d: Vector3, y: f32) -> Option<(f32, Vector3)> {
+    if d.y == 0.0 {
+        return Option::None;
+    }
+    let t = (y - o.y) / d.y;
+    return Option::from((t, o + t * d));
+}
+
+fn azimuth(p: Vector3) -> f32 {
+    return (p.z.atan2(p.x) + 2.0 * PI) % (2.0 * PI) / (2.0 * PI);
+}
+
+// Area lights sample points uniformly over the surface, so the solid angle density is summed over every
+// crossing along the direction.
+fn area_pdf_value(shape: &dyn Hittable, area: f32, o: Point, v: Vector3) -> f32 {
+    let ray = Ray { origin: o, direction: v.direction(), time: 0.0, wavelengths: None, absorption: None };
+    return shape.hit_all(ray, 0.001, INF_F32).iter().map(|hit_rec| {
+        let cosine = ray.direction.dot(hit_rec.normal).abs();
+        hit_rec.t.powi(2) / (cosine * area)
+    }).sum();
+}
+
+// Real roots of x⁴ + a x³ + b x² + c x + d = 0 by Ferrari's method, each refined with Newton steps, and how
+// many of the four slots hold one.
+fn solve_quartic(a: f64, b: f64, c: f64, d: f64) -> ([f64; 4], usize) {
+    // Depressed quartic y⁴ + p y² + q y + r with x = y - a / 4.
+    let shift = a / 4.0;
+    let p = b - 6.0 * shift * shift;
+    let q = c - 2.0 * b * shift + 8.0 * shift.powi(3);
+    let r = d - c * shift + b * shift * shift - 3.0 * shift.powi(4);
+
+    let mut roots = [0.0; 4];
+    let mut count = 0;
+    let mut push = |y: f64| {
+        roots[count] = y;
+        count += 1;
+    };
+    if q.abs() < 1e-12 {
+        // Biquadratic.
+        let discriminant = p * p - 4.0 * r;
+        if discriminant >= 0.0 {
+            for &z in [(-p - discriminant.sqrt()) / 2.0, (-p + discriminant.sqrt()) / 2.0].iter() {
+                if z >= 0.0 {
+                    push(-z.sqrt());
+                    push(z.sqrt());
+                }
+            }
+        }
+    } else {
+        // A positive root m of the resolvent splits the quartic into two quadratics.
+        let m = largest_cubic_root(p, p * p / 4.0 - r, -q * q / 8.0);
+        let sqrt_2m = (2.0 * m).sqrt();
+        for &sign in [-1.0, 1.0].iter() {
+            let half_b = sign * sqrt_2m / 2.0;
+            let constant = p / 2.0 + m - sign * q / (2.0 * sqrt_2m);
+            let discriminant = half_b * half_b - constant;
+            if discriminant >= 0.0 {
+                push(-half_b - discriminant.sqrt());
+                push(-half_b + discriminant.sqrt());
+            }
+        }
+    }
+
+    for root in roots[..count].iter_mut() {
+        let mut x = *root - shift;
+        for _ in 0..2 {
+            let f = (((x + a) * x + b) * x + c) * x + d;
+            let df = ((4.0 * x + 3.0 * a) * x + 2.0 * b) * x + c;
+            if df != 0.0 {
+                x -= f / df;
+            }
+        }
+        *root = x;
+    }
+    return (roots, count);
+}
+
+// Largest real root of m³ + a m² + b m + c = 0.
+fn largest_cubic_root(a: f64, b: f64, c: f64) -> f64 {
+    let shift = a / 3.0;
+    let p = b - a * a / 3.0;
+    let q = 2.0 * shift.powi(3) - shift * b + c;
+    let discriminant = (q / 2.0).powi(2) + (p / 3.0).powi(3);
+
+    let z = if discriminant > 0.0 {
+        let root = discriminant.sqrt();
+        (-q / 2.0 + root).cbrt() + (-q / 2.0 - root).cbrt()
+    } else if p == 0.0 {
+        0.0
+    } else {
+        let rho = (-p / 3.0).sqrt();
+        let cos_angle = (-q / (2.0 * rho.powi(3))).clamp(-1.0, 1.0);
+        2.0 * rho * (cos_angle.acos() / 3.0).cos()
+    };
+    return z - shift;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::color::Color;
+    use crate::materials::lambertian::Lambertian;
+
+    fn plaster() -> Arc<dyn Material> {
+        return Arc::new(Lambertian::new(Color { r: 0.5, g: 0.5, b: 0.5 }));
+    }
+
+    fn ray(origin: Point, direction: Vector3) -> Ray {
+        return Ray { origin, direction, time: 0.0, wavelengths: None, absorption: None };
+    }
+
+    // Checks t, the normal facing the ray, which side was hit and the texture coordinates.
+    fn assert_hit(shape: &dyn Hittable, ray: Ray, t: f32, normal: Vector3, front_face: bool, (u, v): (f32, f32)) {
+        let hit_rec = shape.hit(ray, 0.001, INF_F32).unwrap();
+        assert!((hit_rec.t - t).abs() < 1e-4, "t {} != {}", hit_rec.t, t);
+        assert!((hit_rec.normal - normal).length() < 1e-4, "normal {:?}", (hit_rec.normal.x, hit_rec.normal.y, hit_rec.normal.z));
+        assert_eq!(hit_rec.front_face, front_face);
+        assert!((hit_rec.u - u).abs() < 1e-4 && (hit_rec.v - v).abs() < 1e-4, "uv ({}, {})", hit_rec.u, hit_rec.v);
+    }
+
+    fn torus() -> Torus {
+        return Torus {
+            center: Point { x: 0.0, y: 0.0, z: 0.0 },
+            major_radius: 2.0,
+            minor_radius: 0.5,
+            material: Arc::new(Lambertian::new(Color { r: 0.5, g: 0.5, b: 0.5 })),
+        };
+    }
+
+    #[test]
+    fn quartic_roots_satisfy_the_polynomial() {
+        // (x - 1)(x + 2)(x - 3)(x + 0.5) = x⁴ - 1.5 x³ - 6 x² + 3.5 x + 3
+        let (roots, count) = solve_quartic(-1.5, -6.0, 3.5, 3.0);
+        assert_eq!(count, 4);
+        let mut sorted = roots;
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        for (root, expected) in sorted.iter().zip([-2.0, -0.5, 1.0, 3.0].iter()) {
+            assert!((root - expected).abs() < 1e-9, "{} != {}", root, expected);
+        }
+
+        let (_, count) = solve_quartic(0.0, 0.0, 0.0, 1.0);
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn quadratic_roots_are_ordered() {
+        let (roots, count) = quadratic_roots(1.0, -2.0, 3.0);
+        assert_eq!(count, 2);
+        assert_eq!(roots, [1.0, 3.0]);
+        assert_eq!(quadratic_roots(1.0, 0.0, 1.0).1, 0);
+    }
+
+    #[test]
+    fn torus_hit_is_independent_of_direction_length() {
+        let torus = torus();
+        let origin = Point { x: -10.0, y: 0.1, z: 0.0 };
+        let unit = Ray { origin, direction: Vector3 { x: 1.0, y: 0.0, z: 0.0 }, time: 0.0, wavelengths: None, absorption: None };
+        let scaled = Ray { direction: 4.0 * unit.direction, ..unit };
+
+        let unit_hit = torus.hit(unit, 0.001, INF_F32).unwrap();
+        let scaled_hit = torus.hit(scaled, 0.001, INF_F32).unwrap();
+        assert!((unit_hit.t - 4.0 * scaled_hit.t).abs() < 1e-3);
+        assert!((unit_hit.intersection - scaled_hit.intersection).length() < 1e-3);
+        // The ray enters the tube's outer side at x = -(R + √(r² - y²)).
+        assert!((unit_hit.intersection.x + 2.0 + (0.25f32 - 0.01).sqrt()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn cylinder_hits_its_side_and_caps() {
+        let cylinder = Cylinder { center: Point::ORIGIN, radius: 1.0, height: 2.0, capped: true, material: plaster() };
+        let x = Vector3 { x: 1.0, y: 0.0, z: 0.0 };
+        assert_hit(&cylinder, ray(Point { x: -5.0, y: 1.0, z: 0.0 }, x), 4.0, -x, true, (0.5, 0.5));
+        assert_hit(&cylinder, ray(Point { x: 0.0, y: 1.0, z: 0.0 }, x), 1.0, -x, false, (0.0, 0.5));
+        assert_hit(&cylinder, ray(Point { x: 0.5, y: 5.0, z: 0.0 }, Vector3 { x: 0.0, y: -1.0, z: 0.0 }), 3.0, Vector3 { x: 0.0, y: 1.0, z: 0.0 }, true, (0.0, 0.5));
+        assert!(cylinder.hit(ray(Point { x: -5.0, y: 2.5, z: 0.0 }, x), 0.001, INF_F32).is_none());
+    }
+
+    #[test]
+    fn cone_narrows_to_its_apex() {
+        let cone = Cone { center: Point::ORIGIN, radius: 1.0, height: 2.0, capped: true, material: plaster() };
+        // Halfway up the radius is halved, and the side leans back by the radius lost per unit of height.
+        let side_normal = Vector3 { x: -2.0, y: 1.0, z: 0.0 }.direction();
+        assert_hit(&cone, ray(Point { x: -5.0, y: 1.0, z: 0.0 }, Vector3 { x: 1.0, y: 0.0, z: 0.0 }), 4.5, side_normal, true, (0.5, 0.5));
+        assert_hit(&cone, ray(Point { x: 0.3, y: -3.0, z: 0.0 }, Vector3 { x: 0.0, y: 1.0, z: 0.0 }), 3.0, Vector3 { x: 0.0, y: -1.0, z: 0.0 }, true, (0.0, 0.3));
+        assert!(cone.hit(ray(Point { x: -5.0, y: 1.0, z: 0.6 }, Vector3 { x: 1.0, y: 0.0, z: 0.0 }), 0.001, INF_F32).is_none());
+    }
+
+    #[test]
+    fn disk_has_a_hole() {
+        let disk = Disk { center: Point::ORIGIN, radius: 1.0, inner_radius: 0.4, material: plaster() };
+        let down = Vector3 { x: 0.0, y: -1.0, z: 0.0 };
+        assert_hit(&disk, ray(Point { x: 0.7, y: 3.0, z: 0.0 }, down), 3.0, -down, true, (0.0, 0.5));
+        assert_hit(&disk, ray(Point { x: 0.0, y: -2.0, z: 0.7 }, -down), 2.0, down, false, (0.25, 0.5));
+        assert!(disk.hit(ray(Point { x: 0.2, y: 3.0, z: 0.0 }, down), 0.001, INF_F32).is_none());
+        assert!(disk.hit(ray(Point { x: 1.2, y: 3.0, z: 0.0 }, down), 0.001, INF_F32).is_none());
+    }
+
+    #[test]
+    fn paraboloid_is_an_open_bowl() {
+        let bowl = Paraboloid { center: Point::ORIGIN, radius: 1.0, height: 1.0, material: plaster() };
+        let up = Vector3 { x: 0.0, y: 1.0, z: 0.0 };
+        // y = x², so at x = 0.5 the surface is at y = 0.25 with outward normal (1, -1, 0) / √2.
+        let outward = Vector3 { x: 1.0, y: -1.0, z: 0.0 }.direction();
+        assert_hit(&bowl, ray(Point { x: 0.5, y: 5.0, z: 0.0 }, -up), 4.75, -outward, false, (0.0, 0.25));
+        assert_hit(&bowl, ray(Point { x: 0.5, y: -5.0, z: 0.0 }, up), 5.25, outward, true, (0.0, 0.25));
+        // Above the rim.
+        assert!(bowl.hit(ray(Point { x: 2.0, y: 5.0, z: 0.0 }, -up), 0.001, INF_F32).is_none());
+    }
+
+    // Integral of `f` over the sphere of directions. Along a silhouette the pdf grows without bound, so a lone
+    // grazing sample can swamp a plain mean; the median of several batch means shrugs it off.
+    fn integrate_over_sphere(f: impl Fn(Vector3) -> f32) -> f32 {
+        let (batches, batch_size) = (15, 30_000);
+        let mut means: Vec<f32> = (0..batches)
+            .map(|_| (0..batch_size).map(|_| f(Vector3::random_unit_vector())).sum::<f32>() * 4.0 * PI / batch_size as f32)
+            .collect();
+        means.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        return means[batches / 2];
+    }
+
+    // Seen from close by, so that the shapes cover much of the sphere of directions.
+    #[test]
+    fn area_light_pdfs_integrate_to_one() {
+        let shapes: Vec<Box<dyn Hittable>> = vec![
+            Box::new(Cylinder { center: Point::ORIGIN, radius: 1.0, height: 2.0, capped: true, material: plaster() }),
+            Box::new(Cylinder { center: Point::ORIGIN, radius: 1.0, height: 2.0, capped: false, material: plaster() }),
+            Box::new(Cone { center: Point::ORIGIN, radius: 1.0, height: 2.0, capped: true, material: plaster() }),
+            Box::new(Disk { center: Point::ORIGIN, radius: 1.5, inner_radius: 0.4, material: plaster() }),
+            Box::new(Paraboloid { center: Point::ORIGIN, radius: 1.0, height: 1.0, material: plaster() }),
+            Box::new(torus()),
+        ];
+        let o = Point { x: 1.2, y: 1.5, z: 1.0 };
+        for shape in shapes.iter() {
+            let integral = integrate_over_sphere(|direction| shape.pdf_value(o, direction));
+            assert!((integral - 1.0).abs() < 0.05, "integral {}", integral);
+
+            // Sampled directions land below the viewpoint as often as the pdf says they should.
+            let below = |direction: Vector3| direction.y < -0.5 * direction.length();
+            let samples = 100_000;
+            let generated = (0..samples).filter(|_| below(shape.random(o))).count() as f32 / samples as f32;
+            let expected = integrate_over_sphere(|direction| if below(direction) { shape.pdf_value(o, direction) } else { 0.0 });
+            assert!((generated - expected).abs() < 0.05, "generated {} vs pdf {}", generated, expected);
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use std::sync::Arc;
+
+use crate::geometry::bounding_volume::AxisAlignedBoundingBox;
+use crate::geometry::ray::Ray;
+use crate::geometry::vector::{Point, Vector3};
+use crate::materials::material::Material;
+use crate::objects::hittable::{HitRecord, Hittable};
+use crate::utils::PI;
+
+// Surface tolerance for sphere tracing, relative to the distance travelled once that exceeds one unit.
+const SDF_EPSILON: f32 = 1e-4;
+const MAX_STEPS: u32 = 512;
+
+// Signed distance to a shape, negative inside. Primitives are centred on the origin; operators wrap other SDFs.
+pub enum SDF {
+    Sphere { radius: f32 },
+    Box { half_size: Vector3 },
+    // Lies in the XZ plane.
+    Torus { major_radius: f32, minor_radius: f32 },
+    Capsule { a: Point, b: Point, radius: f32 },
+    // Distance estimate of the power-n Mandelbulb fractal, which fits within a radius of about 1.2.
+    Mandelbulb { power: f32, iterations: u32 },
+    Translate(Box<SDF>, Vector3),
+    // Blends the two shapes over a distance of about `k`; a `k` of zero gives the sharp union.
+    SmoothUnion(Box<SDF>, Box<SDF>, f32),
+    // Carves the second shape out of the first, rounding the cut over about `k`.
+    SmoothSubtraction(Box<SDF>, Box<SDF>, f32),
+    // Infinite copies spaced by the period along each axis with a positive period.
+    Repeat(Box<SDF>, Vector3),
+    // Rotates each horizontal slice by `rate` radians per unit of height.
+    Twist(Box<SDF>, f32),
+}
+
+impl SDF {
+    pub fn translate(self, offset: Vector3) -> Self {
+        return SDF::Translate(Box::new(self), offset);
+    }
+
+    pub fn smooth_union(self, other: SDF, k: f32) -> Self {
+        return SDF::SmoothUnion(Box::new(self), Box::new(other), k);
+    }
+
+    pub fn smooth_subtract(self, other: SDF, k: f32) -> Self {
+        return SDF::SmoothSubtraction(Box::new(self), Box::new(other), k);
+    }
+
+    pub fn repeat(self, period: Vector3) -> Self {
+        return SDF::Repeat(Box::new(self), period);
+    }
+
+    pub fn twist(self, rate: f32) -> Self {
+        return SDF::Twist(Box::new(self), rate);
+    }
+
+    pub fn distance(&self, p: Point) -> f32 {
+        return match self {
+            SDF::Sphere { radius } => p.length() - radius,
+            SDF::Box { half_size } => {
+                let q = Vector3 { x: p.x.abs() - half_size.x, y: p.y.abs() - half_size.y, z: p.z.abs() - half_size.z };
+                let outside = Vector3 { x: q.x.max(0.0), y: q.y.max(0.0), z: q.z.max(0.0) };
+                outside.length() + q.x.max(q.y.max(q.z)).min(0.0)
+            }
+            SDF::Torus { major_radius, minor_radius } => {
+                let ring = (p.x.powi(2) + p.z.powi(2)).sqrt() - major_radius;
+                (ring.powi(2) + p.y.powi(2)).sqrt() - minor_radius
+            }
+            SDF::Capsule { a, b, radius } => {
+                let pa = p - *a;
+                let ba = *b - *a;
+                let h = (pa.dot(ba) / ba.dot(ba)).clamp(0.0, 1.0);
+                (pa - h * ba).length() - radius
+            }
+            SDF::Mandelbulb { power, iterations } => Self::mandelbulb(p, *power, *iterations),
+            SDF::Translate(sdf, offset) => sdf.distance(p - *offset),
+            SDF::SmoothUnion(a, b, k) => {
+                let (d1, d2) = (a.distance(p), b.distance(p));
+                if *k <= 0.0 {
+                    d1.min(d2)
+                } else {
+                    let h = (0.5 + 0.5 * (d2 - d1) / k).clamp(0.0, 1.0);
+                    d2 + h * (d1 - d2) - k * h * (1.0 - h)
+                }
+            }
+            SDF::SmoothSubtraction(a, b, k) => {
+                let (d1, d2) = (a.distance(p), b.distance(p));
+                if *k <= 0.0 {
+                    d1.max(-d2)
+                } else {
+                    let h = (0.5 - 0.5 * (d1 + d2) / k).clamp(0.0, 1.0);
+                    d1 + h * (-d2 - d1) + k * h * (1.0 - h)
+                }
+            }
+            SDF::Repeat(sdf, period) => {
+                let mut q = p;
+                for c in 0..3 {
+                    if period[c] > 0.0 {
+                        q[c] -= period[c] * (q[c] / period[c]).round();
+                    }
+                }
+                sdf.distance(q)
+            }
+            SDF::Twist(sdf, rate) => {
+                let (sin, cos) = (rate * p.y).sin_cos();
+                let q = Point { x: cos * p.x - sin * p.z, y: p.y, z: sin * p.x + cos * p.z };
+                // Twisting stretches distances by up to this factor, so it is divided out to keep steps safe.
+                let stretch = (1.0 + (rate * (p.x.powi(2) + p.z.powi(2)).sqrt()).powi(2)).sqrt();
+                sdf.distance(q) / stretch
+            }
+        };
+    }
+
+    fn mandelbulb(p: Point, power: f32, iterations: u32) -> f32 {
+        let mut z = p;
+        let mut dr = 1.0;
+        let mut r = p.length();
+        for _ in 0..iterations {
+            r = z.length();
+            if r > 2.0 {
+                break;
+            }
+
+            // The origin maps to itself under the power, so its angles don't matter.
+            let theta = if r > 0.0 { (z.y / r).acos() * power } else { 0.0 };
+            let phi = z.z.atan2(z.x) * power;
+            dr = r.powf(power - 1.0) * power * dr + 1.0;
+
+            let zr = r.powf(power);
+            z = zr * Vector3 { x: theta.sin() * phi.cos(), y: theta.cos(), z: theta.sin() * phi.sin() } + p;
+        }
+        // The estimate tends to zero as r does, but evaluating it there gives 0 * -inf.
+        if r <= 0.0 {
+            return 0.0;
+        }
+        return 0.5 * r.ln() * r / dr;
+    }
+
+    // Central differences on a tetrahedron, four evaluations instead of six.
+    fn gradient(&self, p: Point, h: f32) -> Vector3 {
+        let offsets = [
+            Vector3 { x: 1.0, y: -1.0, z: -1.0 },
+            Vector3 { x: -1.0, y: -1.0, z: 1.0 },
+            Vector3 { x: -1.0, y: 1.0, z: -1.0 },
+            Vector3 { x: 1.0, y: 1.0, z: 1.0 },
+        ];
+
+        let mut gradient = Vector3 { x: 0.0, y: 0.0, z: 0.0 };
+        for offset in offsets.iter() {
+            gradient += self.distance(p + h * *offset) * *offset;
+        }
+        return gradient.direction();
+    }
+}
+
+// Renders an SDF by sphere tracing inside `bound_box`, which must enclose the whole surface.
+pub struct SDFObject {
+    pub sdf: SDF,
+    pub bound_box: AxisAlignedBoundingBox,
+    pub material: Arc<dyn Material>,
+}
+
+impl SDFObject {
+    pub fn new(sdf: SDF, bound_box: AxisAlignedBoundingBox, material: Arc<dyn Material>) -> Self {
+        return Self { sdf, bound_box, material };
+    }
+}
+
+impl Hittable for SDFObject {
+    fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let inv_direction = Vector3 { x: 1.0 / ray.direction.x, y: 1.0 / ray.direction.y, z: 1.0 / ray.direction.z };
+        let (t_start, t_end) = self.bound_box.hit_range(ray.origin, inv_direction, t_min, t_max)?;
+
+        // March in units of distance, which differ from t when the direction isn't unit length.
+        let speed = ray.direction.length();
+        let direction = ray.direction / speed;
+        let (s_start, s_end) = (t_start * speed, t_end * speed);
+
+        // Rays starting inside the shape march towards its boundary from that side.
+        let start = ray.origin + s_start * direction;
+        let start_distance = self.sdf.distance(start);
+        let side = if start_distance < 0.0 { -1.0 } else { 1.0 };
+
+        // A ray leaving the surface it was spawned on starts within the tolerance; step away before testing.
+        let mut escaping = side * start_distance < SDF_EPSILON * s_start.max(1.0)
+            && side * self.sdf.gradient(start, SDF_EPSILON).dot(direction) > 0.0;
+
+        let mut s = s_start;
+        for _ in 0..MAX_STEPS {
+            if s > s_end {
+                return Option::None;
+            }
+
+            let epsilon = SDF_EPSILON * s.max(1.0);
+            let point = ray.origin + s * direction;
+            let distance = side * self.sdf.distance(point);
+            if distance < 0.0 || (distance < epsilon && !escaping) {
+                let t = s / speed;
+                let outward_normal = self.sdf.gradient(point, epsilon);
+                let local = (point - self.bound_box.centroid()).direction();
+                let u = ((-local.z).atan2(local.x) + PI) / (2.0 * PI);
+                let v = (-local.y).acos() / PI;
+
+                let mut hit_rec = HitRecord { intersection: ray.at_distance(t), normal: outward_normal, material: self.material.clone(), t, u, v, front_face: false, tangent: Option::None };
+                hit_rec.set_face_normal(ray);
+                return Option::from(hit_rec);
+            }
+            if distance >= epsilon {
+                escaping = false;
+            }
+
+            s += distance.max(epsilon);
+        }
+        return Option::None;
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AxisAlignedBoundingBox> {
+        return Option::from(self.bound_box.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::color::Color;
+    use crate::materials::lambertian::Lambertian;
+    use crate::utils::INF_F32;
+
+    use super::*;
+
+    fn object(sdf: SDF, half_size: f32) -> SDFObject {
+        let corner = Vector3 { x: half_size, y: half_size, z: half_size };
+        let bound_box = AxisAlignedBoundingBox { minimum: Point::ORIGIN - corner, maximum: Point::ORIGIN + corner };
+        return SDFObject::new(sdf, bound_box, Arc::new(Lambertian::new(Color::WHITE)));
+    }
+
+    fn ray(origin: Point, direction: Vector3) -> Ray {
+        return Ray { origin, direction, time: 0.0, wavelengths: None, absorption: None };
+    }
+
+    fn assert_close(actual: Vector3, expected: Vector3) {
+        assert!((actual - expected).length() < 1e-3, "{:?} vs {:?}", (actual.x, actual.y, actual.z), (expected.x, expected.y, expected.z));
+    }
+
+    // The direction is twice unit length, so t is half the distance marched.
+    #[test]
+    fn sphere_trace_matches_the_analytic_sphere() {
+        let sphere = object(SDF::Sphere { radius: 1.0 }, 1.5);
+        let hit_rec = sphere.hit(ray(Point { x: 0.0, y: 0.6, z: -5.0 }, Vector3 { x: 0.0, y: 0.0, z: 2.0 }), 0.001, INF_F32).unwrap();
+        assert!((hit_rec.t - 2.1).abs() < 1e-3, "{}", hit_rec.t);
+        assert!(hit_rec.front_face);
+        assert_close(hit_rec.intersection, Point { x: 0.0, y: 0.6, z: -0.8 });
+        assert_close(hit_rec.normal, Vector3 { x: 0.0, y: 0.6, z: -0.8 });
+
+        // From the centre the ray marches out to the far side.
+        let hit_rec = sphere.hit(ray(Point::ORIGIN, Vector3 { x: 0.0, y: 0.0, z: 2.0 }), 0.001, INF_F32).unwrap();
+        assert!((hit_rec.t - 0.5).abs() < 1e-3 && !hit_rec.front_face, "{}", hit_rec.t);
+        assert_close(hit_rec.normal, Vector3 { x: 0.0, y: 0.0, z: -1.0 });
+
+        assert!(sphere.hit(ray(Point { x: 0.0, y: 1.1, z: -5.0 }, Vector3 { x: 0.0, y: 0.0, z: 1.0 }), 0.001, INF_F32).is_none());
+        assert!(sphere.hit(ray(Point { x: 0.0, y: 0.6, z: -5.0 }, Vector3 { x: 0.0, y: 0.0, z: 1.0 }), 0.001, 4.0).is_none());
+    }
+
+    // Unit spheres at x = ±1.5 blended over k = 2.4, which bridges the gap with a neck of radius sqrt(1.6² - 1.5²).
+    #[test]
+    fn smooth_union_bridges_the_gap() {
+        let offset = Vector3 { x: 1.5, y: 0.0, z: 0.0 };
+        let blend = SDF::Sphere { radius: 1.0 }.translate(-offset).smooth_union(SDF::Sphere { radius: 1.0 }.translate(offset), 2.4);
+        let blob = object(blend, 3.0);
+
+        let neck = (1.6f32.powi(2) - 1.5f32.powi(2)).sqrt();
+        let hit_rec = blob.hit(ray(Point { x: 0.0, y: 5.0, z: 0.0 }, Vector3 { x: 0.0, y: -1.0, z: 0.0 }), 0.001, INF_F32).unwrap();
+        assert!((hit_rec.t - (5.0 - neck)).abs() < 1e-3, "{}", hit_rec.t);
+        assert_close(hit_rec.normal, Vector3 { x: 0.0, y: 1.0, z: 0.0 });
+
+        // Far from the other sphere the blend leaves the surface untouched.
+        let hit_rec = blob.hit(ray(Point { x: -5.0, y: 0.0, z: 0.0 }, Vector3 { x: 1.0, y: 0.0, z: 0.0 }), 0.001, INF_F32).unwrap();
+        assert!((hit_rec.t - 2.5).abs() < 1e-3, "{}", hit_rec.t);
+        assert_close(hit_rec.normal, Vector3 { x: -1.0, y: 0.0, z: 0.0 });
+    }
+
+    #[test]
+    fn mandelbulb_is_finite_at_the_origin_and_without_iterations() {
+        for &iterations in [0, 8].iter() {
+            let bulb = SDF::Mandelbulb { power: 8.0, iterations };
+            assert!(bulb.distance(Point::ORIGIN).is_finite());
+            assert!(bulb.distance(Point { x: 0.3, y: 0.2, z: -0.1 }).is_finite());
+        }
+    }
+}
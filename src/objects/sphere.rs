@@ -57,7 +57,7 @@ impl Hittable for Sphere {
         let material = self.material.clone();
         let (u, v) = Self::get_sphere_uv(normal);
 
-        let mut hit_rec = HitRecord { intersection, normal, material, t, u, v, front_face: false };
+        let mut hit_rec = HitRecord { intersection, normal, material, t, u, v, front_face: false, tangent: Option::None };
         hit_rec.set_face_normal(ray);
         return Option::from(hit_rec);
     }
@@ -71,7 +71,7 @@ impl Hittable for Sphere {
     }
 
     fn pdf_value(&self, o: Point, v: Vector3) -> f32 {
-        let opt_hit_rec = self.hit(Ray { origin: o, direction: v, time: 0.0 }, 0.001, INF_F32);
+        let opt_hit_rec = self.hit(Ray { origin: o, direction: v, time: 0.0, wavelengths: None, absorption: None }, 0.001, INF_F32);
         if opt_hit_rec.is_none() {
             return 0.0;
         }
@@ -136,7 +136,7 @@ impl Hittable for MovingSphere {
         let material = self.material.clone();
         let (u, v) = Sphere::get_sphere_uv(normal);
 
-        let mut hit_rec = HitRecord { intersection, normal, material, t, u, v, front_face: false };
+        let mut hit_rec = HitRecord { intersection, normal, material, t, u, v, front_face: false, tangent: Option::None };
         hit_rec.set_face_normal(ray);
         return Option::from(hit_rec);
     }
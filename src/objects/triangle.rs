@@ -0,0 +1,128 @@
+use std::sync::Arc;
+
+use crate::geometry::bounding_volume::AxisAlignedBoundingBox;
+use crate::geometry::ray::Ray;
+use crate::geometry::vector::{Point, Vector3};
+use crate::materials::material::Material;
+use crate::objects::hittable::{HitRecord, Hittable};
+use crate::utils::{INF_F32, random_f32};
+
+pub struct Triangle {
+    pub vertices: [Point; 3],
+    pub normals: Option<[Vector3; 3]>,
+    pub uvs: [(f32, f32); 3],
+    pub material: Arc<dyn Material>,
+}
+
+impl Triangle {
+    pub fn new(vertices: [Point; 3], material: Arc<dyn Material>) -> Self {
+        return Self {
+            vertices,
+            normals: Option::None,
+            uvs: [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+            material,
+        };
+    }
+
+    fn edges(&self) -> (Vector3, Vector3) {
+        return (self.vertices[1] - self.vertices[0], self.vertices[2] - self.vertices[0]);
+    }
+
+    fn geometric_normal(&self) -> Vector3 {
+        let (edge1, edge2) = self.edges();
+        return edge1.cross(edge2).direction();
+    }
+
+    fn area(&self) -> f32 {
+        let (edge1, edge2) = self.edges();
+        return 0.5 * edge1.cross(edge2).length();
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (t, b1, b2) = hit_triangle(ray, self.vertices[0], self.vertices[1], self.vertices[2], t_min, t_max)?;
+        let b0 = 1.0 - b1 - b2;
+
+        let intersection = ray.at_distance(t);
+        let normal = match self.normals {
+            Some(n) => (b0 * n[0] + b1 * n[1] + b2 * n[2]).direction(),
+            None => {
+                let (edge1, edge2) = self.edges();
+                edge1.cross(edge2).direction()
+            }
+        };
+        let material = self.material.clone();
+        let u = b0 * self.uvs[0].0 + b1 * self.uvs[1].0 + b2 * self.uvs[2].0;
+        let v = b0 * self.uvs[0].1 + b1 * self.uvs[1].1 + b2 * self.uvs[2].1;
+
+        let mut hit_rec = HitRecord { intersection, normal, material, t, u, v, front_face: false, tangent: Option::None };
+        hit_rec.set_face_normal(ray);
+        return Option::from(hit_rec);
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AxisAlignedBoundingBox> {
+        let mut min = Point { x: INF_F32, y: INF_F32, z: INF_F32 };
+        let mut max = Point { x: -INF_F32, y: -INF_F32, z: -INF_F32 };
+
+        for vertex in self.vertices.iter() {
+            for c in 0..3 {
+                min[c] = min[c].min(vertex[c] - 0.0001);
+                max[c] = max[c].max(vertex[c] + 0.0001);
+            }
+        }
+
+        return Option::from(AxisAlignedBoundingBox { minimum: min, maximum: max });
+    }
+
+    fn pdf_value(&self, origin: Point, v: Vector3) -> f32 {
+        let opt_hit_rec = self.hit(Ray { origin, direction: v, time: 0.0, wavelengths: None, absorption: None }, 0.001, INF_F32);
+        if opt_hit_rec.is_none() {
+            return 0.0;
+        }
+
+        let hit_rec = opt_hit_rec.unwrap();
+        let dist_squared = hit_rec.t.powi(2) * v.length_squared();
+        let cosine = (v.dot(self.geometric_normal()) / v.length()).abs();
+
+        return dist_squared / (cosine * self.area());
+    }
+
+    fn random(&self, origin: Vector3) -> Vector3 {
+        let (edge1, edge2) = self.edges();
+        let r1 = random_f32().sqrt();
+        let r2 = random_f32();
+
+        let random_point = self.vertices[0] + r1 * (1.0 - r2) * edge1 + r1 * r2 * edge2;
+        return random_point - origin;
+    }
+}
+
+// Möller–Trumbore, returning the distance and the barycentric weights of `b` and `c`.
+pub(crate) fn hit_triangle(ray: Ray, a: Point, b: Point, c: Point, t_min: f32, t_max: f32) -> Option<(f32, f32, f32)> {
+    let (edge1, edge2) = (b - a, c - a);
+    let p_vec = ray.direction.cross(edge2);
+    let det = edge1.dot(p_vec);
+    if det.abs() < 1e-8 {
+        return Option::None;
+    }
+    let inv_det = 1.0 / det;
+
+    let t_vec = ray.origin - a;
+    let b1 = t_vec.dot(p_vec) * inv_det;
+    if b1 < 0.0 || b1 > 1.0 {
+        return Option::None;
+    }
+
+    let q_vec = t_vec.cross(edge1);
+    let b2 = ray.direction.dot(q_vec) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return Option::None;
+    }
+
+    let t = edge2.dot(q_vec) * inv_det;
+    if t < t_min || t > t_max {
+        return Option::None;
+    }
+    return Option::from((t, b1, b2));
+}
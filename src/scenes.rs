@@ -1,23 +1,44 @@
 use std::sync::Arc;
 
+use image::{ImageBuffer, Luma};
+
+use crate::geometry::bounding_volume::AxisAlignedBoundingBox;
 use crate::geometry::color::Color;
+use crate::geometry::matrix::Matrix4;
+use crate::geometry::quaternion::Quaternion;
 use crate::geometry::vector::{Point, Vector3};
-use crate::materials::dielectric::Dielectric;
+use crate::materials::coated::Coated;
+use crate::materials::dielectric::{Dielectric, Dispersion};
 use crate::materials::lambertian::Lambertian;
 use crate::materials::light::DiffuseLight;
+use crate::materials::material::Material;
 use crate::materials::metal::Metal;
-use crate::objects::boxes::AxisAlignedBox;
-use crate::objects::hittable::{FlipFace, Hittable};
-use crate::objects::instances::{RotateY, Translate};
-use crate::objects::rectangle::{XYRect, XZRect, YZRect};
+use crate::materials::mix::MixMaterial;
+use crate::materials::oren_nayar::{DiffuseModel, OrenNayar};
+use crate::materials::rough_dielectric::RoughDielectric;
+use crate::loaders::strands::load_strands;
+use crate::objects::boxes::{AxisAlignedBox, OrientedBox};
+use crate::objects::csg::{CSG, CSGOperation};
+use crate::objects::curve::{CurveType, grow_strands, strands_bvh};
+use crate::objects::heightfield::Heightfield;
+use crate::objects::hittable::Hittable;
+use crate::objects::instances::{AnimatedTransform, Instance, RotateY, Transform, TransformKeyframe, Translate};
+use crate::objects::medium::SubsurfaceMedium;
+use crate::objects::quad::Quad;
+use crate::objects::quadrics::{Cone, Cylinder, Disk, Paraboloid, Torus};
+use crate::objects::sdf::{SDF, SDFObject};
 use crate::objects::sphere::{MovingSphere, Sphere};
+use crate::objects::triangle::Triangle;
 use crate::textures::image::ImageTexture;
 use crate::textures::perlin::{NoiseTexture, Perlin};
 use crate::textures::solid::SolidColor;
-use crate::utils::random_f32_range;
+use crate::utils::{PI, random_f32_range};
 use crate::world::bvh_node::BVHNode;
+use crate::world::sah::SplitMethod;
+use crate::world::wide_bvh::WideBVH;
 
-pub fn cornell_box() -> Vec<Arc<dyn Hittable>> {
+// With `dispersive` set the glass sphere splits light into colours, which only a spectral render shows.
+pub fn cornell_box(box_angle: f32, dispersive: bool) -> Vec<Arc<dyn Hittable>> {
     let mut world: Vec<Arc<dyn Hittable>> = Vec::new();
 
     let red_material = Arc::new(Lambertian::new(Color { r: 0.65, g: 0.05, b: 0.05 }));
@@ -25,52 +46,21 @@ pub fn cornell_box() -> Vec<Arc<dyn Hittable>> {
     let green_material = Arc::new(Lambertian::new(Color { r: 0.12, g: 0.45, b: 0.15 }));
     let light = Arc::new(DiffuseLight::new(Color { r: 15.0, g: 15.0, b: 15.0 }));
 
-    world.push(Arc::new(YZRect {
-        y: (0.0, 555.0),
-        z: (0.0, 555.0),
-        k: 555.0,
-        material: green_material,
-    }));
-    world.push(Arc::new(YZRect {
-        y: (0.0, 555.0),
-        z: (0.0, 555.0),
-        k: 0.0,
-        material: red_material,
-    }));
-    world.push(Arc::new(FlipFace {
-        object: Arc::new(XZRect {
-            x: (213.0, 343.0),
-            z: (227.0, 332.0),
-            k: 554.0,
-            material: light,
-        })
-    }));
-    world.push(Arc::new(XZRect {
-        x: (0.0, 555.0),
-        z: (0.0, 555.0),
-        k: 0.0,
-        material: white_material.clone(),
-    }));
-    world.push(Arc::new(XZRect {
-        x: (0.0, 555.0),
-        z: (0.0, 555.0),
-        k: 555.0,
-        material: white_material.clone(),
-    }));
-    world.push(Arc::new(XYRect {
-        x: (0.0, 555.0),
-        y: (0.0, 555.0),
-        k: 555.0,
-        material: white_material.clone(),
-    }));
+    // Quads facing into the box; the light faces down.
+    world.push(Arc::new(Quad::new(Point { x: 555.0, y: 0.0, z: 0.0 }, Vector3 { x: 0.0, y: 0.0, z: 555.0 }, Vector3 { x: 0.0, y: 555.0, z: 0.0 }, green_material)));
+    world.push(Arc::new(Quad::new(Point::ORIGIN, Vector3 { x: 0.0, y: 555.0, z: 0.0 }, Vector3 { x: 0.0, y: 0.0, z: 555.0 }, red_material)));
+    world.push(Arc::new(Quad::new(Point { x: 213.0, y: 554.0, z: 227.0 }, Vector3 { x: 130.0, y: 0.0, z: 0.0 }, Vector3 { x: 0.0, y: 0.0, z: 105.0 }, light)));
+    world.push(Arc::new(Quad::new(Point::ORIGIN, Vector3 { x: 0.0, y: 0.0, z: 555.0 }, Vector3 { x: 555.0, y: 0.0, z: 0.0 }, white_material.clone())));
+    world.push(Arc::new(Quad::new(Point { x: 0.0, y: 555.0, z: 0.0 }, Vector3 { x: 555.0, y: 0.0, z: 0.0 }, Vector3 { x: 0.0, y: 0.0, z: 555.0 }, white_material.clone())));
+    world.push(Arc::new(Quad::new(Point { x: 0.0, y: 0.0, z: 555.0 }, Vector3 { x: 0.0, y: 555.0, z: 0.0 }, Vector3 { x: 555.0, y: 0.0, z: 0.0 }, white_material.clone())));
 
-    let aluminium = Arc::new(Metal { color: Color { r: 0.8, g: 0.85, b: 0.88 }, fuzz: 0.0 });
+    let aluminium = Arc::new(Metal::new(Color { r: 0.8, g: 0.85, b: 0.88 }, 0.0));
     let mut box1: Arc<dyn Hittable> = Arc::new(AxisAlignedBox::new(Point::ORIGIN, Point { x: 165.0, y: 330.0, z: 165.0 }, aluminium));
-    box1 = Arc::new(RotateY::new(box1.clone(), 15.0));
+    box1 = Arc::new(RotateY::new(box1.clone(), box_angle));
     box1 = Arc::new(Translate { object: box1.clone(), offset: Vector3 { x: 265.0, y: 0.0, z: 295.0 } });
     world.push(box1);
 
-    let glass = Arc::new(Dielectric { refractive_index: 1.5 });
+    let glass = Arc::new(if dispersive { Dielectric::dispersive(Dispersion::BK7) } else { Dielectric::new(1.5) });
     world.push(Arc::new(Sphere {
         center: Point { x: 190.0, y: 90.0, z: 190.0 },
         radius: 90.0,
@@ -79,3 +69,230 @@ pub fn cornell_box() -> Vec<Arc<dyn Hittable>> {
 
     return world;
 }
+
+// Conifers scattered over a meadow. Every tree instances the same trunk and crown BVHs,
+// with its own placement, size and crown colour.
+pub fn forest(tree_count: u32) -> Vec<Arc<dyn Hittable>> {
+    let mut world: Vec<Arc<dyn Hittable>> = Vec::new();
+
+    let bark = Arc::new(Lambertian::new(Color { r: 0.35, g: 0.22, b: 0.12 }));
+    let needles = Arc::new(Lambertian::new(Color { r: 0.1, g: 0.35, b: 0.12 }));
+
+    let trunk_shape: Arc<dyn Hittable> = Arc::new(WideBVH::build(&cone_mesh(0.0, 0.25, 2.0, 12, bark), 0.0, 1.0, SplitMethod::Binned(12)).0);
+    let mut crown: Vec<Arc<dyn Hittable>> = Vec::new();
+    for tier in 0..3 {
+        let base = 1.2 + 1.3 * tier as f32;
+        crown.extend(cone_mesh(base, 1.6 - 0.4 * tier as f32, 2.4, 24, needles.clone()));
+    }
+    let crown_shape: Arc<dyn Hittable> = Arc::new(WideBVH::build(&crown, 0.0, 1.0, SplitMethod::Binned(12)).0);
+
+    let extent = 4.0 * (tree_count as f32).sqrt();
+    for _ in 0..tree_count {
+        let position = Vector3 { x: random_f32_range(-extent, extent), y: 0.0, z: random_f32_range(-extent, extent) };
+        let size = random_f32_range(0.7, 1.4);
+        let matrix = Matrix4::translation(position)
+            * Matrix4::rotation(Vector3 { x: 0.0, y: 1.0, z: 0.0 }, random_f32_range(0.0, 360.0))
+            * Matrix4::scale(Vector3 { x: size, y: size * random_f32_range(0.9, 1.2), z: size });
+        let crown_color = Color { r: random_f32_range(0.05, 0.15), g: random_f32_range(0.25, 0.45), b: random_f32_range(0.08, 0.15) };
+
+        world.push(Arc::new(Instance::new(trunk_shape.clone(), matrix)));
+        world.push(Arc::new(Instance::new(crown_shape.clone(), matrix).with_material(Arc::new(Lambertian::new(crown_color)))));
+    }
+
+    world.push(Arc::new(Quad::new(
+        Point { x: -2.0 * extent, y: 0.0, z: -2.0 * extent },
+        Vector3 { x: 0.0, y: 0.0, z: 4.0 * extent },
+        Vector3 { x: 4.0 * extent, y: 0.0, z: 0.0 },
+        Arc::new(Lambertian::new(Color { r: 0.3, g: 0.4, b: 0.15 })),
+    )));
+    world.push(Arc::new(Sphere {
+        center: Point { x: -3000.0, y: 4000.0, z: -2000.0 },
+        radius: 150.0,
+        material: Arc::new(DiffuseLight::new(Color { r: 30.0, g: 28.0, b: 24.0 })),
+    }));
+
+    return world;
+}
+
+// Grassy hills under the forest's sun, read from a grayscale `heightmap` if given and rolling Perlin noise otherwise.
+// Round strands from a `strands` file are added where the file puts them.
+pub fn terrain(heightmap: Option<&String>, strands: Option<&String>) -> Result<Vec<Arc<dyn Hittable>>, String> {
+    let mut world: Vec<Arc<dyn Hittable>> = Vec::new();
+
+    let corner = Point { x: -20.0, y: 0.0, z: -20.0 };
+    let size = Vector3 { x: 40.0, y: 6.0, z: 40.0 };
+    let grass = Arc::new(Lambertian::new(Color { r: 0.3, g: 0.45, b: 0.2 }));
+    let hills = match heightmap {
+        Some(path) => Heightfield::new(path, corner, size, grass)?,
+        None => {
+            let perlin = Perlin::new();
+            let img = ImageBuffer::from_fn(257, 257, |i, j| {
+                let height = 0.5 + 0.5 * perlin.turb(Point { x: i as f32 / 40.0, y: 0.0, z: j as f32 / 40.0 }, 5);
+                Luma([(height.clamp(0.0, 1.0) * u16::MAX as f32) as u16])
+            });
+            Heightfield::from_image(img, corner, size, grass)
+        }
+    };
+    // Grass grown over the hills as flat ribbons.
+    let blades = grow_strands(&hills, 20000, 0.6, (0.04, 0.005), 0.4, CurveType::Flat, Arc::new(Lambertian::new(Color { r: 0.35, g: 0.55, b: 0.15 })));
+    world.push(strands_bvh(blades));
+    world.push(Arc::new(hills));
+    if let Some(path) = strands {
+        world.push(strands_bvh(load_strands(path, CurveType::Round, Arc::new(Lambertian::new(Color { r: 0.4, g: 0.25, b: 0.1 })))?));
+    }
+    world.push(Arc::new(Sphere {
+        center: Point { x: -3000.0, y: 4000.0, z: -2000.0 },
+        radius: 150.0,
+        material: Arc::new(DiffuseLight::new(Color { r: 30.0, g: 28.0, b: 24.0 })),
+    }));
+
+    return Ok(world);
+}
+
+// Studio floor under a large overhead light, with the renderer's analytic shapes set out on it. A glowing
+// tube at the end of the row is sampled as a light too.
+pub fn shapes() -> Vec<Arc<dyn Hittable>> {
+    let mut world: Vec<Arc<dyn Hittable>> = Vec::new();
+
+    let floor = Arc::new(Lambertian::new(Color { r: 0.5, g: 0.5, b: 0.5 }));
+    world.push(Arc::new(Quad::new(Point { x: -20.0, y: 0.0, z: -20.0 }, Vector3 { x: 0.0, y: 0.0, z: 40.0 }, Vector3 { x: 40.0, y: 0.0, z: 0.0 }, floor)));
+    world.push(Arc::new(Quad::new(
+        Point { x: -3.0, y: 8.0, z: 0.0 },
+        Vector3 { x: 6.0, y: 0.0, z: 0.0 },
+        Vector3 { x: 0.0, y: 0.0, z: 6.0 },
+        Arc::new(DiffuseLight::new(Color { r: 6.0, g: 6.0, b: 6.0 })),
+    )));
+
+    // The quadrics, with a ring tilted up to face the camera.
+    let plaster = Arc::new(Lambertian::new(Color { r: 0.8, g: 0.78, b: 0.7 }));
+    world.push(Arc::new(Cylinder { center: Point { x: -5.0, y: 0.0, z: 2.5 }, radius: 0.6, height: 1.4, capped: true, material: plaster.clone() }));
+    world.push(Arc::new(Cone { center: Point { x: -3.0, y: 0.0, z: 2.5 }, radius: 0.7, height: 1.6, capped: true, material: plaster.clone() }));
+    let ring: Arc<dyn Hittable> = Arc::new(Disk { center: Point::ORIGIN, radius: 0.75, inner_radius: 0.35, material: plaster.clone() });
+    world.push(Arc::new(Transform::new(ring, Matrix4::translation(Vector3 { x: -1.0, y: 0.8, z: 2.5 }) * Matrix4::rotation(Vector3 { x: 1.0, y: 0.0, z: 0.0 }, -70.0))));
+    world.push(Arc::new(Torus { center: Point { x: 1.0, y: 0.25, z: 2.5 }, major_radius: 0.6, minor_radius: 0.25, material: plaster.clone() }));
+    world.push(Arc::new(Paraboloid { center: Point { x: 3.0, y: 0.0, z: 2.5 }, radius: 0.7, height: 1.2, material: plaster }));
+    world.push(shapes_tube_light());
+
+    // Behind them, sphere-traced distance fields, each in a box that encloses it.
+    let sdf_box = |center: Point, half_size: f32| AxisAlignedBoundingBox {
+        minimum: center - Vector3 { x: half_size, y: half_size, z: half_size },
+        maximum: center + Vector3 { x: half_size, y: half_size, z: half_size },
+    };
+    let clay = Arc::new(Lambertian::new(Color { r: 0.85, g: 0.55, b: 0.35 }));
+    let bulb_center = Point { x: -4.0, y: 1.0, z: 5.5 };
+    world.push(Arc::new(SDFObject::new(
+        SDF::Mandelbulb { power: 8.0, iterations: 8 }.translate(bulb_center),
+        sdf_box(bulb_center, 1.25),
+        clay.clone(),
+    )));
+    // A twisted column blended into a ring at its foot.
+    let column_center = Point { x: -1.3, y: 1.0, z: 5.5 };
+    let column = SDF::Box { half_size: Vector3 { x: 0.3, y: 0.9, z: 0.3 } }.twist(1.2)
+        .smooth_union(SDF::Torus { major_radius: 0.55, minor_radius: 0.12 }.translate(Vector3 { x: 0.0, y: -0.85, z: 0.0 }), 0.2);
+    world.push(Arc::new(SDFObject::new(column.translate(column_center), sdf_box(column_center, 1.0), clay.clone())));
+    // A block dimpled all over by a lattice of spheres, with a capsule handle melted onto it.
+    let block_center = Point { x: 1.3, y: 0.55, z: 5.5 };
+    let dimpled = SDF::Box { half_size: Vector3 { x: 0.6, y: 0.5, z: 0.6 } }
+        .smooth_subtract(SDF::Sphere { radius: 0.12 }.repeat(Vector3 { x: 0.4, y: 0.4, z: 0.4 }), 0.04)
+        .smooth_union(SDF::Capsule { a: Point { x: -0.4, y: 0.5, z: 0.0 }, b: Point { x: 0.4, y: 0.5, z: 0.0 }, radius: 0.12 }, 0.1);
+    world.push(Arc::new(SDFObject::new(dimpled.translate(block_center), sdf_box(block_center, 0.8), clay)));
+
+    // A leaning block: shear pushes its top over without turning it.
+    let block: Arc<dyn Hittable> = Arc::new(AxisAlignedBox::new(Point::ORIGIN, Point { x: 1.0, y: 1.6, z: 1.0 }, Arc::new(Lambertian::new(Color { r: 0.7, g: 0.5, b: 0.3 }))));
+    world.push(Arc::new(Transform::new(block, Matrix4::translation(Vector3 { x: -5.5, y: 0.0, z: -0.5 }) * Matrix4::shear(0.3, 0.0, 0.0, 0.0, 0.0, 0.2))));
+
+    // A cube tumbling and sliding while the shutter is open, blurred along its path.
+    let cube: Arc<dyn Hittable> = Arc::new(AxisAlignedBox::new(
+        Point { x: -0.5, y: -0.5, z: -0.5 },
+        Point { x: 0.5, y: 0.5, z: 0.5 },
+        Arc::new(Lambertian::new(Color { r: 0.2, g: 0.3, b: 0.7 })),
+    ));
+    world.push(Arc::new(AnimatedTransform::new(cube, vec![
+        TransformKeyframe { time: 0.0, translation: Vector3 { x: -3.5, y: 0.9, z: -0.5 }, rotation: Quaternion::IDENTITY, scale: Vector3 { x: 1.0, y: 1.0, z: 1.0 } },
+        TransformKeyframe {
+            time: 1.0,
+            translation: Vector3 { x: -2.5, y: 0.9, z: -0.5 },
+            rotation: Quaternion::from_axis_angle(Vector3 { x: 1.0, y: 1.0, z: 0.0 }, 60.0),
+            scale: Vector3 { x: 1.0, y: 1.0, z: 1.0 },
+        },
+    ])));
+
+    // In front, the corners of a cube rounded off by a sphere, with two more spheres scooped out of it.
+    let center = Point { x: -0.5, y: 0.75, z: -0.5 };
+    let offset = Point { x: 0.6, y: 0.6, z: 0.6 };
+    let csg_material = Arc::new(Lambertian::new(Color { r: 0.75, g: 0.2, b: 0.2 }));
+    let rounded: Arc<dyn Hittable> = Arc::new(CSG::new(
+        CSGOperation::Intersection,
+        Arc::new(AxisAlignedBox::new(center - offset, center + offset, csg_material.clone())),
+        Arc::new(Sphere { center, radius: 0.8, material: csg_material.clone() }),
+    ));
+    let scoops: Arc<dyn Hittable> = Arc::new(CSG::new(
+        CSGOperation::Union,
+        Arc::new(Sphere { center: center + Vector3 { x: 0.0, y: 0.7, z: 0.0 }, radius: 0.45, material: csg_material.clone() }),
+        Arc::new(Sphere { center: center + Vector3 { x: 0.0, y: 0.0, z: -0.7 }, radius: 0.45, material: csg_material }),
+    ));
+    world.push(Arc::new(CSG::new(CSGOperation::Difference, rounded, scoops)));
+
+    // A flint glass slab propped up on one edge, its faces quads along its own axes.
+    let lean = 30.0f32.to_radians();
+    world.push(Arc::new(OrientedBox::new(
+        Point { x: 1.5, y: 0.1, z: -0.8 },
+        [Vector3 { x: 1.2, y: 0.0, z: 0.0 }, Vector3 { x: 0.0, y: 1.3 * lean.cos(), z: 1.3 * lean.sin() }, Vector3 { x: 0.0, y: -0.2 * lean.sin(), z: 0.2 * lean.cos() }],
+        Arc::new(Dielectric::dispersive(Dispersion::FLINT)),
+    )));
+
+    // Front row: a ball of each material, read from left to right.
+    let ball = |x: f32, material: Arc<dyn Material>| -> Arc<dyn Hittable> {
+        return Arc::new(Sphere { center: Point { x, y: 0.5, z: -3.0 }, radius: 0.5, material });
+    };
+    // Gold from its measured optical constants, brushed more along one direction than the other.
+    let gold = Metal::from_ior(Color { r: 0.143, g: 0.374, b: 1.442 }, Color { r: 3.983, g: 2.385, b: 1.603 }, 0.35, 0.1);
+    world.push(ball(4.2, Arc::new(gold)));
+
+    world.push(ball(3.0, Arc::new(RoughDielectric::new(1.5, 0.3))));
+
+    world.push(ball(1.8, Arc::new(Dielectric::tinted(1.5, Color { r: 0.35, g: 0.75, b: 0.55 }, 0.5))));
+
+    world.push(ball(0.6, Arc::new(Dielectric::dispersive(Dispersion::DIAMOND))));
+
+    let varnish = Coated::tinted(Arc::new(Lambertian::new(Color { r: 0.7, g: 0.15, b: 0.1 })), 1.5, 0.2, Color { r: 0.9, g: 0.75, b: 0.45 }, 0.1);
+    world.push(ball(-0.6, Arc::new(varnish)));
+
+    // Wax: the ball is only the boundary here, the medium replaces its material.
+    let wax = ball(-1.8, Arc::new(Lambertian::new(Color::WHITE)));
+    world.push(Arc::new(SubsurfaceMedium::new(wax, 1.4, Color { r: 0.95, g: 0.85, b: 0.7 }, Color { r: 0.3, g: 0.15, b: 0.08 }).unwrap()));
+
+    world.push(ball(-3.0, Arc::new(OrenNayar::new(Color { r: 0.8, g: 0.8, b: 0.8 }, 1.0, DiffuseModel::EnergyPreserving))));
+
+    // Copper rusting through in streaks of marbled noise.
+    let copper = Arc::new(Metal::new(Color { r: 0.95, g: 0.64, b: 0.54 }, 0.25));
+    let rust = Arc::new(Lambertian::new(Color { r: 0.45, g: 0.2, b: 0.08 }));
+    let streaks = Arc::new(NoiseTexture { noise: Perlin::new(), scale: 6.0 });
+    world.push(ball(-4.2, Arc::new(MixMaterial::new(copper, rust, 0.5).with_mask(streaks))));
+
+    return world;
+}
+
+// The shapes scene's tube, which the light list samples as well.
+pub fn shapes_tube_light() -> Arc<dyn Hittable> {
+    return Arc::new(Cylinder {
+        center: Point { x: 5.0, y: 0.0, z: 2.5 },
+        radius: 0.1,
+        height: 2.0,
+        capped: true,
+        material: Arc::new(DiffuseLight::new(Color { r: 8.0, g: 6.0, b: 4.0 })),
+    });
+}
+
+// Open cone around the y axis, `segments` triangles from a base circle at height `base` to the apex.
+fn cone_mesh(base: f32, radius: f32, height: f32, segments: u32, material: Arc<dyn Material>) -> Vec<Arc<dyn Hittable>> {
+    let apex = Point { x: 0.0, y: base + height, z: 0.0 };
+    let rim = |i: u32| {
+        let angle = 2.0 * PI * i as f32 / segments as f32;
+        return Point { x: radius * angle.cos(), y: base, z: radius * angle.sin() };
+    };
+
+    return (0..segments).map(|i| {
+        Arc::new(Triangle::new([rim(i), apex, rim(i + 1)], material.clone())) as Arc<dyn Hittable>
+    }).collect();
+}
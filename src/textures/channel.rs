@@ -0,0 +1,26 @@
+use std::sync::Arc;
+
+use crate::geometry::color::Color;
+use crate::geometry::vector::Point;
+use crate::textures::texture::Texture;
+
+// One channel of another texture, scaled, repeated in all three. Unpacks maps that store several scalar
+// parameters in one image, such as glTF's roughness in green and metallic in blue.
+pub struct ChannelTexture {
+    pub texture: Arc<dyn Texture>,
+    // 0 for red, 1 for green, 2 for blue.
+    pub channel: usize,
+    pub scale: f32,
+}
+
+impl Texture for ChannelTexture {
+    fn color(&self, u: f32, v: f32, point: Point) -> Color {
+        let color = self.texture.color(u, v, point);
+        let value = self.scale * match self.channel {
+            0 => color.r,
+            1 => color.g,
+            _ => color.b,
+        };
+        return Color { r: value, g: value, b: value };
+    }
+}
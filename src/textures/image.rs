@@ -1,21 +1,52 @@
+use std::sync::Arc;
+
 use image::RgbImage;
 
 use crate::geometry::color::Color;
 use crate::geometry::vector::Point;
 use crate::textures::texture::Texture;
 
+// How texture coordinates outside [0, 1] are brought back onto the image.
+#[derive(Clone, Copy)]
+pub enum WrapMode {
+    Clamp,
+    Repeat,
+    MirroredRepeat,
+}
+
+impl WrapMode {
+    fn apply(&self, x: f32) -> f32 {
+        return match self {
+            WrapMode::Clamp => x.clamp(0.0, 1.0),
+            WrapMode::Repeat => x - x.floor(),
+            WrapMode::MirroredRepeat => {
+                let t = x.rem_euclid(2.0);
+                if t > 1.0 { 2.0 - t } else { t }
+            }
+        };
+    }
+}
+
 pub struct ImageTexture {
-    pub img_data: RgbImage,
+    pub img_data: Arc<RgbImage>,
     pub width: u32,
     pub height: u32,
     pub bytes_per_scanline: u32,
+    pub wrap_u: WrapMode,
+    pub wrap_v: WrapMode,
+    // Pixels hold sRGB-encoded colours, which are decoded to linear ones on lookup.
+    pub srgb: bool,
 }
 
 impl ImageTexture {
     const BYTES_PER_PIXEL: u32 = 3;
 
     pub fn new(path: String) -> Self {
-        let img = image::open(path).unwrap().to_rgb8();
+        return Self::shared(Arc::new(image::open(path).unwrap().to_rgb8()));
+    }
+
+    // Several textures can sample the same pixels with their own wrapping and encoding.
+    pub fn shared(img: Arc<RgbImage>) -> Self {
         let width = img.width();
         let height = img.height();
 
@@ -24,14 +55,32 @@ impl ImageTexture {
             width,
             height,
             bytes_per_scanline: width * Self::BYTES_PER_PIXEL,
+            wrap_u: WrapMode::Clamp,
+            wrap_v: WrapMode::Clamp,
+            srgb: false,
         };
     }
+
+    pub fn with_wrap(mut self, wrap_u: WrapMode, wrap_v: WrapMode) -> Self {
+        self.wrap_u = wrap_u;
+        self.wrap_v = wrap_v;
+        self
+    }
+
+    pub fn with_srgb(mut self, srgb: bool) -> Self {
+        self.srgb = srgb;
+        self
+    }
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    return if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) };
 }
 
 impl Texture for ImageTexture {
     fn color(&self, u: f32, v: f32, _point: Point) -> Color {
-        let _u = u.clamp(0.0, 1.0);
-        let _v = 1.0 - v.clamp(0.0, 1.0);
+        let _u = self.wrap_u.apply(u);
+        let _v = 1.0 - self.wrap_v.apply(v);
 
         let mut i = (_u * self.width as f32) as i32;
         let mut j = (_v * self.height as f32) as i32;
@@ -46,10 +95,35 @@ impl Texture for ImageTexture {
         const COLOR_SCALE: f32 = 1.0 / 255.0;
         let pixel = self.img_data.get_pixel(i as u32, j as u32).clone();
 
-        return Color {
+        let color = Color {
             r: pixel[0] as f32 * COLOR_SCALE,
             g: pixel[1] as f32 * COLOR_SCALE,
             b: pixel[2] as f32 * COLOR_SCALE,
         };
+        if self.srgb {
+            return Color { r: srgb_to_linear(color.r), g: srgb_to_linear(color.g), b: srgb_to_linear(color.b) };
+        }
+        return color;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_modes_fold_coordinates_into_the_image() {
+        for &(x, clamp, repeat, mirrored) in [(0.25, 0.25, 0.25, 0.25), (1.25, 1.0, 0.25, 0.75), (-0.25, 0.0, 0.75, 0.25), (2.25, 1.0, 0.25, 0.25)].iter() {
+            assert!((WrapMode::Clamp.apply(x) - clamp).abs() < 1e-6);
+            assert!((WrapMode::Repeat.apply(x) - repeat).abs() < 1e-6);
+            assert!((WrapMode::MirroredRepeat.apply(x) - mirrored).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn srgb_decoding_matches_known_values() {
+        assert_eq!(srgb_to_linear(0.0), 0.0);
+        assert!((srgb_to_linear(1.0) - 1.0).abs() < 1e-6);
+        assert!((srgb_to_linear(0.5) - 0.214_041).abs() < 1e-5);
     }
 }
@@ -3,3 +3,5 @@ pub mod solid;
 pub mod checkered;
 pub mod perlin;
 pub mod image;
+pub mod scaled;
+pub mod channel;
@@ -0,0 +1,16 @@
+use std::sync::Arc;
+
+use crate::geometry::color::Color;
+use crate::geometry::vector::Point;
+use crate::textures::texture::Texture;
+
+pub struct ScaledTexture {
+    pub texture: Arc<dyn Texture>,
+    pub scale: Color,
+}
+
+impl Texture for ScaledTexture {
+    fn color(&self, u: f32, v: f32, point: Point) -> Color {
+        return self.scale * self.texture.color(u, v, point);
+    }
+}
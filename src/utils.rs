@@ -19,12 +19,6 @@ pub fn random_f32_range(min: f32, max: f32) -> f32 {
     random_f32() * (max - min) + min
 }
 
-pub fn random_in_unit_sphere() -> Point {
-    let direction = Vector3::random_unit_vector();
-    let distance = random_f32().cbrt();
-    distance * direction
-}
-
 pub fn random_in_unit_disk() -> Point {
     let direction = Vector3::random_unit_vector();
     let distance = random_f32().sqrt();
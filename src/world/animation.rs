@@ -0,0 +1,201 @@
+use std::fs;
+
+use crate::geometry::vector::{Point, Vector3};
+use crate::utils::PI;
+use crate::world::camera::PerspectiveCamera;
+
+// Scalar scene parameter keyed by frame, linearly interpolated and held past either end.
+#[derive(Clone)]
+pub struct Track {
+    keys: Vec<(f32, f32)>,
+}
+
+impl Track {
+    pub fn new(mut keys: Vec<(f32, f32)>) -> Self {
+        assert!(!keys.is_empty(), "track needs at least one key");
+        keys.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        return Self { keys };
+    }
+
+    pub fn value_at(&self, frame: f32) -> f32 {
+        let first = self.keys[0];
+        let last = self.keys[self.keys.len() - 1];
+        if frame <= first.0 {
+            return first.1;
+        }
+        if frame >= last.0 {
+            return last.1;
+        }
+
+        let idx = self.keys.iter().position(|k| k.0 > frame).unwrap();
+        let (f0, v0) = self.keys[idx - 1];
+        let (f1, v1) = self.keys[idx];
+        return v0 + (frame - f0) / (f1 - f0) * (v1 - v0);
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct CameraKeyframe {
+    pub frame: f32,
+    pub look_from: Point,
+    pub look_at: Point,
+    pub v_fov: f32,
+    pub focus_dist: f32,
+}
+
+// Camera positions and targets follow a Catmull-Rom spline through the keyframes; FOV and focus are linear.
+pub struct CameraPath {
+    keyframes: Vec<CameraKeyframe>,
+    pub v_up: Vector3,
+    pub aspect_ratio: f32,
+    pub aperture: f32,
+}
+
+impl CameraPath {
+    pub fn new(mut keyframes: Vec<CameraKeyframe>, v_up: Vector3, aspect_ratio: f32, aperture: f32) -> Self {
+        assert!(!keyframes.is_empty(), "camera path needs at least one keyframe");
+        keyframes.sort_by(|a, b| a.frame.partial_cmp(&b.frame).unwrap());
+        return Self { keyframes, v_up, aspect_ratio, aperture };
+    }
+
+    // One keyframe per line: `frame  from_x from_y from_z  at_x at_y at_z  v_fov  focus_dist`.
+    // Blank lines and lines starting with `#` are ignored.
+    pub fn load(path: &str, v_up: Vector3, aspect_ratio: f32, aperture: f32) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+
+        let mut keyframes: Vec<CameraKeyframe> = Vec::new();
+        for (line_no, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let values: Vec<f32> = line.split_whitespace()
+                .map(|field| field.parse::<f32>())
+                .collect::<Result<_, _>>()
+                .map_err(|e| format!("{}:{}: {}", path, line_no + 1, e))?;
+            if values.len() != 9 {
+                return Err(format!("{}:{}: expected 9 values, found {}", path, line_no + 1, values.len()));
+            }
+
+            keyframes.push(CameraKeyframe {
+                frame: values[0],
+                look_from: Point { x: values[1], y: values[2], z: values[3] },
+                look_at: Point { x: values[4], y: values[5], z: values[6] },
+                v_fov: values[7],
+                focus_dist: values[8],
+            });
+        }
+
+        if keyframes.is_empty() {
+            return Err(format!("{}: no keyframes", path));
+        }
+        return Ok(Self::new(keyframes, v_up, aspect_ratio, aperture));
+    }
+
+    // One full orbit around `center` over frames first..=last, starting on the -z side.
+    pub fn turntable(center: Point, radius: f32, height: f32, v_fov: f32, first_frame: u32, last_frame: u32, v_up: Vector3, aspect_ratio: f32) -> Self {
+        let frame_count = (last_frame - first_frame + 1) as f32;
+        let keyframes = (first_frame..=last_frame + 1).map(|frame| {
+            let angle = 2.0 * PI * (frame - first_frame) as f32 / frame_count;
+            let look_from = center + Point { x: -radius * angle.sin(), y: height, z: -radius * angle.cos() };
+            CameraKeyframe {
+                frame: frame as f32,
+                look_from,
+                look_at: center,
+                v_fov,
+                focus_dist: (center - look_from).length(),
+            }
+        }).collect();
+
+        return Self::new(keyframes, v_up, aspect_ratio, 0.0);
+    }
+
+    pub fn keyframe_at(&self, frame: f32) -> CameraKeyframe {
+        let last_idx = self.keyframes.len() - 1;
+        if frame <= self.keyframes[0].frame {
+            return self.keyframes[0];
+        }
+        if frame >= self.keyframes[last_idx].frame {
+            return self.keyframes[last_idx];
+        }
+
+        let idx = self.keyframes.iter().position(|k| k.frame > frame).unwrap();
+        let k0 = &self.keyframes[idx.saturating_sub(2)];
+        let k1 = &self.keyframes[idx - 1];
+        let k2 = &self.keyframes[idx];
+        let k3 = &self.keyframes[(idx + 1).min(last_idx)];
+        let s = (frame - k1.frame) / (k2.frame - k1.frame);
+
+        return CameraKeyframe {
+            frame,
+            look_from: Self::catmull_rom(k0.look_from, k1.look_from, k2.look_from, k3.look_from, s),
+            look_at: Self::catmull_rom(k0.look_at, k1.look_at, k2.look_at, k3.look_at, s),
+            v_fov: k1.v_fov + s * (k2.v_fov - k1.v_fov),
+            focus_dist: k1.focus_dist + s * (k2.focus_dist - k1.focus_dist),
+        };
+    }
+
+    // Ray times run from `frame` to `frame + shutter`, with the camera moving over that interval.
+    pub fn camera_at(&self, frame: f32, shutter: f32) -> PerspectiveCamera {
+        let start = self.camera_for(&self.keyframe_at(frame), frame, frame + shutter);
+        if shutter <= 0.0 {
+            return start;
+        }
+        let end = self.camera_for(&self.keyframe_at(frame + shutter), frame, frame + shutter);
+        return start.with_motion(end);
+    }
+
+    fn camera_for(&self, key: &CameraKeyframe, time0: f32, time1: f32) -> PerspectiveCamera {
+        return PerspectiveCamera::new(key.look_from, key.look_at, self.v_up, key.v_fov, self.aspect_ratio, self.aperture, key.focus_dist, time0, time1);
+    }
+
+    fn catmull_rom(p0: Point, p1: Point, p2: Point, p3: Point, s: f32) -> Point {
+        let s2 = s * s;
+        let s3 = s2 * s;
+        return 0.5 * (2.0 * p1
+            + s * (p2 - p0)
+            + s2 * (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3)
+            + s3 * (3.0 * p1 - p0 - 3.0 * p2 + p3));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(frame: f32, x: f32, z: f32) -> CameraKeyframe {
+        return CameraKeyframe { frame, look_from: Point { x, y: 0.0, z }, look_at: Point::ORIGIN, v_fov: 40.0 + frame, focus_dist: 10.0 };
+    }
+
+    #[test]
+    fn path_passes_through_its_keyframes() {
+        let path = CameraPath::new(vec![key(0.0, 0.0, 0.0), key(10.0, 4.0, 1.0), key(20.0, 5.0, -3.0), key(30.0, 9.0, 2.0)],
+                                   Vector3 { x: 0.0, y: 1.0, z: 0.0 }, 1.0, 0.0);
+        for (frame, x, z) in [(0.0, 0.0, 0.0), (10.0, 4.0, 1.0), (20.0, 5.0, -3.0), (30.0, 9.0, 2.0)].iter() {
+            let keyframe = path.keyframe_at(*frame);
+            assert!((keyframe.look_from.x - x).abs() < 1e-5 && (keyframe.look_from.z - z).abs() < 1e-5, "frame {}", frame);
+        }
+        // Held past either end, and FOV is linear in between.
+        assert_eq!(path.keyframe_at(-5.0).look_from.x, 0.0);
+        assert_eq!(path.keyframe_at(50.0).look_from.x, 9.0);
+        assert!((path.keyframe_at(15.0).v_fov - 55.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn evenly_spaced_collinear_keyframes_move_at_constant_speed() {
+        let path = CameraPath::new((0..5).map(|i| key(i as f32, 2.0 * i as f32, 0.0)).collect(),
+                                   Vector3 { x: 0.0, y: 1.0, z: 0.0 }, 1.0, 0.0);
+        for &frame in [1.25, 1.5, 2.75].iter() {
+            assert!((path.keyframe_at(frame).look_from.x - 2.0 * frame).abs() < 1e-5, "frame {}", frame);
+        }
+    }
+
+    #[test]
+    fn track_interpolates_linearly() {
+        let track = Track::new(vec![(10.0, 1.0), (0.0, -1.0)]);
+        assert_eq!(track.value_at(-1.0), -1.0);
+        assert!((track.value_at(2.5) + 0.5).abs() < 1e-6);
+        assert_eq!(track.value_at(11.0), 1.0);
+    }
+}
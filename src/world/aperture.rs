@@ -0,0 +1,148 @@
+use std::sync::Arc;
+
+use image::GrayImage;
+
+use crate::geometry::vector::Point;
+use crate::utils::{degrees_to_radians, PI, random_f32, random_in_unit_disk, random_int};
+
+// Lens opening shape, which gives out-of-focus highlights their bokeh shape.
+// Samples are points on the lens within the unit disk (z = 0), scaled by the lens radius.
+#[derive(Clone)]
+pub enum Aperture {
+    Circular,
+    // Regular polygon with `blades` sides inscribed in the unit circle, rotated by `rotation` degrees.
+    Polygonal { blades: u32, rotation: f32 },
+    Mask(Arc<ApertureMask>),
+}
+
+impl Aperture {
+    pub fn sample(&self) -> Point {
+        return match self {
+            Aperture::Circular => random_in_unit_disk(),
+            Aperture::Polygonal { blades, rotation } => Self::sample_polygon(*blades, *rotation),
+            Aperture::Mask(mask) => mask.sample(),
+        };
+    }
+
+    fn sample_polygon(blades: u32, rotation: f32) -> Point {
+        if blades < 3 {
+            return random_in_unit_disk();
+        }
+
+        // Pick one of the equal triangles fanned out from the centre, then a uniform point in it.
+        let sector = random_int(0, blades - 1) as f32;
+        let step = 2.0 * PI / blades as f32;
+        let angle0 = degrees_to_radians(rotation) + sector * step;
+        let angle1 = angle0 + step;
+
+        let mut a = random_f32();
+        let mut b = random_f32();
+        if a + b > 1.0 {
+            a = 1.0 - a;
+            b = 1.0 - b;
+        }
+
+        return Point {
+            x: a * angle0.cos() + b * angle1.cos(),
+            y: a * angle0.sin() + b * angle1.sin(),
+            z: 0.0,
+        };
+    }
+}
+
+// Aperture shape taken from a grayscale image, brighter pixels letting through more light.
+pub struct ApertureMask {
+    width: u32,
+    height: u32,
+    cdf: Vec<f32>,
+}
+
+impl ApertureMask {
+    pub fn new(path: &str) -> Result<Self, String> {
+        let img = image::open(path).map_err(|e| format!("{}: {}", path, e))?.to_luma8();
+        return Self::from_image(img).map_err(|e| format!("{}: {}", path, e));
+    }
+
+    pub fn from_image(img: GrayImage) -> Result<Self, String> {
+        let width = img.width();
+        let height = img.height();
+
+        let mut cdf: Vec<f32> = Vec::with_capacity((width * height) as usize);
+        let mut total = 0.0;
+        for pixel in img.pixels() {
+            total += pixel[0] as f32;
+            cdf.push(total);
+        }
+        if total <= 0.0 {
+            return Err(String::from("aperture mask is completely black"));
+        }
+
+        for value in cdf.iter_mut() {
+            *value /= total;
+        }
+
+        return Ok(Self { width, height, cdf });
+    }
+
+    // The first texel whose cumulative share passes `target`, so black texels, which add nothing, are never picked.
+    // The last share is exactly 1, above any target in [0, 1).
+    fn texel(&self, target: f32) -> usize {
+        return self.cdf.partition_point(|&c| c <= target).min(self.cdf.len() - 1);
+    }
+
+    fn sample(&self) -> Point {
+        let idx = self.texel(random_f32()) as u32;
+        let i = idx % self.width;
+        let j = idx / self.width;
+
+        // The image is centred on the lens, its longer side spanning the unit disk's diameter.
+        let size = self.width.max(self.height) as f32;
+        return Point {
+            x: (2.0 * (i as f32 + random_f32()) - self.width as f32) / size,
+            y: (self.height as f32 - 2.0 * (j as f32 + random_f32())) / size,
+            z: 0.0,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Luma;
+
+    use super::*;
+
+    #[test]
+    fn polygon_samples_stay_inside_the_polygon() {
+        for &(blades, rotation) in [(3, 0.0), (6, 15.0), (9, -40.0)].iter() {
+            let step = 2.0 * PI / blades as f32;
+            for _ in 0..10_000 {
+                let p = Aperture::Polygonal { blades, rotation }.sample();
+                // Inside every edge, each as far from the centre as cos(step / 2) along its own normal.
+                for k in 0..blades {
+                    let normal_angle = degrees_to_radians(rotation) + (k as f32 + 0.5) * step;
+                    let distance = p.x * normal_angle.cos() + p.y * normal_angle.sin();
+                    assert!(distance <= (step / 2.0).cos() + 1e-5, "{} blades: ({}, {}) outside edge {}", blades, p.x, p.y, k);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mask_never_picks_a_black_texel() {
+        // Black first and last texels, and black ones between the lit ones.
+        let img = GrayImage::from_fn(5, 4, |i, j| Luma([if (i + j) % 3 == 1 { 200 } else { 0 }]));
+        let mask = ApertureMask::from_image(img.clone()).unwrap();
+        let lit = |idx: usize| img.get_pixel(idx as u32 % 5, idx as u32 / 5)[0] > 0;
+
+        assert!(lit(mask.texel(0.0)));
+        for _ in 0..10_000 {
+            assert!(lit(mask.texel(random_f32())));
+        }
+    }
+
+    #[test]
+    fn black_mask_is_rejected() {
+        assert!(ApertureMask::from_image(GrayImage::new(4, 4)).is_err());
+        assert!(ApertureMask::new("no/such/mask.png").is_err());
+    }
+}
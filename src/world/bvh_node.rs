@@ -49,7 +49,7 @@ impl Hittable for BVHNode {
         let mut _t_max = t_max;
         let hit_left = self.left_node.hit(ray, t_min, _t_max);
         if hit_left.is_some() {
-            _t_max = (ray.origin - hit_left.as_ref().unwrap().intersection).length();
+            _t_max = hit_left.as_ref().unwrap().t;
         }
 
         let hit_right = self.right_node.hit(ray, t_min, _t_max);
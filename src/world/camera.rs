@@ -1,6 +1,42 @@
+use std::sync::Arc;
+
 use crate::geometry::ray::Ray;
 use crate::geometry::vector::{Point, Vector3};
-use crate::utils::{degrees_to_radians, random_f32_range, random_in_unit_disk};
+use crate::utils::{degrees_to_radians, PI, random_f32_range};
+use crate::world::aperture::Aperture;
+
+// `u` runs left to right and `v` top to bottom across the image, both in [0, 1].
+// Returns None for pixels the projection does not cover, such as the corners of a circular fisheye.
+pub trait Camera: Send + Sync {
+    fn get_ray(&self, u: f32, v: f32) -> Option<Ray>;
+    // The same camera with its shutter open from `time0` to `time1` instead, e.g. for another frame of a sequence.
+    fn with_shutter(&self, time0: f32, time1: f32) -> Arc<dyn Camera>;
+}
+
+#[derive(Clone)]
+struct Orientation {
+    horizontal: Vector3,
+    vertical: Vector3,
+    forward: Vector3,
+}
+
+impl Orientation {
+    fn new(look_from: Point, look_at: Point, v_up: Vector3) -> Self {
+        let normal_in = (look_from - look_at).direction();
+        let horizontal = v_up.cross(normal_in).direction();
+        let vertical = normal_in.cross(horizontal);
+
+        Orientation {
+            horizontal,
+            vertical,
+            forward: -normal_in,
+        }
+    }
+
+    fn local(&self, x: f32, y: f32, z: f32) -> Vector3 {
+        x * self.horizontal + y * self.vertical + z * self.forward
+    }
+}
 
 #[derive(Clone)]
 pub struct Screen {
@@ -13,20 +49,30 @@ impl Screen {
     fn pixel_position(&self, x: f32, y: f32) -> Point {
         self.upper_left_corner + x * self.width - y * self.height
     }
+
+    fn lerp(&self, other: &Self, s: f32) -> Self {
+        Screen {
+            width: self.width + s * (other.width - self.width),
+            height: self.height + s * (other.height - self.height),
+            upper_left_corner: self.upper_left_corner + s * (other.upper_left_corner - self.upper_left_corner),
+        }
+    }
 }
 
 #[derive(Clone)]
-pub struct Camera {
+pub struct PerspectiveCamera {
     position: Point,
     horizontal_orientation: Vector3,
     vertical_orientation: Vector3,
     aperture: f32,
+    aperture_shape: Aperture,
     viewport: Screen,
     time0: f32,
     time1: f32,
+    end_pose: Option<Box<PerspectiveCamera>>,
 }
 
-impl Camera {
+impl PerspectiveCamera {
     pub fn new(look_from: Point, look_at: Point, v_up: Vector3, v_fov: f32, aspect_ratio: f32, aperture: f32, focus_dist: f32, time0: f32, time1: f32) -> Self {
         let normal_in = (look_from - look_at).direction();
         let horizontal_orientation = v_up.cross(normal_in).direction();
@@ -34,28 +80,37 @@ impl Camera {
 
         let viewport = Self::setup_viewport(look_from, normal_in, horizontal_orientation, vertical_orientation, v_fov, aspect_ratio, focus_dist);
 
-        Camera {
+        PerspectiveCamera {
             position: look_from,
             horizontal_orientation,
             vertical_orientation,
             aperture,
+            aperture_shape: Aperture::Circular,
             viewport,
             time0,
             time1,
+            end_pose: None,
         }
     }
 
-    pub fn get_ray(&self, u: f32, v: f32) -> Ray {
-        let rd = self.aperture * random_in_unit_disk();
-        let offset = rd.x * self.horizontal_orientation + rd.y * self.vertical_orientation;
+    // Builds the camera from photographic settings. Sensor size and focal length are in mm, and
+    // `units_per_mm` converts lens sizes to scene units. The image aspect ratio should match the sensor's.
+    pub fn physical(look_from: Point, look_at: Point, v_up: Vector3, sensor_width: f32, sensor_height: f32, focal_length: f32, f_number: f32, focus_dist: f32, shutter: (f32, f32), units_per_mm: f32) -> Self {
+        let v_fov = 2.0 * (sensor_height / (2.0 * focal_length)).atan() * 180.0 / PI;
+        let lens_radius = 0.5 * focal_length / f_number * units_per_mm;
 
-        let ray_origin = self.position + offset;
-        let ray_direction = (self.viewport.pixel_position(u, v) - ray_origin).direction();
-        Ray {
-            origin: ray_origin,
-            direction: ray_direction,
-            time: random_f32_range(self.time0, self.time1),
-        }
+        Self::new(look_from, look_at, v_up, v_fov, sensor_width / sensor_height, lens_radius, focus_dist, shutter.0, shutter.1)
+    }
+
+    pub fn with_aperture(mut self, aperture_shape: Aperture) -> Self {
+        self.aperture_shape = aperture_shape;
+        self
+    }
+
+    // Moves the camera over the shutter interval, from this pose at time0 to `end` at time1.
+    pub fn with_motion(mut self, end: PerspectiveCamera) -> Self {
+        self.end_pose = Some(Box::new(end));
+        self
     }
 
     fn setup_viewport(position: Point, normal_in: Point, horizontal_orientation: Vector3, vertical_orientation: Point, v_fov: f32, aspect_ratio: f32, focus_dist: f32) -> Screen {
@@ -74,4 +129,253 @@ impl Camera {
             upper_left_corner,
         }
     }
-}
\ No newline at end of file
+}
+
+impl Camera for PerspectiveCamera {
+    fn get_ray(&self, u: f32, v: f32) -> Option<Ray> {
+        let time = random_f32_range(self.time0, self.time1);
+        let (position, horizontal_orientation, vertical_orientation, viewport) = match &self.end_pose {
+            Some(end) => {
+                // An instantaneous shutter sees only the start pose.
+                let s = if self.time1 > self.time0 { (time - self.time0) / (self.time1 - self.time0) } else { 0.0 };
+                (
+                    self.position + s * (end.position - self.position),
+                    (self.horizontal_orientation + s * (end.horizontal_orientation - self.horizontal_orientation)).direction(),
+                    (self.vertical_orientation + s * (end.vertical_orientation - self.vertical_orientation)).direction(),
+                    self.viewport.lerp(&end.viewport, s),
+                )
+            }
+            None => (self.position, self.horizontal_orientation, self.vertical_orientation, self.viewport.clone()),
+        };
+
+        let rd = self.aperture * self.aperture_shape.sample();
+        let offset = rd.x * horizontal_orientation + rd.y * vertical_orientation;
+
+        let ray_origin = position + offset;
+        let ray_direction = (viewport.pixel_position(u, v) - ray_origin).direction();
+        Some(Ray {
+            origin: ray_origin,
+            direction: ray_direction,
+            time,
+            wavelengths: None,
+            absorption: None,
+        })
+    }
+
+    fn with_shutter(&self, time0: f32, time1: f32) -> Arc<dyn Camera> {
+        Arc::new(PerspectiveCamera { time0, time1, ..self.clone() })
+    }
+}
+
+// Parallel rays through a `view_height` tall window centred on `look_from`.
+#[derive(Clone)]
+pub struct OrthographicCamera {
+    orientation: Orientation,
+    viewport: Screen,
+    time0: f32,
+    time1: f32,
+}
+
+impl OrthographicCamera {
+    pub fn new(look_from: Point, look_at: Point, v_up: Vector3, view_height: f32, aspect_ratio: f32, time0: f32, time1: f32) -> Self {
+        let orientation = Orientation::new(look_from, look_at, v_up);
+        let width = view_height * aspect_ratio * orientation.horizontal;
+        let height = view_height * orientation.vertical;
+
+        OrthographicCamera {
+            viewport: Screen {
+                width,
+                height,
+                upper_left_corner: look_from - width / 2.0 + height / 2.0,
+            },
+            orientation,
+            time0,
+            time1,
+        }
+    }
+}
+
+impl Camera for OrthographicCamera {
+    fn get_ray(&self, u: f32, v: f32) -> Option<Ray> {
+        Some(Ray {
+            origin: self.viewport.pixel_position(u, v),
+            direction: self.orientation.forward,
+            time: random_f32_range(self.time0, self.time1),
+            wavelengths: None,
+            absorption: None,
+        })
+    }
+
+    fn with_shutter(&self, time0: f32, time1: f32) -> Arc<dyn Camera> {
+        Arc::new(OrthographicCamera { time0, time1, ..self.clone() })
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum FisheyeMapping {
+    // Image radius proportional to the angle off axis.
+    Equidistant,
+    // Image radius proportional to 2 sin(angle / 2), preserving solid angle.
+    Equisolid,
+}
+
+// Circular fisheye whose image circle spans the image height and covers `fov` degrees.
+#[derive(Clone)]
+pub struct FisheyeCamera {
+    position: Point,
+    orientation: Orientation,
+    half_fov: f32,
+    aspect_ratio: f32,
+    mapping: FisheyeMapping,
+    time0: f32,
+    time1: f32,
+}
+
+impl FisheyeCamera {
+    pub fn new(look_from: Point, look_at: Point, v_up: Vector3, fov: f32, aspect_ratio: f32, mapping: FisheyeMapping, time0: f32, time1: f32) -> Self {
+        FisheyeCamera {
+            position: look_from,
+            orientation: Orientation::new(look_from, look_at, v_up),
+            half_fov: degrees_to_radians(fov.min(360.0)) / 2.0,
+            aspect_ratio,
+            mapping,
+            time0,
+            time1,
+        }
+    }
+}
+
+impl Camera for FisheyeCamera {
+    fn get_ray(&self, u: f32, v: f32) -> Option<Ray> {
+        let x = (2.0 * u - 1.0) * self.aspect_ratio;
+        let y = 1.0 - 2.0 * v;
+        let radius = (x.powi(2) + y.powi(2)).sqrt();
+        if radius > 1.0 {
+            return None;
+        }
+
+        let theta = match self.mapping {
+            FisheyeMapping::Equidistant => radius * self.half_fov,
+            FisheyeMapping::Equisolid => 2.0 * (radius * (self.half_fov / 2.0).sin()).asin(),
+        };
+        let phi = y.atan2(x);
+
+        Some(Ray {
+            origin: self.position,
+            direction: self.orientation.local(theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos()),
+            time: random_f32_range(self.time0, self.time1),
+            wavelengths: None,
+            absorption: None,
+        })
+    }
+
+    fn with_shutter(&self, time0: f32, time1: f32) -> Arc<dyn Camera> {
+        Arc::new(FisheyeCamera { time0, time1, ..self.clone() })
+    }
+}
+
+// Full 360 by 180 degree latitude-longitude panorama, with `look_at` at the image centre.
+#[derive(Clone)]
+pub struct EquirectangularCamera {
+    position: Point,
+    orientation: Orientation,
+    time0: f32,
+    time1: f32,
+}
+
+impl EquirectangularCamera {
+    pub fn new(look_from: Point, look_at: Point, v_up: Vector3, time0: f32, time1: f32) -> Self {
+        EquirectangularCamera {
+            position: look_from,
+            orientation: Orientation::new(look_from, look_at, v_up),
+            time0,
+            time1,
+        }
+    }
+}
+
+impl Camera for EquirectangularCamera {
+    fn get_ray(&self, u: f32, v: f32) -> Option<Ray> {
+        let longitude = (2.0 * u - 1.0) * PI;
+        let latitude = (0.5 - v) * PI;
+
+        Some(Ray {
+            origin: self.position,
+            direction: self.orientation.local(latitude.cos() * longitude.sin(), latitude.sin(), latitude.cos() * longitude.cos()),
+            time: random_f32_range(self.time0, self.time1),
+            wavelengths: None,
+            absorption: None,
+        })
+    }
+
+    fn with_shutter(&self, time0: f32, time1: f32) -> Arc<dyn Camera> {
+        Arc::new(EquirectangularCamera { time0, time1, ..self.clone() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOOK_FROM: Point = Point { x: 1.0, y: 2.0, z: 3.0 };
+    const LOOK_AT: Point = Point { x: 4.0, y: 2.0, z: -1.0 };
+    const V_UP: Vector3 = Vector3 { x: 0.0, y: 1.0, z: 0.0 };
+
+    fn orientation() -> Orientation {
+        Orientation::new(LOOK_FROM, LOOK_AT, V_UP)
+    }
+
+    fn assert_direction(ray: Option<Ray>, expected: Vector3) {
+        let direction = ray.unwrap().direction;
+        assert!((direction - expected).length() < 1e-5, "{:?} vs {:?}", (direction.x, direction.y, direction.z), (expected.x, expected.y, expected.z));
+    }
+
+    #[test]
+    fn centre_pixel_looks_forward() {
+        let forward = orientation().forward;
+        let cameras: Vec<Box<dyn Camera>> = vec![
+            Box::new(PerspectiveCamera::new(LOOK_FROM, LOOK_AT, V_UP, 40.0, 1.5, 0.0, 5.0, 0.0, 1.0)),
+            Box::new(OrthographicCamera::new(LOOK_FROM, LOOK_AT, V_UP, 2.0, 1.5, 0.0, 1.0)),
+            Box::new(FisheyeCamera::new(LOOK_FROM, LOOK_AT, V_UP, 180.0, 1.5, FisheyeMapping::Equidistant, 0.0, 1.0)),
+            Box::new(FisheyeCamera::new(LOOK_FROM, LOOK_AT, V_UP, 180.0, 1.5, FisheyeMapping::Equisolid, 0.0, 1.0)),
+            Box::new(EquirectangularCamera::new(LOOK_FROM, LOOK_AT, V_UP, 0.0, 1.0)),
+        ];
+        for camera in cameras.iter() {
+            assert_direction(camera.get_ray(0.5, 0.5), forward);
+        }
+    }
+
+    // A full-frame sensor behind a 50 mm f/2 lens, in a scene modelled in metres.
+    #[test]
+    fn physical_camera_follows_its_lens() {
+        let focus_dist = 4.0;
+        let camera = PerspectiveCamera::physical(LOOK_FROM, LOOK_AT, V_UP, 36.0, 24.0, 50.0, 2.0, focus_dist, (0.0, 1.0), 0.001);
+
+        // tan(v_fov / 2) = 12 / 50, which puts a 24 mm tall sensor's view across 0.48 times the focus distance.
+        let v_fov = 2.0 * (12.0f32 / 50.0).atan();
+        assert!((camera.viewport.height.length() - 2.0 * focus_dist * (v_fov / 2.0).tan()).abs() < 1e-4);
+        assert!((camera.viewport.width.length() - 0.72 * focus_dist).abs() < 1e-4);
+        // 50 mm / f/2 is a 25 mm opening.
+        assert!((camera.aperture - 0.0125).abs() < 1e-6);
+    }
+
+    #[test]
+    fn fisheye_rim_is_half_the_field_of_view_off_axis() {
+        let forward = orientation().forward;
+        for &mapping in [FisheyeMapping::Equidistant, FisheyeMapping::Equisolid].iter() {
+            let camera = FisheyeCamera::new(LOOK_FROM, LOOK_AT, V_UP, 150.0, 1.0, mapping, 0.0, 1.0);
+            // Outside the image circle.
+            assert!(camera.get_ray(0.0, 0.0).is_none());
+
+            let direction = camera.get_ray(1.0, 0.5).unwrap().direction;
+            let angle = direction.dot(forward).clamp(-1.0, 1.0).acos();
+            assert!((angle - degrees_to_radians(75.0)).abs() < 1e-4, "angle {}", angle);
+        }
+    }
+
+    #[test]
+    fn panorama_quarter_turn_looks_right() {
+        let camera = EquirectangularCamera::new(LOOK_FROM, LOOK_AT, V_UP, 0.0, 1.0);
+        assert_direction(camera.get_ray(0.75, 0.5), orientation().horizontal);
+    }
+}
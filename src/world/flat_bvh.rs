@@ -0,0 +1,173 @@
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::geometry::bounding_volume::AxisAlignedBoundingBox;
+use crate::geometry::ray::Ray;
+use crate::geometry::vector::Vector3;
+use crate::objects::hittable::{HitRecord, Hittable};
+use crate::world::sah::{build_items, BuildItem, BVHStats, choose_split, enclosing_box, SplitMethod};
+
+// Leaves hold `count` primitives starting at `offset`; interior nodes have `count == 0`, the first
+// child directly after them and the second child at `offset`.
+pub struct LinearNode {
+    pub bound_box: AxisAlignedBoundingBox,
+    pub offset: u32,
+    pub count: u32,
+    pub axis: u8,
+}
+
+// Bounding volume hierarchy stored as a depth-first array of nodes over a reordered primitive list.
+pub struct FlatBVH {
+    pub nodes: Vec<LinearNode>,
+    pub primitives: Vec<Arc<dyn Hittable>>,
+}
+
+impl FlatBVH {
+    const MAX_DEPTH: usize = 64;
+
+    // Builds the hierarchy with the surface area heuristic and lays it out depth first.
+    pub fn build(objects: &[Arc<dyn Hittable>], time0: f32, time1: f32, method: SplitMethod) -> (Self, BVHStats) {
+        let start = Instant::now();
+        let mut items = build_items(objects, time0, time1);
+        let mut stats = BVHStats::new(items.len());
+        let mut bvh = Self {
+            nodes: Vec::with_capacity(2 * items.len()),
+            primitives: Vec::with_capacity(items.len()),
+        };
+
+        if !items.is_empty() {
+            let root_area = enclosing_box(&items).surface_area();
+            bvh.build_node(&mut items, method, root_area, 0, &mut stats);
+        }
+
+        stats.build_time = start.elapsed();
+        return (bvh, stats);
+    }
+
+    fn build_node(&mut self, items: &mut [BuildItem], method: SplitMethod, root_area: f32, depth: usize, stats: &mut BVHStats) {
+        let bound_box = enclosing_box(items);
+        let split = if depth + 1 >= Self::MAX_DEPTH { None } else { choose_split(items, &bound_box, method) };
+        let (axis, mid_idx) = match split {
+            Some(split) => split,
+            None => return self.push_leaf(items, bound_box, root_area, depth, stats),
+        };
+        stats.add_interior(&bound_box, root_area);
+
+        let node_idx = self.nodes.len();
+        self.nodes.push(LinearNode { bound_box, offset: 0, count: 0, axis: axis as u8 });
+
+        let (left_items, right_items) = items.split_at_mut(mid_idx);
+        self.build_node(left_items, method, root_area, depth + 1, stats);
+        self.nodes[node_idx].offset = self.nodes.len() as u32;
+        self.build_node(right_items, method, root_area, depth + 1, stats);
+    }
+
+    fn push_leaf(&mut self, items: &[BuildItem], bound_box: AxisAlignedBoundingBox, root_area: f32, depth: usize, stats: &mut BVHStats) {
+        stats.add_leaf(items.len(), &bound_box, root_area, depth);
+        self.nodes.push(LinearNode {
+            bound_box,
+            offset: self.primitives.len() as u32,
+            count: items.len() as u32,
+            axis: 0,
+        });
+        self.primitives.extend(items.iter().map(|item| item.object.clone()));
+    }
+}
+
+impl Hittable for FlatBVH {
+    fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        if self.nodes.is_empty() {
+            return Option::None;
+        }
+
+        let inv_direction = Vector3 { x: 1.0 / ray.direction.x, y: 1.0 / ray.direction.y, z: 1.0 / ray.direction.z };
+        let direction_is_neg = [inv_direction.x < 0.0, inv_direction.y < 0.0, inv_direction.z < 0.0];
+
+        let mut hit_rec: Option<HitRecord> = Option::None;
+        let mut closest = t_max;
+        let mut stack = [0u32; Self::MAX_DEPTH];
+        let mut stack_size = 0;
+        let mut current = 0usize;
+
+        loop {
+            let node = &self.nodes[current];
+            if node.bound_box.hit_precomputed(ray.origin, inv_direction, t_min, closest) {
+                if node.count > 0 {
+                    let start = node.offset as usize;
+                    for obj in self.primitives[start..start + node.count as usize].iter() {
+                        if let Some(temp_rec) = obj.hit(ray, t_min, closest) {
+                            closest = temp_rec.t;
+                            hit_rec = Option::from(temp_rec);
+                        }
+                    }
+                } else {
+                    // Visit the child on the near side of the split axis first, deferring the other.
+                    if direction_is_neg[node.axis as usize] {
+                        stack[stack_size] = current as u32 + 1;
+                        current = node.offset as usize;
+                    } else {
+                        stack[stack_size] = node.offset;
+                        current += 1;
+                    }
+                    stack_size += 1;
+                    continue;
+                }
+            }
+
+            if stack_size == 0 {
+                break;
+            }
+            stack_size -= 1;
+            current = stack[stack_size] as usize;
+        }
+
+        return hit_rec;
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AxisAlignedBoundingBox> {
+        return self.nodes.first().map(|node| node.bound_box.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::color::Color;
+    use crate::geometry::vector::Point;
+    use crate::materials::lambertian::Lambertian;
+    use crate::objects::sphere::Sphere;
+    use crate::utils::{INF_F32, random_f32_range};
+    use crate::world::bvh_node::BVHNode;
+    use crate::world::sah::MAX_LEAF_SIZE;
+
+    fn random_point(extent: f32) -> Point {
+        return Point { x: random_f32_range(-extent, extent), y: random_f32_range(-extent, extent), z: random_f32_range(-extent, extent) };
+    }
+
+    #[test]
+    fn hits_match_the_reference_tree() {
+        let material = Arc::new(Lambertian::new(Color::WHITE));
+        let mut objects: Vec<Arc<dyn Hittable>> = (0..300).map(|_| {
+            Arc::new(Sphere { center: random_point(20.0), radius: random_f32_range(0.2, 1.5), material: material.clone() }) as Arc<dyn Hittable>
+        }).collect();
+        // Concentric spheres that no centroid split can separate.
+        objects.extend((1..=10).map(|i| Arc::new(Sphere { center: Point::ORIGIN, radius: i as f32 * 0.3, material: material.clone() }) as Arc<dyn Hittable>));
+
+        let reference = BVHNode::create_tree(&mut objects.clone(), 0.0, 1.0);
+        for &method in [SplitMethod::Binned(12), SplitMethod::FullSweep].iter() {
+            let (bvh, stats) = FlatBVH::build(&objects, 0.0, 1.0, method);
+            assert!(stats.max_leaf_size <= MAX_LEAF_SIZE);
+            for _ in 0..2000 {
+                let origin = random_point(30.0);
+                let ray = Ray { origin, direction: (random_point(10.0) - origin).direction(), time: 0.0, wavelengths: None, absorption: None };
+                let expected = reference.hit(ray, 0.001, INF_F32).map(|hit_rec| hit_rec.t);
+                let actual = bvh.hit(ray, 0.001, INF_F32).map(|hit_rec| hit_rec.t);
+                match (expected, actual) {
+                    (Some(e), Some(a)) => assert!((e - a).abs() < 1e-4, "{} != {}", e, a),
+                    (None, None) => {}
+                    _ => panic!("reference {:?}, flat {:?}", expected, actual),
+                }
+            }
+        }
+    }
+}
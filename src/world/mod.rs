@@ -1,3 +1,8 @@
 pub mod camera;
 pub mod bvh_node;
 pub mod hittable_list;
+pub mod aperture;
+pub mod animation;
+pub mod sah;
+pub mod flat_bvh;
+pub mod wide_bvh;
@@ -0,0 +1,259 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::geometry::bounding_volume::AxisAlignedBoundingBox;
+use crate::geometry::vector::Point;
+use crate::objects::hittable::Hittable;
+
+// Relative costs of stepping into a node and of intersecting a primitive.
+const TRAVERSAL_COST: f32 = 0.125;
+const INTERSECTION_COST: f32 = 1.0;
+// Largest leaf made even when splitting it would cost more.
+pub const MAX_LEAF_SIZE: usize = 4;
+
+#[derive(Clone, Copy)]
+pub enum SplitMethod {
+    // Buckets centroids into this many bins per axis and only evaluates splits between bins.
+    Binned(usize),
+    // Evaluates a split between every pair of neighbouring centroids on every axis.
+    FullSweep,
+}
+
+pub struct BVHStats {
+    pub build_time: Duration,
+    pub primitives: usize,
+    pub interior_nodes: usize,
+    pub leaves: usize,
+    pub max_depth: usize,
+    pub min_leaf_size: usize,
+    pub max_leaf_size: usize,
+    pub sah_cost: f32,
+}
+
+impl fmt::Display for BVHStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BVH: {} primitives, {} interior nodes, {} leaves (size {}..{}, avg {:.2}), depth {}, SAH cost {:.2}, built in {:.1?}",
+               self.primitives, self.interior_nodes, self.leaves, self.min_leaf_size, self.max_leaf_size,
+               self.primitives as f32 / self.leaves.max(1) as f32, self.max_depth, self.sah_cost, self.build_time)
+    }
+}
+
+impl BVHStats {
+    pub fn new(primitives: usize) -> Self {
+        return Self {
+            build_time: Duration::default(),
+            primitives,
+            interior_nodes: 0,
+            leaves: 0,
+            max_depth: 0,
+            min_leaf_size: if primitives == 0 { 0 } else { usize::MAX },
+            max_leaf_size: 0,
+            sah_cost: 0.0,
+        };
+    }
+
+    pub fn add_interior(&mut self, bound_box: &AxisAlignedBoundingBox, root_area: f32) {
+        self.interior_nodes += 1;
+        self.sah_cost += TRAVERSAL_COST * bound_box.surface_area() / root_area;
+    }
+
+    pub fn add_leaf(&mut self, size: usize, bound_box: &AxisAlignedBoundingBox, root_area: f32, depth: usize) {
+        self.leaves += 1;
+        self.max_depth = self.max_depth.max(depth);
+        self.min_leaf_size = self.min_leaf_size.min(size);
+        self.max_leaf_size = self.max_leaf_size.max(size);
+        self.sah_cost += INTERSECTION_COST * size as f32 * bound_box.surface_area() / root_area;
+    }
+}
+
+pub struct BuildItem {
+    pub object: Arc<dyn Hittable>,
+    pub bound_box: AxisAlignedBoundingBox,
+    pub centroid: Point,
+}
+
+pub fn build_items(objects: &[Arc<dyn Hittable>], time0: f32, time1: f32) -> Vec<BuildItem> {
+    return objects.iter().map(|object| {
+        let bound_box = object.bounding_box(time0, time1).unwrap();
+        BuildItem { object: object.clone(), centroid: bound_box.centroid(), bound_box }
+    }).collect();
+}
+
+pub fn enclosing_box(items: &[BuildItem]) -> AxisAlignedBoundingBox {
+    let mut bound_box = items[0].bound_box.clone();
+    for item in items.iter().skip(1) {
+        bound_box = AxisAlignedBoundingBox::surrounding_box(bound_box, item.bound_box.clone());
+    }
+    return bound_box;
+}
+
+// Surface area heuristic: picks the split where the expected cost of tracing a random ray is lowest and reorders
+// `items` so it falls at the returned index. Returns the split axis and index, or None when a leaf is cheaper.
+pub fn choose_split(items: &mut [BuildItem], bound_box: &AxisAlignedBoundingBox, method: SplitMethod) -> Option<(usize, usize)> {
+    if items.len() == 1 {
+        return Option::None;
+    }
+
+    let split = match method {
+        SplitMethod::Binned(bins) => binned_split(items, bound_box, bins.max(2)),
+        SplitMethod::FullSweep => sweep_split(items, bound_box),
+    };
+
+    let leaf_cost = INTERSECTION_COST * items.len() as f32;
+    return match split {
+        Some((cost, axis, mid_idx)) if cost < leaf_cost || items.len() > MAX_LEAF_SIZE => Some((axis, mid_idx)),
+        // Nothing separates the centroids, so halve by count to keep leaves small.
+        None if items.len() > MAX_LEAF_SIZE => Some(median_split(items, bound_box)),
+        _ => None,
+    };
+}
+
+// Splits at the median centroid along the widest axis of `bound_box`.
+fn median_split(items: &mut [BuildItem], bound_box: &AxisAlignedBoundingBox) -> (usize, usize) {
+    let extent = bound_box.maximum - bound_box.minimum;
+    let axis = if extent.x >= extent.y && extent.x >= extent.z { 0 } else if extent.y >= extent.z { 1 } else { 2 };
+    let mid_idx = items.len() / 2;
+    items.select_nth_unstable_by(mid_idx, |a, b| a.centroid[axis].partial_cmp(&b.centroid[axis]).unwrap());
+    return (axis, mid_idx);
+}
+
+fn split_cost(parent_area: f32, left: &AxisAlignedBoundingBox, left_count: usize, right: &AxisAlignedBoundingBox, right_count: usize) -> f32 {
+    return TRAVERSAL_COST + INTERSECTION_COST *
+        (left.surface_area() * left_count as f32 + right.surface_area() * right_count as f32) / parent_area;
+}
+
+// Returns the best cost, its axis and the index to split the reordered items at, or None if nothing separates them.
+fn binned_split(items: &mut [BuildItem], bound_box: &AxisAlignedBoundingBox, bins: usize) -> Option<(f32, usize, usize)> {
+    let mut centroid_min = items[0].centroid;
+    let mut centroid_max = items[0].centroid;
+    for item in items.iter() {
+        for c in 0..3 {
+            centroid_min[c] = centroid_min[c].min(item.centroid[c]);
+            centroid_max[c] = centroid_max[c].max(item.centroid[c]);
+        }
+    }
+
+    let parent_area = bound_box.surface_area();
+    let bin_of = |centroid: Point, axis: usize| -> usize {
+        let offset = (centroid[axis] - centroid_min[axis]) / (centroid_max[axis] - centroid_min[axis]);
+        return ((offset * bins as f32) as usize).min(bins - 1);
+    };
+
+    let mut best: Option<(f32, usize, usize)> = None;
+    for axis in 0..3 {
+        if centroid_max[axis] - centroid_min[axis] <= 0.0 {
+            continue;
+        }
+
+        let mut counts = vec![0usize; bins];
+        let mut boxes: Vec<Option<AxisAlignedBoundingBox>> = vec![None; bins];
+        for item in items.iter() {
+            let b = bin_of(item.centroid, axis);
+            counts[b] += 1;
+            boxes[b] = Some(match boxes[b].take() {
+                Some(bin_box) => AxisAlignedBoundingBox::surrounding_box(bin_box, item.bound_box.clone()),
+                None => item.bound_box.clone(),
+            });
+        }
+
+        // Sweep from the right to collect the box and count of everything above each split.
+        let mut right_boxes: Vec<Option<AxisAlignedBoundingBox>> = vec![None; bins];
+        let mut right_counts = vec![0usize; bins];
+        let mut acc_box: Option<AxisAlignedBoundingBox> = None;
+        let mut acc_count = 0;
+        for b in (1..bins).rev() {
+            acc_box = merge(acc_box, boxes[b].clone());
+            acc_count += counts[b];
+            right_boxes[b] = acc_box.clone();
+            right_counts[b] = acc_count;
+        }
+
+        let mut left_box: Option<AxisAlignedBoundingBox> = None;
+        let mut left_count = 0;
+        for b in 1..bins {
+            left_box = merge(left_box, boxes[b - 1].clone());
+            left_count += counts[b - 1];
+            if let (Some(lb), Some(rb)) = (left_box.as_ref(), right_boxes[b].as_ref()) {
+                let cost = split_cost(parent_area, lb, left_count, rb, right_counts[b]);
+                if best.map_or(true, |(best_cost, _, _)| cost < best_cost) {
+                    best = Some((cost, axis, b));
+                }
+            }
+        }
+    }
+
+    let (cost, axis, split_bin) = best?;
+    let mut mid_idx = 0;
+    for i in 0..items.len() {
+        if bin_of(items[i].centroid, axis) < split_bin {
+            items.swap(i, mid_idx);
+            mid_idx += 1;
+        }
+    }
+    return Some((cost, axis, mid_idx));
+}
+
+fn sweep_split(items: &mut [BuildItem], bound_box: &AxisAlignedBoundingBox) -> Option<(f32, usize, usize)> {
+    let parent_area = bound_box.surface_area();
+    let n = items.len();
+
+    let mut best: Option<(f32, usize, usize)> = None;
+    for axis in 0..3 {
+        items.sort_unstable_by(|a, b| a.centroid[axis].partial_cmp(&b.centroid[axis]).unwrap());
+
+        let mut right_areas = vec![0.0; n];
+        let mut acc_box = items[n - 1].bound_box.clone();
+        for i in (1..n).rev() {
+            acc_box = AxisAlignedBoundingBox::surrounding_box(acc_box, items[i].bound_box.clone());
+            right_areas[i] = acc_box.surface_area();
+        }
+
+        let mut left_box = items[0].bound_box.clone();
+        for i in 1..n {
+            left_box = AxisAlignedBoundingBox::surrounding_box(left_box, items[i - 1].bound_box.clone());
+            let cost = TRAVERSAL_COST + INTERSECTION_COST *
+                (left_box.surface_area() * i as f32 + right_areas[i] * (n - i) as f32) / parent_area;
+            if best.map_or(true, |(best_cost, _, _)| cost < best_cost) {
+                best = Some((cost, axis, i));
+            }
+        }
+    }
+
+    let (cost, axis, mid_idx) = best?;
+    items.sort_unstable_by(|a, b| a.centroid[axis].partial_cmp(&b.centroid[axis]).unwrap());
+    return Some((cost, axis, mid_idx));
+}
+
+fn merge(a: Option<AxisAlignedBoundingBox>, b: Option<AxisAlignedBoundingBox>) -> Option<AxisAlignedBoundingBox> {
+    return match (a, b) {
+        (Some(a), Some(b)) => Some(AxisAlignedBoundingBox::surrounding_box(a, b)),
+        (a, None) => a,
+        (None, b) => b,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::color::Color;
+    use crate::materials::lambertian::Lambertian;
+    use crate::objects::sphere::Sphere;
+
+    #[test]
+    fn splits_between_separated_clusters() {
+        let material = Arc::new(Lambertian::new(Color::WHITE));
+        let objects: Vec<Arc<dyn Hittable>> = [-10.0, 9.0, -11.0, 10.0, -9.0, 11.0].iter().map(|&x| {
+            Arc::new(Sphere { center: Point { x, y: 0.0, z: 0.0 }, radius: 0.5, material: material.clone() }) as Arc<dyn Hittable>
+        }).collect();
+
+        for &method in [SplitMethod::Binned(8), SplitMethod::FullSweep].iter() {
+            let mut items = build_items(&objects, 0.0, 1.0);
+            let bound_box = enclosing_box(&items);
+            let (axis, mid_idx) = choose_split(&mut items, &bound_box, method).unwrap();
+            assert_eq!((axis, mid_idx), (0, 3));
+            assert!(items[..mid_idx].iter().all(|item| item.centroid.x < 0.0));
+            assert!(items[mid_idx..].iter().all(|item| item.centroid.x > 0.0));
+        }
+    }
+}
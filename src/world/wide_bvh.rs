@@ -0,0 +1,361 @@
+use std::mem::swap;
+use std::sync::Arc;
+
+use crate::geometry::bounding_volume::AxisAlignedBoundingBox;
+use crate::geometry::ray::Ray;
+use crate::objects::hittable::{HitRecord, Hittable};
+use crate::utils::INF_F32;
+use crate::world::flat_bvh::FlatBVH;
+use crate::world::sah::{BVHStats, SplitMethod};
+
+const WIDTH: usize = 4;
+
+// Child boxes stored plane by plane (min x, y, z, then max x, y, z) so one ray is tested against all of them at once.
+// A lane with `counts[i] == 0` points to another wide node, otherwise to `counts[i]` primitives starting at `children[i]`.
+// Unused lanes keep an inverted box that no ray can enter.
+struct WideNode {
+    bounds: [[f32; WIDTH]; 6],
+    children: [u32; WIDTH],
+    counts: [u32; WIDTH],
+}
+
+// Per-ray values shared by every box test, including which bounds plane is entered first on each axis.
+struct RayLanes {
+    origin: [f32; 3],
+    inv_direction: [f32; 3],
+    near: [usize; 3],
+    far: [usize; 3],
+}
+
+impl RayLanes {
+    fn new(ray: &Ray) -> Self {
+        let inv_direction = [1.0 / ray.direction.x, 1.0 / ray.direction.y, 1.0 / ray.direction.z];
+        let mut near = [0, 1, 2];
+        let mut far = [3, 4, 5];
+        for a in 0..3 {
+            if inv_direction[a] < 0.0 {
+                swap(&mut near[a], &mut far[a]);
+            }
+        }
+        return Self { origin: [ray.origin.x, ray.origin.y, ray.origin.z], inv_direction, near, far };
+    }
+}
+
+// Four-wide bounding volume hierarchy collapsed from a binary one.
+pub struct WideBVH {
+    nodes: Vec<WideNode>,
+    primitives: Vec<Arc<dyn Hittable>>,
+    bound_box: Option<AxisAlignedBoundingBox>,
+}
+
+impl WideBVH {
+    const STACK_SIZE: usize = 256;
+    const PACKET_SIZE: usize = 64;
+
+    // Builds a binary SAH tree over the objects and collapses it.
+    pub fn build(objects: &[Arc<dyn Hittable>], time0: f32, time1: f32, method: SplitMethod) -> (Self, BVHStats) {
+        let (bvh, stats) = FlatBVH::build(objects, time0, time1, method);
+        return (Self::from_binary(&bvh), stats);
+    }
+
+    // Each wide node takes a binary node's children, then keeps opening the largest interior child until it has four.
+    pub fn from_binary(bvh: &FlatBVH) -> Self {
+        let mut wide = Self {
+            nodes: Vec::with_capacity(bvh.nodes.len() / 2 + 1),
+            primitives: bvh.primitives.clone(),
+            bound_box: bvh.nodes.first().map(|node| node.bound_box.clone()),
+        };
+
+        if let Some(root) = bvh.nodes.first() {
+            if root.count > 0 {
+                wide.nodes.push(Self::empty_node());
+                wide.set_lane(0, 0, &root.bound_box, root.offset, root.count);
+            } else {
+                wide.collapse(bvh, 0);
+            }
+        }
+        return wide;
+    }
+
+    fn collapse(&mut self, bvh: &FlatBVH, binary_idx: usize) -> u32 {
+        let node_idx = self.nodes.len();
+        self.nodes.push(Self::empty_node());
+
+        let mut lanes: Vec<usize> = vec![binary_idx + 1, bvh.nodes[binary_idx].offset as usize];
+        while lanes.len() < WIDTH {
+            let opt_largest = lanes.iter().enumerate()
+                .filter(|(_, &n)| bvh.nodes[n].count == 0)
+                .max_by(|(_, &a), (_, &b)| {
+                    bvh.nodes[a].bound_box.surface_area().partial_cmp(&bvh.nodes[b].bound_box.surface_area()).unwrap()
+                })
+                .map(|(i, _)| i);
+
+            match opt_largest {
+                Some(i) => {
+                    let opened = lanes.swap_remove(i);
+                    lanes.push(opened + 1);
+                    lanes.push(bvh.nodes[opened].offset as usize);
+                }
+                None => break,
+            }
+        }
+
+        for (lane, &child) in lanes.iter().enumerate() {
+            let child_node = &bvh.nodes[child];
+            let (target, count) = if child_node.count > 0 {
+                (child_node.offset, child_node.count)
+            } else {
+                (self.collapse(bvh, child), 0)
+            };
+            self.set_lane(node_idx, lane, &child_node.bound_box, target, count);
+        }
+        return node_idx as u32;
+    }
+
+    fn empty_node() -> WideNode {
+        return WideNode {
+            bounds: [[INF_F32; WIDTH], [INF_F32; WIDTH], [INF_F32; WIDTH], [-INF_F32; WIDTH], [-INF_F32; WIDTH], [-INF_F32; WIDTH]],
+            children: [0; WIDTH],
+            counts: [0; WIDTH],
+        };
+    }
+
+    fn set_lane(&mut self, node_idx: usize, lane: usize, bound_box: &AxisAlignedBoundingBox, target: u32, count: u32) {
+        let node = &mut self.nodes[node_idx];
+        for a in 0..3 {
+            node.bounds[a][lane] = bound_box.minimum[a];
+            node.bounds[a + 3][lane] = bound_box.maximum[a];
+        }
+        node.children[lane] = target;
+        node.counts[lane] = count;
+    }
+
+    // Returns a bit mask of the lanes whose box the ray enters within [t_min, t_max], and each lane's entry distance.
+    #[cfg(target_arch = "x86_64")]
+    fn intersect_lanes(node: &WideNode, ray: &RayLanes, t_min: f32, t_max: f32) -> (u32, [f32; WIDTH]) {
+        use std::arch::x86_64::*;
+
+        // SSE is part of the x86_64 baseline. The new value goes first in min/max so NaN slabs are ignored,
+        // matching the scalar test.
+        unsafe {
+            let mut t_near = _mm_set1_ps(t_min);
+            let mut t_far = _mm_set1_ps(t_max);
+            for a in 0..3 {
+                let origin = _mm_set1_ps(ray.origin[a]);
+                let inv_direction = _mm_set1_ps(ray.inv_direction[a]);
+                let near = _mm_mul_ps(_mm_sub_ps(_mm_loadu_ps(node.bounds[ray.near[a]].as_ptr()), origin), inv_direction);
+                let far = _mm_mul_ps(_mm_sub_ps(_mm_loadu_ps(node.bounds[ray.far[a]].as_ptr()), origin), inv_direction);
+                t_near = _mm_max_ps(near, t_near);
+                t_far = _mm_min_ps(far, t_far);
+            }
+
+            let mask = _mm_movemask_ps(_mm_cmplt_ps(t_near, t_far)) as u32;
+            let mut entry = [0.0; WIDTH];
+            _mm_storeu_ps(entry.as_mut_ptr(), t_near);
+            return (mask, entry);
+        }
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    fn intersect_lanes(node: &WideNode, ray: &RayLanes, t_min: f32, t_max: f32) -> (u32, [f32; WIDTH]) {
+        let mut t_near = [t_min; WIDTH];
+        let mut t_far = [t_max; WIDTH];
+        for a in 0..3 {
+            for lane in 0..WIDTH {
+                let near = (node.bounds[ray.near[a]][lane] - ray.origin[a]) * ray.inv_direction[a];
+                let far = (node.bounds[ray.far[a]][lane] - ray.origin[a]) * ray.inv_direction[a];
+                if near > t_near[lane] { t_near[lane] = near; }
+                if far < t_far[lane] { t_far[lane] = far; }
+            }
+        }
+
+        let mut mask = 0;
+        for lane in 0..WIDTH {
+            if t_near[lane] < t_far[lane] {
+                mask |= 1 << lane;
+            }
+        }
+        return (mask, t_near);
+    }
+
+    // Lanes set in `mask`, nearest entry first.
+    fn sorted_lanes(mask: u32, entry: &[f32; WIDTH]) -> ([usize; WIDTH], usize) {
+        let mut order = [0; WIDTH];
+        let mut count = 0;
+        for lane in 0..WIDTH {
+            if mask & (1 << lane) == 0 {
+                continue;
+            }
+            let mut i = count;
+            while i > 0 && entry[order[i - 1]] > entry[lane] {
+                order[i] = order[i - 1];
+                i -= 1;
+            }
+            order[i] = lane;
+            count += 1;
+        }
+        return (order, count);
+    }
+
+    fn hit_leaf(&self, node: &WideNode, lane: usize, ray: Ray, t_min: f32, closest: &mut f32, hit_rec: &mut Option<HitRecord>) {
+        let start = node.children[lane] as usize;
+        for obj in self.primitives[start..start + node.counts[lane] as usize].iter() {
+            if let Some(temp_rec) = obj.hit(ray, t_min, *closest) {
+                *closest = temp_rec.t;
+                *hit_rec = Option::from(temp_rec);
+            }
+        }
+    }
+
+    // Masked packet traversal: the rays walk the tree together and every stack entry carries the set of rays
+    // that entered that node, so each node is fetched once for the whole packet.
+    fn hit_chunk(&self, rays: &[Ray], t_min: f32, t_max: f32) -> Vec<Option<HitRecord>> {
+        let mut hit_recs: Vec<Option<HitRecord>> = rays.iter().map(|_| Option::None).collect();
+        if self.nodes.is_empty() {
+            return hit_recs;
+        }
+
+        let lanes: Vec<RayLanes> = rays.iter().map(RayLanes::new).collect();
+        let mut closest = vec![t_max; rays.len()];
+        let all_rays = if rays.len() == 64 { u64::MAX } else { (1u64 << rays.len()) - 1 };
+
+        let mut stack = [(0u32, 0u64); Self::STACK_SIZE];
+        stack[0] = (0, all_rays);
+        let mut stack_size = 1;
+
+        while stack_size > 0 {
+            stack_size -= 1;
+            let (node_idx, active) = stack[stack_size];
+            let node = &self.nodes[node_idx as usize];
+
+            let mut child_rays = [0u64; WIDTH];
+            let mut child_entry = [INF_F32; WIDTH];
+            let mut remaining = active;
+            while remaining != 0 {
+                let r = remaining.trailing_zeros() as usize;
+                remaining &= remaining - 1;
+
+                let (mask, entry) = Self::intersect_lanes(node, &lanes[r], t_min, closest[r]);
+                for lane in 0..WIDTH {
+                    if mask & (1 << lane) != 0 {
+                        child_rays[lane] |= 1 << r;
+                        child_entry[lane] = child_entry[lane].min(entry[lane]);
+                    }
+                }
+            }
+
+            let entered = (0..WIDTH).filter(|&lane| child_rays[lane] != 0).fold(0, |mask, lane| mask | (1 << lane));
+            let (order, count) = Self::sorted_lanes(entered, &child_entry);
+            for &lane in order[..count].iter().filter(|&&lane| node.counts[lane] > 0) {
+                let mut remaining = child_rays[lane];
+                while remaining != 0 {
+                    let r = remaining.trailing_zeros() as usize;
+                    remaining &= remaining - 1;
+                    self.hit_leaf(node, lane, rays[r], t_min, &mut closest[r], &mut hit_recs[r]);
+                }
+            }
+            for &lane in order[..count].iter().rev().filter(|&&lane| node.counts[lane] == 0) {
+                stack[stack_size] = (node.children[lane], child_rays[lane]);
+                stack_size += 1;
+            }
+        }
+
+        return hit_recs;
+    }
+}
+
+impl Hittable for WideBVH {
+    fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        if self.nodes.is_empty() {
+            return Option::None;
+        }
+
+        let lanes = RayLanes::new(&ray);
+        let mut hit_rec: Option<HitRecord> = Option::None;
+        let mut closest = t_max;
+        let mut stack = [0u32; Self::STACK_SIZE];
+        let mut stack_size = 1;
+
+        while stack_size > 0 {
+            stack_size -= 1;
+            let node = &self.nodes[stack[stack_size] as usize];
+            let (mask, entry) = Self::intersect_lanes(node, &lanes, t_min, closest);
+            let (order, count) = Self::sorted_lanes(mask, &entry);
+
+            // Leaves are intersected straight away, nearest first, which tightens `closest` before
+            // interior children are pushed farthest first so the nearest is popped next.
+            for &lane in order[..count].iter().filter(|&&lane| node.counts[lane] > 0) {
+                self.hit_leaf(node, lane, ray, t_min, &mut closest, &mut hit_rec);
+            }
+            for &lane in order[..count].iter().rev().filter(|&&lane| node.counts[lane] == 0) {
+                if entry[lane] < closest {
+                    stack[stack_size] = node.children[lane];
+                    stack_size += 1;
+                }
+            }
+        }
+
+        return hit_rec;
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AxisAlignedBoundingBox> {
+        return self.bound_box.clone();
+    }
+
+    fn hit_packet(&self, rays: &[Ray], t_min: f32, t_max: f32) -> Vec<Option<HitRecord>> {
+        let mut hit_recs: Vec<Option<HitRecord>> = Vec::with_capacity(rays.len());
+        for chunk in rays.chunks(Self::PACKET_SIZE) {
+            hit_recs.extend(self.hit_chunk(chunk, t_min, t_max));
+        }
+        return hit_recs;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::color::Color;
+    use crate::geometry::vector::Point;
+    use crate::materials::lambertian::Lambertian;
+    use crate::objects::sphere::Sphere;
+    use crate::utils::random_f32_range;
+    use crate::world::bvh_node::BVHNode;
+
+    fn random_point(extent: f32) -> Point {
+        return Point { x: random_f32_range(-extent, extent), y: random_f32_range(-extent, extent), z: random_f32_range(-extent, extent) };
+    }
+
+    fn assert_same_hit(expected: Option<HitRecord>, actual: Option<HitRecord>) {
+        match (expected.map(|hit_rec| hit_rec.t), actual.map(|hit_rec| hit_rec.t)) {
+            (Some(e), Some(a)) => assert!((e - a).abs() < 1e-4, "{} != {}", e, a),
+            (None, None) => {}
+            (e, a) => panic!("reference {:?}, wide {:?}", e, a),
+        }
+    }
+
+    #[test]
+    fn single_rays_and_packets_match_the_reference_tree() {
+        let material = Arc::new(Lambertian::new(Color::WHITE));
+        let objects: Vec<Arc<dyn Hittable>> = (0..500).map(|_| {
+            Arc::new(Sphere { center: random_point(20.0), radius: random_f32_range(0.2, 1.5), material: material.clone() }) as Arc<dyn Hittable>
+        }).collect();
+
+        let reference = BVHNode::create_tree(&mut objects.clone(), 0.0, 1.0);
+        let (wide, _) = WideBVH::build(&objects, 0.0, 1.0, SplitMethod::Binned(12));
+        for _ in 0..2000 {
+            let origin = random_point(30.0);
+            let ray = Ray { origin, direction: (random_point(10.0) - origin).direction(), time: 0.0, wavelengths: None, absorption: None };
+            assert_same_hit(reference.hit(ray, 0.001, INF_F32), wide.hit(ray, 0.001, INF_F32));
+        }
+
+        // A pinhole camera's rays over a 16 by 16 grid of pixels.
+        let origin = Point { x: 0.0, y: 0.0, z: -40.0 };
+        let rays: Vec<Ray> = (0..256).map(|i| {
+            let target = Point { x: (i % 16) as f32 * 2.5 - 20.0, y: (i / 16) as f32 * 2.5 - 20.0, z: 0.0 };
+            Ray { origin, direction: (target - origin).direction(), time: 0.0, wavelengths: None, absorption: None }
+        }).collect();
+        for (ray, hit_rec) in rays.iter().zip(wide.hit_packet(&rays, 0.001, INF_F32)) {
+            assert_same_hit(reference.hit(*ray, 0.001, INF_F32), hit_rec);
+        }
+    }
+}